@@ -0,0 +1,75 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// `examples.source_kind` was created as text while it holds an `NGLDataKind`, which is stored
+/// as an integer, so examples that had one couldn't be read back.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        retype(
+            manager,
+            integer_null(Example::SourceKind),
+            Expr::col(Example::SourceKindOld).cast_as(Alias::new("integer")),
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        retype(
+            manager,
+            string_null(Example::SourceKind),
+            Expr::col(Example::SourceKindOld).into(),
+        )
+        .await
+    }
+}
+
+/// Replaces `source_kind` with `column`, filled from the old values through `value`. SQLite
+/// can't change a column's type, so the old one is renamed, copied over and dropped.
+async fn retype(
+    manager: &SchemaManager<'_>,
+    mut column: ColumnDef,
+    value: SimpleExpr,
+) -> Result<(), DbErr> {
+    manager
+        .alter_table(
+            Table::alter()
+                .table(Alias::new("examples"))
+                .rename_column(Example::SourceKind, Example::SourceKindOld)
+                .to_owned(),
+        )
+        .await?;
+    manager
+        .alter_table(
+            Table::alter()
+                .table(Alias::new("examples"))
+                .add_column(&mut column)
+                .to_owned(),
+        )
+        .await?;
+    manager
+        .exec_stmt(
+            Query::update()
+                .table(Alias::new("examples"))
+                .value(Example::SourceKind, value)
+                .and_where(Expr::col(Example::SourceKindOld).is_not_null())
+                .to_owned(),
+        )
+        .await?;
+    manager
+        .alter_table(
+            Table::alter()
+                .table(Alias::new("examples"))
+                .drop_column(Example::SourceKindOld)
+                .to_owned(),
+        )
+        .await
+}
+
+#[derive(DeriveIden)]
+enum Example {
+    SourceKind,
+    SourceKindOld,
+}
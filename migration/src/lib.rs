@@ -1,12 +1,22 @@
 pub use sea_orm_migration::prelude::*;
 
+mod example_source_kind;
 mod init;
+mod option_source_url;
+mod sync_locks;
+mod sync_runs;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(init::Migration)]
+        vec![
+            Box::new(init::Migration),
+            Box::new(sync_runs::Migration),
+            Box::new(sync_locks::Migration),
+            Box::new(example_source_kind::Migration),
+            Box::new(option_source_url::Migration),
+        ]
     }
 }
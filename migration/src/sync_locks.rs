@@ -0,0 +1,37 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("sync_locks"))
+                    .if_not_exists()
+                    .col(string(SyncLock::ProviderName).primary_key())
+                    .col(string(SyncLock::Holder))
+                    .col(big_integer(SyncLock::ExpiresAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Alias::new("sync_locks")).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(iden = "sync_locks")]
+enum SyncLock {
+    #[allow(unused)]
+    Table,
+    ProviderName,
+    Holder,
+    ExpiresAt,
+}
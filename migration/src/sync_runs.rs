@@ -0,0 +1,76 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("sync_runs"))
+                    .if_not_exists()
+                    .col(pk_auto(SyncRun::Id))
+                    .col(string(SyncRun::ProviderName))
+                    .col(integer(SyncRun::Kind))
+                    .col(timestamp_with_time_zone(SyncRun::StartedAt))
+                    .col(timestamp_with_time_zone(SyncRun::FinishedAt))
+                    .col(big_integer(SyncRun::DurationMs))
+                    .col(big_integer(SyncRun::Rows))
+                    .col(string_null(SyncRun::SourceRevision))
+                    .col(string(SyncRun::Outcome))
+                    .col(string_null(SyncRun::Error))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-sync_run-provider")
+                            .from(Alias::new("sync_runs"), SyncRun::ProviderName)
+                            .to(Alias::new("providers"), Provider::Name),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-sync_runs-provider_name")
+                    .table(Alias::new("sync_runs"))
+                    .col(SyncRun::ProviderName)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Alias::new("sync_runs")).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(iden = "providers")]
+enum Provider {
+    #[allow(unused)]
+    Table,
+    Name,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(iden = "sync_runs")]
+enum SyncRun {
+    #[allow(unused)]
+    Table,
+    Id,
+    ProviderName,
+    Kind,
+    StartedAt,
+    FinishedAt,
+    DurationMs,
+    Rows,
+    SourceRevision,
+    Outcome,
+    Error,
+}
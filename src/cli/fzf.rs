@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use anyhow::bail;
+use console::Term;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    cli::{PreviewArgs, ProgressFormat, Shell, progress},
+    daemon,
+    db::{self, services::fetch},
+    error::NglError,
+    query,
+    registry::ProviderRegistry,
+    render,
+    schema::{NGLDataKind, NGLRequest, NGLSearchHit},
+};
+
+/// The picker the shell snippets bind to a key. fzf runs a new search on every keystroke
+/// and previews the highlighted line; the selected name is inserted at the cursor.
+const FZF_COMMAND: &str = "fzf --disabled --delimiter '\\t' --with-nth 1,2,3 \
+    --prompt 'ngl> ' \
+    --bind 'change:reload:ngl --output fzf -- {q} 2>/dev/null || true' \
+    --preview 'ngl preview --kind {1} {4}' --preview-window 'right,60%,wrap' \
+    | cut -f3";
+
+/// `ngl --output fzf`: one `kind<TAB>provider<TAB>name<TAB>id` line per hit, through the
+/// daemon when `socket` is given.
+pub async fn search(
+    database_url: &str,
+    socket: Option<&Path>,
+    request: NGLRequest,
+    registry: ProviderRegistry,
+    progress_format: ProgressFormat,
+) -> anyhow::Result<()> {
+    // An empty query is what fzf sends before anything is typed.
+    if request
+        .search_term
+        .as_deref()
+        .is_none_or(|term| term.trim().is_empty())
+    {
+        return Ok(());
+    }
+
+    if let Some(socket) = socket {
+        match daemon::search(socket, &request).await {
+            Ok(hits) => {
+                print_hits(&hits);
+                return Ok(());
+            }
+            // Most likely a socket left behind by a daemon that's gone, answer it ourselves.
+            Err(NglError::Io(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let db = db::establish_connection(database_url).await?;
+    let (status, renderer) = progress::spawn(progress_format);
+    let hits = query::hits(&db, request, &registry, &status).await;
+    drop(status);
+    renderer.await?;
+    print_hits(&hits?);
+    Ok(())
+}
+
+fn print_hits(hits: &[NGLSearchHit]) {
+    for hit in hits {
+        println!(
+            "{}\t{}\t{}\t{}",
+            kind_name(hit.kind),
+            hit.provider_name,
+            hit.name.replace('\t', " "),
+            hit.id
+        );
+    }
+}
+
+/// Matches the values `--kind` takes.
+fn kind_name(kind: NGLDataKind) -> String {
+    format!("{:?}", kind).to_lowercase()
+}
+
+/// `ngl preview`: one entry rendered for a terminal, or as markdown when piped somewhere
+/// other than fzf's preview window.
+pub async fn preview(db: &DatabaseConnection, args: PreviewArgs) -> anyhow::Result<()> {
+    let kind: NGLDataKind = args.kind.into();
+    let Some(data) = fetch(db, kind, args.id).await? else {
+        bail!("no {} with id {}", kind_name(kind), args.id);
+    };
+    let markdown = render::markdown(&data);
+
+    let fzf_columns = std::env::var("FZF_PREVIEW_COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok());
+    let term = Term::stdout();
+    let width = match fzf_columns {
+        Some(columns) => {
+            console::set_colors_enabled(true);
+            columns
+        }
+        None if term.is_term() => term.size().1 as usize,
+        None => {
+            println!("{}", markdown);
+            return Ok(());
+        }
+    };
+    for line in render::terminal(&markdown, width) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// `ngl shell-init`: a Ctrl-X Ctrl-N binding that opens the picker and inserts the selection.
+pub fn shell_init(shell: Shell) {
+    let snippet = match shell {
+        Shell::Bash => format!(
+            r#"# ngl: Ctrl-X Ctrl-N searches Nix docs and inserts the selected name
+__ngl_fzf() {{
+  local selected
+  selected=$({FZF_COMMAND})
+  READLINE_LINE="${{READLINE_LINE:0:$READLINE_POINT}}$selected${{READLINE_LINE:$READLINE_POINT}}"
+  READLINE_POINT=$((READLINE_POINT + ${{#selected}}))
+}}
+bind -x '"\C-x\C-n": __ngl_fzf'"#
+        ),
+        Shell::Zsh => format!(
+            r#"# ngl: Ctrl-X Ctrl-N searches Nix docs and inserts the selected name
+__ngl_fzf() {{
+  local selected
+  selected=$({FZF_COMMAND})
+  LBUFFER+=$selected
+  zle reset-prompt
+}}
+zle -N __ngl_fzf
+bindkey '^X^N' __ngl_fzf"#
+        ),
+        Shell::Fish => format!(
+            r#"# ngl: Ctrl-X Ctrl-N searches Nix docs and inserts the selected name
+function __ngl_fzf
+  set -l selected ({FZF_COMMAND})
+  commandline -i -- $selected
+  commandline -f repaint
+end
+bind \cx\cn __ngl_fzf"#
+        ),
+    };
+    println!("{}", snippet);
+}
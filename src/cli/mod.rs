@@ -1,8 +1,18 @@
+pub mod fzf;
+pub mod output;
 pub mod progress;
+pub mod status;
+pub mod sync;
+pub mod tui;
 
-use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
 
-use crate::schema::{NGLDataKind, NGLRequest};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    error::NglError,
+    schema::{NGLDataKind, NGLLockPolicy, NGLRequest, NGLSyncMode},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum Kind {
@@ -14,6 +24,58 @@ pub enum Kind {
     Type,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IfLocked {
+    /// Wait for the other process (up to five minutes), then sync whatever is still expired
+    Wait,
+    /// Leave the provider alone and use its existing data
+    Skip,
+}
+
+impl From<IfLocked> for NGLLockPolicy {
+    fn from(policy: IfLocked) -> Self {
+        match policy {
+            IfLocked::Wait => NGLLockPolicy::Wait,
+            IfLocked::Skip => NGLLockPolicy::Skip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+    /// Spinners on a terminal, plain lines otherwise
+    Auto,
+    /// One JSON object per status event on stderr
+    Json,
+    /// No progress output
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Pretty printed JSON responses
+    Json,
+    /// One JSON object per hit, with its provider
+    Jsonl,
+    /// Aligned kind, provider, name and short description per hit
+    Table,
+    /// A markdown document per hit
+    Markdown,
+    /// Text without markup
+    Plain,
+    /// Styled for the terminal, with highlighted Nix examples
+    Pretty,
+    /// One `kind<TAB>provider<TAB>name<TAB>id` line per hit, for fzf, rofi or dmenu
+    Fzf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
 impl From<Kind> for NGLDataKind {
     fn from(kind: Kind) -> Self {
         match kind {
@@ -27,10 +89,20 @@ impl From<Kind> for NGLDataKind {
     }
 }
 
+/// Parses a value from the config file the way clap parses the flag of the same name, so
+/// `"output": "table"` means the same as `--output table`.
+pub fn from_config<T: ValueEnum>(setting: &str, value: &str) -> Result<T, NglError> {
+    T::from_str(value, true).map_err(|e| NglError::Config(format!("{setting}: {e}")))
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "ngl")]
 #[command(about = "Nix Global Lookup", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     pub search_term: Option<String>,
 
     #[arg(short, long, value_delimiter = ',')]
@@ -39,8 +111,67 @@ pub struct Cli {
     #[arg(short, long, value_delimiter = ',')]
     pub kinds: Option<Vec<Kind>>,
 
-    #[arg(long, env = "DATABASE_URL")]
+    /// Defaults to the config's database, or ngl.db in $XDG_DATA_HOME/ngl
+    #[arg(long, env = "DATABASE_URL", global = true)]
     pub database_url: Option<String>,
+
+    /// Config file to use instead of $XDG_CONFIG_HOME/ngl/config.json
+    #[arg(long, env = "NGL_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Named profile from the config file, e.g. work or personal
+    #[arg(long, env = "NGL_PROFILE", global = true)]
+    pub profile: Option<String>,
+
+    /// Answer from the existing database without syncing any provider
+    #[arg(
+        long,
+        env = "NGL_OFFLINE",
+        value_parser = clap::builder::FalseyValueParser::new(),
+        conflicts_with_all = ["refresh", "background_refresh"]
+    )]
+    pub offline: bool,
+
+    /// Sync the requested providers even if their cache has not expired
+    #[arg(long, conflicts_with = "background_refresh")]
+    pub refresh: bool,
+
+    /// Answer from expired data right away and refresh it in a background process
+    #[arg(long)]
+    pub background_refresh: bool,
+
+    /// What to do when another process is already syncing a provider
+    #[arg(
+        long,
+        value_enum,
+        env = "NGL_IF_LOCKED",
+        default_value = "wait",
+        global = true
+    )]
+    pub if_locked: IfLocked,
+
+    /// How to report sync progress on stderr
+    #[arg(
+        long,
+        value_enum,
+        env = "NGL_PROGRESS",
+        default_value = "auto",
+        global = true
+    )]
+    pub progress: ProgressFormat,
+
+    /// Socket of `ngl daemon`, queries go through it when it exists. Without it, queries that
+    /// pick a database, config or profile skip the daemon
+    #[arg(long, env = "NGL_SOCKET", global = true)]
+    pub socket: Option<PathBuf>,
+
+    /// Answer the query in this process even if a daemon is running
+    #[arg(long)]
+    pub no_daemon: bool,
+
+    /// How to print query results, json unless the config says otherwise
+    #[arg(long, value_enum, env = "NGL_OUTPUT")]
+    pub output: Option<OutputFormat>,
 }
 
 impl From<Cli> for NGLRequest {
@@ -49,6 +180,136 @@ impl From<Cli> for NGLRequest {
             search_term: cli.search_term,
             providers: cli.providers,
             kinds: cli.kinds.map(|k| k.into_iter().map(Into::into).collect()),
+            sync_mode: if cli.offline {
+                NGLSyncMode::Offline
+            } else if cli.refresh {
+                NGLSyncMode::Refresh
+            } else if cli.background_refresh {
+                NGLSyncMode::StaleWhileRevalidate
+            } else {
+                crate::utils::default_sync_mode()
+            },
+            on_locked: cli.if_locked.into(),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Sync providers without running a query
+    Sync(SyncArgs),
+    /// Show the outcome of recent syncs
+    Status(StatusArgs),
+    /// Keep the database and provider config loaded and answer queries over a Unix socket
+    Daemon,
+    /// Language server on stdio with hover, completion and go to source for options, functions
+    /// and packages, meant to run next to nil or nixd
+    Lsp,
+    /// Render a single entry, e.g. from fzf's --preview with a line from `--output fzf`
+    Preview(PreviewArgs),
+    /// Print a shell snippet binding Ctrl-X Ctrl-N to an fzf picker over ngl
+    ShellInit(ShellInitArgs),
+    /// Full screen search with filters and a preview of the selected entry
+    Tui(TuiArgs),
+    /// Model Context Protocol server on stdio with search, lookup and guide tools
+    Mcp,
+    /// Serve search, lookups, guides, providers and syncing over HTTP
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct PreviewArgs {
+    /// Id of the entry, the last field of an `--output fzf` line
+    pub id: i32,
+
+    /// Kind of the entry, the first field of an `--output fzf` line
+    #[arg(short, long, value_enum)]
+    pub kind: Kind,
+}
+
+#[derive(Args, Debug)]
+pub struct ShellInitArgs {
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Args, Debug)]
+pub struct TuiArgs {
+    /// Search to start with
+    pub query: Option<String>,
+
+    /// Providers enabled at start, defaults to every registered provider
+    #[arg(short, long, value_delimiter = ',')]
+    pub providers: Option<Vec<String>>,
+
+    /// Kinds enabled at start, defaults to every kind
+    #[arg(short, long, value_delimiter = ',')]
+    pub kinds: Option<Vec<Kind>>,
+}
+
+#[cfg(feature = "serve")]
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, env = "NGL_BIND", default_value = "127.0.0.1:7700")]
+    pub bind: std::net::SocketAddr,
+}
+
+#[derive(Args, Debug)]
+pub struct SyncArgs {
+    /// Providers to sync, defaults to every registered provider
+    #[arg(short, long, value_delimiter = ',')]
+    pub providers: Option<Vec<String>>,
+
+    /// Kinds to sync, defaults to every kind
+    #[arg(short, long, value_delimiter = ',')]
+    pub kinds: Option<Vec<Kind>>,
+
+    /// Sync even if the cache has not expired
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Only print what would be synced
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl From<SyncArgs> for NGLRequest {
+    fn from(args: SyncArgs) -> Self {
+        NGLRequest {
+            search_term: None,
+            providers: args.providers,
+            kinds: Some(
+                args.kinds
+                    .map(|k| k.into_iter().map(Into::into).collect())
+                    .unwrap_or_else(NGLDataKind::all),
+            ),
+            sync_mode: if args.force {
+                NGLSyncMode::Refresh
+            } else {
+                NGLSyncMode::Auto
+            },
+            on_locked: NGLLockPolicy::default(),
         }
     }
 }
+
+#[derive(Args, Debug)]
+pub struct StatusArgs {
+    /// Providers to report on, defaults to all of them
+    #[arg(short, long, value_delimiter = ',')]
+    pub providers: Option<Vec<String>>,
+
+    /// List every recorded run instead of only the latest per provider and kind
+    #[arg(long)]
+    pub history: bool,
+
+    /// Maximum number of runs to list with --history
+    #[arg(long, default_value_t = 50)]
+    pub limit: u64,
+
+    /// Print the runs as JSON
+    #[arg(long)]
+    pub json: bool,
+}
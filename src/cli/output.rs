@@ -0,0 +1,115 @@
+use console::{Term, measure_text_width, pad_str, style};
+
+use crate::{
+    cli::OutputFormat,
+    render,
+    schema::{NGLData, NGLResponse},
+};
+
+/// Width used for tables and pretty output when stdout isn't a terminal.
+const DEFAULT_WIDTH: usize = 100;
+
+/// Prints query responses in `format`. Fzf lines are printed from the search index instead,
+/// see [`crate::cli::fzf`].
+pub fn print(responses: &[NGLResponse], format: OutputFormat) -> anyhow::Result<()> {
+    let hits = || {
+        responses.iter().flat_map(|response| {
+            response
+                .matches
+                .iter()
+                .map(move |data| (response.provider_name.as_str(), data))
+        })
+    };
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(responses)?),
+        OutputFormat::Jsonl => {
+            for (provider_name, data) in hits() {
+                let line = serde_json::json!({ "provider_name": provider_name, "data": data.data });
+                println!("{}", serde_json::to_string(&line)?);
+            }
+        }
+        OutputFormat::Table => table(hits().collect()),
+        OutputFormat::Markdown => {
+            for response in responses {
+                println!("# {}\n", response.provider_name);
+                for data in &response.matches {
+                    println!("{}\n", render::markdown(data));
+                }
+            }
+        }
+        OutputFormat::Plain => {
+            for (_, data) in hits() {
+                println!("{}\n", render::plain(&render::markdown(data)));
+            }
+        }
+        OutputFormat::Pretty => {
+            let width = width();
+            for (provider_name, data) in hits() {
+                println!(
+                    "{}",
+                    style(format!("{} · {}", kind_name(data), provider_name)).dim()
+                );
+                for line in render::terminal(&render::markdown(data), width) {
+                    println!("{}", line);
+                }
+                println!();
+            }
+        }
+        OutputFormat::Fzf => unreachable!("fzf lines come from the search index"),
+    }
+    Ok(())
+}
+
+/// Kind, provider, name and the first line of the description, one row per hit.
+fn table(hits: Vec<(&str, &NGLData)>) {
+    let header = ["KIND", "PROVIDER", "NAME", "DESCRIPTION"];
+    let rows: Vec<[String; 4]> = hits
+        .iter()
+        .map(|(provider_name, data)| {
+            [
+                kind_name(data),
+                provider_name.to_string(),
+                render::title(data),
+                render::summary(data),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(measure_text_width(cell));
+        }
+    }
+    // Long option names shouldn't push the descriptions off screen.
+    widths[2] = widths[2].min(60);
+    let total = width();
+
+    let line = |cells: [&str; 4]| {
+        let mut line = String::new();
+        for (cell, width) in cells.iter().zip(widths).take(3) {
+            let cell = render::fit(cell, width, "…");
+            line.push_str(&pad_str(&cell, width, console::Alignment::Left, None));
+            line.push_str("  ");
+        }
+        let used = measure_text_width(&line);
+        line.push_str(&render::fit(cells[3], total.saturating_sub(used), "…"));
+        line.trim_end().to_string()
+    };
+
+    println!("{}", style(line(header)).bold());
+    for row in &rows {
+        println!("{}", line([&row[0], &row[1], &row[2], &row[3]]));
+    }
+}
+
+fn kind_name(data: &NGLData) -> String {
+    format!("{:?}", data.data.kind()).to_lowercase()
+}
+
+fn width() -> usize {
+    Term::stdout()
+        .size_checked()
+        .map_or(DEFAULT_WIDTH, |(_, columns)| columns as usize)
+}
@@ -1,9 +1,44 @@
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
 use std::collections::HashMap;
-use std::io::IsTerminal;
-use tokio::sync::broadcast;
+use std::io::{IsTerminal, Write};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
 
-use crate::providers::channel::{CountsSnapShot, StatusEvent};
+use crate::{
+    cli::ProgressFormat,
+    providers::channel::{CountsSnapShot, StatusEvent, SyncPhase, status_channel},
+    schema::NGLDataKind,
+};
+
+/// Creates a status sender with `format`'s renderer subscribed to it.
+/// The renderer finishes once every clone of the sender is dropped.
+pub fn spawn(format: ProgressFormat) -> (broadcast::Sender<StatusEvent>, JoinHandle<()>) {
+    let status = status_channel();
+    let handle = match format {
+        ProgressFormat::Auto => tokio::spawn(run_progress_ui(status.subscribe())),
+        ProgressFormat::Json => tokio::spawn(run_json_lines(status.subscribe())),
+        ProgressFormat::None => tokio::spawn(async {}),
+    };
+    (status, handle)
+}
+
+/// Writes every event to stderr as a line of JSON, for GUIs and editor plugins.
+pub async fn run_json_lines(mut rx: broadcast::Receiver<StatusEvent>) {
+    loop {
+        match rx.recv().await {
+            Ok(event) => {
+                if let Ok(line) = serde_json::to_string(&event) {
+                    let mut stderr = std::io::stderr().lock();
+                    let _ = writeln!(stderr, "{}", line);
+                }
+            }
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct SyncProgress {
@@ -11,6 +46,12 @@ pub struct SyncProgress {
     is_tty: bool,
 }
 
+impl Default for SyncProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SyncProgress {
     pub fn new() -> Self {
         let is_tty = std::io::stderr().is_terminal();
@@ -27,11 +68,7 @@ impl SyncProgress {
 
         let pb = self.mp.add(ProgressBar::new_spinner());
 
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template("{spinner:.green} {prefix}: {msg}")
-                .unwrap(),
-        );
+        pb.set_style(spinner_style());
 
         pb.set_prefix(provider.to_string());
         pb.set_message("starting...");
@@ -39,49 +76,171 @@ impl SyncProgress {
 
         pb
     }
+
+    fn bar<'a>(
+        &self,
+        bars: &'a mut HashMap<String, ProviderBar>,
+        provider: &str,
+    ) -> &'a mut ProviderBar {
+        bars.entry(provider.to_string())
+            .or_insert_with(|| ProviderBar::new(self.add_provider(provider)))
+    }
+}
+
+/// Where a provider's bar is at, so later events can be rendered against earlier ones.
+struct ProviderBar {
+    pb: ProgressBar,
+    phase: Option<SyncPhase>,
+    totals: HashMap<NGLDataKind, usize>,
+    counts: CountsSnapShot,
+}
+
+impl ProviderBar {
+    fn new(pb: ProgressBar) -> Self {
+        Self {
+            pb,
+            phase: None,
+            totals: HashMap::new(),
+            counts: CountsSnapShot::default(),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.phase {
+            Some(phase) if self.totals.is_empty() => {
+                format!("{}, {}", phase_label(phase), format_counts(self.counts))
+            }
+            Some(phase) => phase_label(phase).to_string(),
+            None => format_counts(self.counts),
+        }
+    }
+
+    /// Once totals are known the spinner turns into an item bar with an ETA.
+    fn show_items(&self) {
+        if self.totals.is_empty() {
+            self.pb.set_message(self.label());
+            return;
+        }
+        let done: usize = self.totals.keys().map(|kind| self.counts.get(*kind)).sum();
+        self.pb
+            .set_length(self.totals.values().sum::<usize>() as u64);
+        self.pb.set_position(done as u64);
+        self.pb.set_message(self.label());
+    }
 }
 
 pub async fn run_progress_ui(mut rx: broadcast::Receiver<StatusEvent>) {
     let progress = SyncProgress::new();
 
-    let mut bars: HashMap<String, ProgressBar> = HashMap::new();
+    let mut bars: HashMap<String, ProviderBar> = HashMap::new();
     let mut latest: HashMap<String, CountsSnapShot> = HashMap::new();
 
-    while let Ok(event) = rx.recv().await {
+    loop {
+        let event = match rx.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
         match event {
             StatusEvent::ProviderStarted { provider } => {
                 let pb = progress.add_provider(&provider);
-                bars.insert(provider, pb);
+                bars.insert(provider, ProviderBar::new(pb));
             }
             StatusEvent::Counts { provider, counts } => {
                 latest.insert(provider.clone(), counts);
 
                 if progress.is_tty {
-                    let pb = bars
-                        .entry(provider.clone())
-                        .or_insert_with(|| progress.add_provider(&provider));
-
-                    pb.set_message(format_counts(counts));
+                    let bar = progress.bar(&mut bars, &provider);
+                    bar.counts = counts;
+                    bar.show_items();
                 }
             }
             StatusEvent::Message { provider, msg } => {
                 if progress.is_tty {
-                    if let Some(pb) = bars.get(&provider) {
-                        pb.set_message(msg);
+                    progress.bar(&mut bars, &provider).pb.set_message(msg);
+                } else {
+                    eprintln!("{}: {}", provider, msg);
+                }
+            }
+            StatusEvent::Phase { provider, phase } => {
+                if progress.is_tty {
+                    let bar = progress.bar(&mut bars, &provider);
+                    let was_downloading = bar.phase == Some(SyncPhase::Downloading);
+                    bar.phase = Some(phase);
+                    if was_downloading {
+                        bar.pb.reset();
+                        bar.pb.set_style(if bar.totals.is_empty() {
+                            spinner_style()
+                        } else {
+                            items_style()
+                        });
                     }
+                    bar.show_items();
+                } else {
+                    eprintln!("{}: {}", provider, phase_label(phase));
+                }
+            }
+            StatusEvent::Total {
+                provider,
+                kind,
+                total,
+            } => {
+                if progress.is_tty {
+                    let bar = progress.bar(&mut bars, &provider);
+                    if bar.totals.is_empty() && bar.phase != Some(SyncPhase::Downloading) {
+                        bar.pb.reset();
+                        bar.pb.set_style(items_style());
+                    }
+                    bar.totals.insert(kind, total);
+                    bar.show_items();
+                }
+            }
+            StatusEvent::Download {
+                provider,
+                bytes,
+                total_bytes,
+            } => {
+                if progress.is_tty {
+                    let bar = progress.bar(&mut bars, &provider);
+                    match total_bytes {
+                        Some(total) => {
+                            if bar.pb.length() != Some(total) {
+                                bar.pb.reset();
+                                bar.pb.set_style(download_style());
+                                bar.pb.set_length(total);
+                            }
+                            bar.pb.set_position(bytes);
+                            bar.pb.set_message("downloading");
+                        }
+                        None => bar
+                            .pb
+                            .set_message(format!("downloading, {}", HumanBytes(bytes))),
+                    }
+                }
+            }
+
+            StatusEvent::ProviderSkipped { provider, reason } => {
+                if progress.is_tty {
+                    let bar = progress.bar(&mut bars, &provider);
+                    bar.pb.set_style(
+                        ProgressStyle::with_template("\x1b[33m-\x1b[0m {prefix}: {msg}").unwrap(),
+                    );
+                    bar.pb.finish_with_message(reason);
+                } else {
+                    eprintln!("{}: skipped, {}", provider, reason);
                 }
             }
 
             StatusEvent::ProviderFinished { provider, counts } => {
-                let pb = bars
-                    .entry(provider.clone())
-                    .or_insert_with(|| progress.add_provider(&provider));
+                let bar = progress.bar(&mut bars, &provider);
 
-                pb.set_style(
+                bar.pb.set_style(
                     ProgressStyle::with_template("\x1b[32m✔\x1b[0m {prefix}: {msg}").unwrap(),
                 );
-                pb.finish_with_message(format_counts(counts));
+                bar.pb.finish_with_message(format_counts(counts));
             }
+
+            StatusEvent::Reindexing => eprintln!("Reindexing FTS5 tables..."),
         }
     }
 
@@ -92,6 +251,35 @@ pub async fn run_progress_ui(mut rx: broadcast::Receiver<StatusEvent>) {
     }
 }
 
+fn spinner_style() -> ProgressStyle {
+    ProgressStyle::default_spinner()
+        .template("{spinner:.green} {prefix}: {msg}")
+        .unwrap()
+}
+
+fn items_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{spinner:.green} {prefix}: {msg} [{bar:30.cyan/blue}] {human_pos}/{human_len}, {eta} left")
+        .unwrap()
+        .progress_chars("=> ")
+}
+
+fn download_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{spinner:.green} {prefix}: {msg} [{bar:30.cyan/blue}] {bytes}/{total_bytes}, {eta} left")
+        .unwrap()
+        .progress_chars("=> ")
+}
+
+fn phase_label(phase: SyncPhase) -> &'static str {
+    match phase {
+        SyncPhase::Downloading => "downloading",
+        SyncPhase::Decompressing => "decompressing",
+        SyncPhase::Parsing => "parsing",
+        SyncPhase::Indexing => "indexing",
+    }
+}
+
 fn format_counts(c: CountsSnapShot) -> String {
     let mut parts = Vec::new();
 
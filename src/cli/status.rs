@@ -0,0 +1,58 @@
+use std::collections::HashSet;
+
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    cli::StatusArgs,
+    db::services::sync_history,
+    schema::{NGLSyncOutcome, NGLSyncRun},
+};
+
+/// `ngl status`: what the recorded syncs did, so missing data can be traced back to a run.
+pub async fn run(db: &DatabaseConnection, args: StatusArgs) -> anyhow::Result<()> {
+    let limit = args.history.then_some(args.limit);
+    let mut runs = sync_history(db, args.providers.as_deref(), limit).await?;
+
+    if !args.history {
+        let mut seen = HashSet::new();
+        runs.retain(|run| seen.insert((run.provider_name.clone(), run.kind)));
+        runs.sort_by(|a, b| a.provider_name.cmp(&b.provider_name));
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&runs)?);
+        return Ok(());
+    }
+
+    if runs.is_empty() {
+        println!("No syncs recorded yet.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<20} {:<10} {:<20} {:>9} {:>8}  {:<16} OUTCOME",
+        "PROVIDER", "KIND", "STARTED", "DURATION", "ROWS", "REVISION"
+    );
+    for run in &runs {
+        println!(
+            "{:<20} {:<10} {:<20} {:>9} {:>8}  {:<16} {}",
+            run.provider_name,
+            format!("{:?}", run.kind),
+            run.started_at.format("%Y-%m-%d %H:%M:%S"),
+            format!("{:.1}s", run.duration_ms as f64 / 1000.0),
+            run.rows,
+            run.source_revision.as_deref().unwrap_or("-"),
+            format_outcome(run),
+        );
+    }
+
+    Ok(())
+}
+
+fn format_outcome(run: &NGLSyncRun) -> String {
+    match (run.outcome, &run.error) {
+        (NGLSyncOutcome::Success, _) => "ok".to_string(),
+        (NGLSyncOutcome::Failed, Some(error)) => format!("failed: {}", error),
+        (NGLSyncOutcome::Failed, None) => "failed".to_string(),
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::bail;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    cli::{ProgressFormat, SyncArgs, progress},
+    registry::ProviderRegistry,
+    schema::{NGLLockPolicy, NGLRequest},
+};
+
+/// `ngl sync`: refresh providers ahead of time so later queries don't have to.
+pub async fn run(
+    db: &DatabaseConnection,
+    args: SyncArgs,
+    registry: ProviderRegistry,
+    on_locked: NGLLockPolicy,
+    progress_format: ProgressFormat,
+) -> anyhow::Result<()> {
+    let dry_run = args.dry_run;
+    let mut request: NGLRequest = args.into();
+    request.on_locked = on_locked;
+
+    if dry_run {
+        for (provider, kinds) in registry.plan(db, &request).await? {
+            if kinds.is_empty() {
+                println!("{}: up to date", provider);
+            } else {
+                let kinds: Vec<String> = kinds.iter().map(|k| format!("{:?}", k)).collect();
+                println!("{}: {}", provider, kinds.join(", "));
+            }
+        }
+        return Ok(());
+    }
+
+    let (status, renderer) = progress::spawn(progress_format);
+    let report = registry.sync(db, request, &status).await;
+    drop(status);
+    renderer.await?;
+    let report = report?;
+    let failed: Vec<&str> = report
+        .failures()
+        .map(|(provider, error)| {
+            eprintln!("{}: {}", provider, error);
+            provider
+        })
+        .collect();
+    if !failed.is_empty() {
+        bail!("failed to sync: {}", failed.join(", "));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,458 @@
+use anyhow::bail;
+use base64::Engine;
+use console::{Key, Term, style};
+use sea_orm::DatabaseConnection;
+use tokio::signal::unix::{SignalKind, signal};
+
+use crate::{
+    cli::{ProgressFormat, TuiArgs, progress},
+    db::services::{fetch, search_hits},
+    registry::ProviderRegistry,
+    render,
+    schema::{
+        GuideData, GuideRef, NGLData, NGLDataKind, NGLDataVariant, NGLLockPolicy, NGLRequest,
+        NGLSearchHit, NGLSyncMode,
+    },
+    utils,
+};
+
+/// Hits listed per search, nobody scrolls further than this.
+const RESULT_LIMIT: u64 = 500;
+
+const HELP: &str =
+    "↑↓ select  Tab filters  → sub-guides  ← parent  ^O open  ^Y copy  PgUp/PgDn scroll  Esc quit";
+
+/// `ngl tui`: search as you type, with a preview of the selected entry.
+/// Syncs the requested kinds once up front, everything after that is read from the database.
+pub async fn run(
+    db: &DatabaseConnection,
+    args: TuiArgs,
+    registry: ProviderRegistry,
+    on_locked: NGLLockPolicy,
+    progress_format: ProgressFormat,
+) -> anyhow::Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        bail!("ngl tui needs a terminal");
+    }
+
+    let kinds: Vec<NGLDataKind> = args
+        .kinds
+        .map(|k| k.into_iter().map(Into::into).collect())
+        .unwrap_or_else(NGLDataKind::all);
+    let request = NGLRequest {
+        search_term: None,
+        providers: args.providers.clone(),
+        kinds: Some(kinds.clone()),
+        sync_mode: utils::default_sync_mode(),
+        on_locked,
+    };
+    let (status, renderer) = progress::spawn(progress_format);
+    let report = registry.sync(db, request, &status).await;
+    drop(status);
+    renderer.await?;
+    let failed: Vec<String> = report?
+        .failures()
+        .map(|(provider, _)| provider.to_string())
+        .collect();
+
+    let providers: Vec<(String, bool)> = registry
+        .providers()
+        .await
+        .into_iter()
+        .map(|p| p.name)
+        .map(|name| {
+            let enabled = args.providers.as_ref().is_none_or(|p| p.contains(&name));
+            (name, enabled)
+        })
+        .collect();
+
+    let mut app = App {
+        db,
+        query: args.query.unwrap_or_default(),
+        kinds: NGLDataKind::all()
+            .into_iter()
+            .map(|kind| (kind, kinds.contains(&kind)))
+            .collect(),
+        providers,
+        focus: Focus::Search,
+        filter_cursor: 0,
+        hits: Vec::new(),
+        selected: 0,
+        preview: None,
+        scroll: 0,
+        message: (!failed.is_empty()).then(|| format!("failed to sync: {}", failed.join(", "))),
+    };
+    app.search().await?;
+
+    let _screen = Screen::enter(&term)?;
+    let mut resized = signal(SignalKind::window_change())?;
+    let mut pending_key = None;
+
+    loop {
+        app.draw(&term)?;
+        let read = pending_key.get_or_insert_with(|| {
+            let term = term.clone();
+            tokio::task::spawn_blocking(move || term.read_key())
+        });
+        tokio::select! {
+            key = read => {
+                pending_key = None;
+                if !app.handle(key??, &term).await? {
+                    return Ok(());
+                }
+            }
+            _ = resized.recv() => {}
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Focus {
+    Search,
+    Filters,
+}
+
+struct App<'a> {
+    db: &'a DatabaseConnection,
+    query: String,
+    kinds: Vec<(NGLDataKind, bool)>,
+    providers: Vec<(String, bool)>,
+    focus: Focus,
+    /// Index into kinds followed by providers.
+    filter_cursor: usize,
+    hits: Vec<NGLSearchHit>,
+    selected: usize,
+    /// The selected hit, hydrated.
+    preview: Option<NGLData>,
+    scroll: usize,
+    message: Option<String>,
+}
+
+impl App<'_> {
+    /// Returns false once the user asked to quit.
+    async fn handle(&mut self, key: Key, term: &Term) -> anyhow::Result<bool> {
+        self.message = None;
+        match key {
+            Key::Escape | Key::CtrlC | Key::Char('\x03') => return Ok(false),
+            Key::Tab | Key::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Search => Focus::Filters,
+                    Focus::Filters => Focus::Search,
+                };
+            }
+            Key::ArrowUp => self.select(self.selected.saturating_sub(1)).await?,
+            Key::ArrowDown => self.select(self.selected + 1).await?,
+            Key::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            Key::PageDown => self.scroll += 10,
+            Key::Char('\x0f') => self.open(),
+            Key::Char('\x19') => self.copy(term)?,
+            Key::ArrowLeft | Key::ArrowRight | Key::Char(' ') | Key::Enter
+                if self.focus == Focus::Filters =>
+            {
+                let count = self.kinds.len() + self.providers.len();
+                match key {
+                    Key::ArrowLeft => self.filter_cursor = (self.filter_cursor + count - 1) % count,
+                    Key::ArrowRight => self.filter_cursor = (self.filter_cursor + 1) % count,
+                    _ => {
+                        match self.filter_cursor.checked_sub(self.kinds.len()) {
+                            None => self.kinds[self.filter_cursor].1 ^= true,
+                            Some(i) => self.providers[i].1 ^= true,
+                        }
+                        self.search().await?;
+                    }
+                }
+            }
+            Key::ArrowRight => self.sub_guides().await?,
+            Key::ArrowLeft => self.parent_guide().await?,
+            Key::Backspace if self.focus == Focus::Search => {
+                self.query.pop();
+                self.search().await?;
+            }
+            Key::Char(c) if self.focus == Focus::Search && !c.is_control() => {
+                self.query.push(c);
+                self.search().await?;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    async fn search(&mut self) -> anyhow::Result<()> {
+        self.hits = if self.query.trim().is_empty() {
+            Vec::new()
+        } else {
+            let providers: Vec<String> = self
+                .providers
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.clone())
+                .collect();
+            let request = NGLRequest {
+                search_term: Some(self.query.clone()),
+                // Providers outside the registry (removed from templates.json) stay searchable.
+                providers: (providers.len() < self.providers.len()).then_some(providers),
+                kinds: Some(
+                    self.kinds
+                        .iter()
+                        .filter(|(_, enabled)| *enabled)
+                        .map(|(kind, _)| *kind)
+                        .collect(),
+                ),
+                sync_mode: NGLSyncMode::Offline,
+                on_locked: NGLLockPolicy::default(),
+            };
+            search_hits(self.db, &request, Some(RESULT_LIMIT)).await?
+        };
+        self.select(0).await
+    }
+
+    async fn select(&mut self, index: usize) -> anyhow::Result<()> {
+        self.selected = index.min(self.hits.len().saturating_sub(1));
+        self.scroll = 0;
+        self.preview = match self.hits.get(self.selected) {
+            Some(hit) => fetch(self.db, hit.kind, hit.id).await?,
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn selected_guide(&self) -> Option<&GuideData> {
+        match &self.preview.as_ref()?.data {
+            NGLDataVariant::Guide(guide) => Some(guide),
+            _ => None,
+        }
+    }
+
+    /// Lists the selected guide's sub-guides.
+    async fn sub_guides(&mut self) -> anyhow::Result<()> {
+        let Some(guide) = self.selected_guide() else {
+            return Ok(());
+        };
+        if guide.sub_guides.is_empty() {
+            self.message = Some("no sub-guides".to_string());
+            return Ok(());
+        }
+        let provider = self.hits[self.selected].provider_name.clone();
+        self.hits = guide_hits(&guide.sub_guides, &provider);
+        self.select(0).await
+    }
+
+    /// Lists the selected guide's parent among its siblings, or on its own at the top.
+    async fn parent_guide(&mut self) -> anyhow::Result<()> {
+        let Some(parent) = self.selected_guide().and_then(|g| g.parent_guide.clone()) else {
+            self.message = Some("no parent guide".to_string());
+            return Ok(());
+        };
+        let provider = self.hits[self.selected].provider_name.clone();
+        let siblings = match fetch(self.db, NGLDataKind::Guide, parent.id).await? {
+            Some(NGLData {
+                data:
+                    NGLDataVariant::Guide(GuideData {
+                        parent_guide: Some(grandparent),
+                        ..
+                    }),
+            }) => match fetch(self.db, NGLDataKind::Guide, grandparent.id).await? {
+                Some(NGLData {
+                    data: NGLDataVariant::Guide(grandparent),
+                }) => grandparent.sub_guides,
+                _ => vec![parent.clone()],
+            },
+            _ => vec![parent.clone()],
+        };
+        self.hits = guide_hits(&siblings, &provider);
+        let index = siblings.iter().position(|g| g.id == parent.id).unwrap_or(0);
+        self.select(index).await
+    }
+
+    fn open(&mut self) {
+        let Some(url) = self.preview.as_ref().and_then(url_of) else {
+            self.message = Some("nothing to open".to_string());
+            return;
+        };
+        let opener = std::env::var("BROWSER").unwrap_or_else(|_| {
+            if cfg!(target_os = "macos") {
+                "open".to_string()
+            } else {
+                "xdg-open".to_string()
+            }
+        });
+        let spawned = std::process::Command::new(&opener)
+            .arg(&url)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        self.message = Some(match spawned {
+            Ok(_) => format!("opened {}", url),
+            Err(e) => format!("failed to run {}: {}", opener, e),
+        });
+    }
+
+    /// Copies through the terminal (OSC 52), which also works over SSH.
+    fn copy(&mut self, term: &Term) -> anyhow::Result<()> {
+        let Some(hit) = self.hits.get(self.selected) else {
+            return Ok(());
+        };
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&hit.name);
+        term.write_str(&format!("\x1b]52;c;{}\x07", encoded))?;
+        self.message = Some(format!("copied {}", hit.name));
+        Ok(())
+    }
+
+    fn draw(&mut self, term: &Term) -> anyhow::Result<()> {
+        let (rows, cols) = term.size();
+        let (rows, cols) = (rows as usize, cols as usize);
+        let list_width = (cols * 2 / 5).clamp(20.min(cols), 60);
+        let preview_width = cols.saturating_sub(list_width + 3);
+        let body_rows = rows.saturating_sub(4);
+
+        let mut lines = Vec::with_capacity(rows);
+        lines.push(format!("{} {}", style("ngl ›").bold(), self.query));
+        lines.push(self.filter_line());
+        lines.push(style("─".repeat(cols)).dim().to_string());
+
+        let preview: Vec<String> = match &self.preview {
+            Some(data) => render::terminal(&render::markdown(data), preview_width),
+            None if self.query.trim().is_empty() => vec!["Type to search".to_string()],
+            None => vec!["No matches".to_string()],
+        };
+        self.scroll = self.scroll.min(preview.len().saturating_sub(1));
+        let first = self.selected.saturating_sub(body_rows.saturating_sub(1));
+
+        for row in 0..body_rows {
+            let item = match self.hits.get(first + row) {
+                Some(hit) => {
+                    let text = console::pad_str(
+                        &format!("{} {}", kind_label(hit.kind), hit.name),
+                        list_width,
+                        console::Alignment::Left,
+                        Some("…"),
+                    )
+                    .into_owned();
+                    if first + row == self.selected {
+                        style(console::strip_ansi_codes(&text))
+                            .reverse()
+                            .to_string()
+                    } else {
+                        text
+                    }
+                }
+                None => " ".repeat(list_width),
+            };
+            let preview_line = preview
+                .get(self.scroll + row)
+                .map(String::as_str)
+                .unwrap_or_default();
+            lines.push(format!("{} {} {}", item, style("│").dim(), preview_line));
+        }
+
+        let footer = match &self.message {
+            Some(message) => style(message.clone()).yellow().to_string(),
+            None => {
+                let count = match self.hits.len() as u64 {
+                    RESULT_LIMIT => format!("{}+ hits", RESULT_LIMIT),
+                    n => format!("{} hits", n),
+                };
+                style(format!("{}  {}", count, HELP)).dim().to_string()
+            }
+        };
+        lines.push(render::fit(&footer, cols, "…"));
+
+        let mut frame = String::from("\x1b[H");
+        for line in lines.iter().take(rows) {
+            frame.push_str(&render::fit(line, cols, ""));
+            frame.push_str("\x1b[K\r\n");
+        }
+        if frame.ends_with("\r\n") {
+            frame.truncate(frame.len() - 2);
+        }
+
+        if self.focus == Focus::Search {
+            let column = console::measure_text_width(&format!("ngl › {}", self.query)) + 1;
+            frame.push_str(&format!("\x1b[1;{}H\x1b[?25h", column.min(cols)));
+        } else {
+            frame.push_str("\x1b[?25l");
+        }
+        term.write_str(&frame)?;
+        Ok(())
+    }
+
+    fn filter_line(&self) -> String {
+        let toggles = self
+            .kinds
+            .iter()
+            .map(|(kind, enabled)| (format!("{:?}", kind), *enabled))
+            .chain(self.providers.iter().cloned());
+
+        let mut line = String::new();
+        for (i, (label, enabled)) in toggles.enumerate() {
+            if i == self.kinds.len() {
+                line.push_str(&style("│ ").dim().to_string());
+            }
+            let mut label = style(format!(" {} ", label));
+            label = if enabled { label.green() } else { label.dim() };
+            if self.focus == Focus::Filters && i == self.filter_cursor {
+                label = label.reverse();
+            }
+            line.push_str(&label.to_string());
+            line.push(' ');
+        }
+        line
+    }
+}
+
+fn guide_hits(guides: &[GuideRef], provider: &str) -> Vec<NGLSearchHit> {
+    guides
+        .iter()
+        .map(|guide| NGLSearchHit {
+            kind: NGLDataKind::Guide,
+            provider_name: provider.to_string(),
+            id: guide.id,
+            name: guide
+                .title
+                .clone()
+                .or_else(|| guide.link.clone())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn kind_label(kind: NGLDataKind) -> console::StyledObject<&'static str> {
+    match kind {
+        NGLDataKind::Function => style("fn   ").blue(),
+        NGLDataKind::Option => style("opt  ").magenta(),
+        NGLDataKind::Package => style("pkg  ").green(),
+        NGLDataKind::Guide => style("guide").yellow(),
+        NGLDataKind::Example => style("ex   ").cyan(),
+        NGLDataKind::Type => style("type ").red(),
+    }
+}
+
+/// Where an entry lives on the web, preferring rendered docs over source code.
+fn url_of(data: &NGLData) -> Option<String> {
+    match &data.data {
+        NGLDataVariant::Function(f) => f.source_url.clone().or(f.source_code_url.clone()),
+        NGLDataVariant::Package(p) => p.homepage.clone().or(p.source_code_url.clone()),
+        NGLDataVariant::Guide(g) => Some(g.link.clone()),
+        NGLDataVariant::Example(e) => e.source_link.clone(),
+        NGLDataVariant::Option(o) => o.source_url.clone(),
+        NGLDataVariant::Type(_) => None,
+    }
+}
+
+/// The alternate screen for as long as it's alive, so the shell comes back as it was.
+struct Screen<'a>(&'a Term);
+
+impl<'a> Screen<'a> {
+    fn enter(term: &'a Term) -> std::io::Result<Self> {
+        term.write_str("\x1b[?1049h\x1b[2J")?;
+        Ok(Self(term))
+    }
+}
+
+impl Drop for Screen<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.write_str("\x1b[?25h\x1b[?1049l");
+    }
+}
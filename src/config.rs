@@ -0,0 +1,279 @@
+//! User configuration from `$XDG_CONFIG_HOME/ngl/config.json`.
+//!
+//! Everything in it is optional. Top level settings apply everywhere, a named profile
+//! overrides them field by field, e.g. a "work" profile with its own database and templates:
+//!
+//! ```json
+//! {
+//!   "templates": ["templates.json"],
+//!   "output": "table",
+//!   "sync_intervals": { "nixpkgs": 168 },
+//!   "network": { "timeout_secs": 30 },
+//!   "profile": "personal",
+//!   "profiles": {
+//!     "personal": { "providers": ["noogle", "nixpkgs", "home-manager"] },
+//!     "work": {
+//!       "database": "~/work/ngl.db",
+//!       "templates": ["work.json"],
+//!       "external_providers": [{ "command": "./monorepo-docs.py", "args": ["--root", "~/work"] }]
+//!     }
+//!   }
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    error::NglError,
+    providers::{external::ExternalProviderConfig, meta::MetaProviderConfig},
+};
+
+/// Settings that can be given at the top level of the config file or in a profile.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Settings {
+    /// Path of the SQLite database, relative to the config directory, or a full `sqlite:` URL.
+    pub database: Option<String>,
+    /// Template files (see `templates.json`), relative to the config directory.
+    pub templates: Option<Vec<PathBuf>>,
+    /// Kinds searched when a query doesn't ask for any, spelled like `--kinds`.
+    pub kinds: Option<Vec<String>>,
+    /// Providers used when a query doesn't name any.
+    pub providers: Option<Vec<String>>,
+    /// Executables to register as providers, see `providers::external`. Relative paths are
+    /// resolved against the config directory.
+    pub external_providers: Option<Vec<ExternalProviderConfig>>,
+    /// Hours between syncs for every provider, instead of each provider's own interval.
+    pub sync_interval_hours: Option<u32>,
+    /// Hours between syncs per provider name, these beat `sync_interval_hours`.
+    #[serde(default)]
+    pub sync_intervals: HashMap<String, u32>,
+    /// How query results are printed without `--output`, spelled like `--output`.
+    pub output: Option<String>,
+    #[serde(default)]
+    pub network: NetworkSettings,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NetworkSettings {
+    /// Answer from the existing database without syncing, like `--offline`.
+    pub offline: Option<bool>,
+    /// Seconds to wait for a connection or for more data before giving up, 60 by default.
+    pub timeout_secs: Option<u64>,
+    /// Attempts per download, 3 by default.
+    pub retries: Option<u32>,
+    /// Proxy for every request, e.g. `http://proxy.example:3128`.
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(flatten)]
+    settings: Settings,
+    /// Profile used when none is picked with `--profile`.
+    profile: Option<String>,
+    #[serde(default)]
+    profiles: HashMap<String, Settings>,
+}
+
+/// The config file with the selected profile applied.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Directory relative template paths are resolved against.
+    dir: PathBuf,
+    pub settings: Settings,
+}
+
+impl Config {
+    /// Reads `path`, or `config.json` in [`config_dir`] without one, and applies `profile`
+    /// (or the file's default profile). A missing default config file is fine, a missing
+    /// explicit one or an unknown profile is an error.
+    pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Self, NglError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => (config_dir().join("config.json"), false),
+        };
+        let dir = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(config_dir);
+
+        let file: ConfigFile = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| NglError::Config(format!("{}: {}", path.display(), e)))?,
+            Err(e) if required || e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(NglError::Config(format!("{}: {}", path.display(), e)));
+            }
+            Err(_) => ConfigFile::default(),
+        };
+
+        let mut settings = file.settings;
+        if let Some(name) = profile.or(file.profile.as_deref()) {
+            let Some(overrides) = file.profiles.get(name) else {
+                let mut known: Vec<&str> = file.profiles.keys().map(String::as_str).collect();
+                known.sort_unstable();
+                return Err(NglError::Config(format!(
+                    "unknown profile '{}', {} has: {}",
+                    name,
+                    path.display(),
+                    if known.is_empty() {
+                        "none".to_string()
+                    } else {
+                        known.join(", ")
+                    }
+                )));
+            };
+            settings = settings.with(overrides.clone());
+        }
+
+        Ok(Self { dir, settings })
+    }
+
+    /// [`Config::load`] with the file and profile from `NGL_CONFIG` and `NGL_PROFILE`, for
+    /// library callers without a command line. A broken config is warned about and ignored.
+    #[allow(unused)]
+    pub fn from_env() -> Self {
+        let path = std::env::var_os("NGL_CONFIG").map(PathBuf::from);
+        let profile = std::env::var("NGL_PROFILE").ok();
+        Self::load(path.as_deref(), profile.as_deref()).unwrap_or_else(|e| {
+            eprintln!("Warning: {}", e);
+            Self::default()
+        })
+    }
+
+    /// The configured database as a connection URL, by default `ngl.db` in [`data_dir`].
+    /// The directory holding it is created if need be.
+    pub fn database_url(&self) -> Result<String, NglError> {
+        let path = match self.settings.database.as_deref() {
+            Some(url) if url.starts_with("sqlite:") => return Ok(url.to_string()),
+            Some(path) => self.dir.join(expand_home(path)),
+            None => data_dir().join("ngl.db"),
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| NglError::Config(format!("{}: {}", parent.display(), e)))?;
+        }
+        Ok(format!("sqlite://{}?mode=rwc", path.display()))
+    }
+
+    /// Template providers from the configured template files, falling back to
+    /// `templates.json` in the config directory and then the current one. Sync intervals
+    /// and external providers from the config come along. Files that fail to load are skipped with a warning.
+    pub fn provider_config(&self) -> Option<MetaProviderConfig> {
+        let paths: Vec<PathBuf> = match &self.settings.templates {
+            Some(templates) => templates
+                .iter()
+                .map(|path| self.dir.join(expand_home(&path.to_string_lossy())))
+                .collect(),
+            None => [
+                self.dir.join("templates.json"),
+                PathBuf::from("templates.json"),
+            ]
+            .into_iter()
+            .find(|path| path.exists())
+            .into_iter()
+            .collect(),
+        };
+
+        let mut config = MetaProviderConfig::default();
+        let mut loaded = false;
+        for path in paths {
+            match MetaProviderConfig::from_file(&path) {
+                Ok(file) => {
+                    let dir = path.parent().unwrap_or(Path::new("."));
+                    config.template_providers.extend(file.template_providers);
+                    config.external_providers.extend(
+                        file.external_providers
+                            .into_iter()
+                            .map(|external| external.relative_to(dir)),
+                    );
+                    config.sync_intervals.extend(file.sync_intervals);
+                    config.sync_interval_hours =
+                        file.sync_interval_hours.or(config.sync_interval_hours);
+                    loaded = true;
+                }
+                Err(e) => eprintln!("Warning: failed to load meta provider config: {}", e),
+            }
+        }
+
+        if self.settings.sync_interval_hours.is_some() {
+            config.sync_interval_hours = self.settings.sync_interval_hours;
+        }
+        config
+            .sync_intervals
+            .extend(self.settings.sync_intervals.clone());
+        config.external_providers.extend(
+            self.settings
+                .external_providers
+                .iter()
+                .flatten()
+                .map(|external| external.clone().relative_to(&self.dir)),
+        );
+
+        let configured = !config.sync_intervals.is_empty()
+            || config.sync_interval_hours.is_some()
+            || !config.external_providers.is_empty();
+        (loaded || configured).then_some(config)
+    }
+}
+
+impl Settings {
+    /// `self` with everything `overrides` sets replaced.
+    fn with(self, overrides: Settings) -> Settings {
+        let mut sync_intervals = self.sync_intervals;
+        sync_intervals.extend(overrides.sync_intervals);
+        Settings {
+            database: overrides.database.or(self.database),
+            templates: overrides.templates.or(self.templates),
+            kinds: overrides.kinds.or(self.kinds),
+            providers: overrides.providers.or(self.providers),
+            external_providers: overrides.external_providers.or(self.external_providers),
+            sync_interval_hours: overrides.sync_interval_hours.or(self.sync_interval_hours),
+            sync_intervals,
+            output: overrides.output.or(self.output),
+            network: NetworkSettings {
+                offline: overrides.network.offline.or(self.network.offline),
+                timeout_secs: overrides.network.timeout_secs.or(self.network.timeout_secs),
+                retries: overrides.network.retries.or(self.network.retries),
+                proxy: overrides.network.proxy.or(self.network.proxy),
+                user_agent: overrides.network.user_agent.or(self.network.user_agent),
+            },
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/ngl`, `~/.config/ngl` when it isn't set.
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config").join("ngl")
+}
+
+/// `$XDG_DATA_HOME/ngl`, `~/.local/share/ngl` when it isn't set.
+pub fn data_dir() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share").join("ngl")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    // The spec says relative paths are invalid and should be ignored.
+    match std::env::var_os(var).map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => home_dir().join(fallback),
+    }
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::broadcast,
+};
+
+use crate::{
+    error::NglError,
+    providers::{StatusEvent, sync},
+    query,
+    registry::ProviderRegistry,
+    schema::{NGLLockPolicy, NGLQueryResult, NGLRequest, NGLSearchHit},
+    utils,
+};
+
+/// How often the daemon looks for cached data that has outlived its provider's sync interval.
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// A request line that asks for more than a plain `NGLRequest` (which is answered as a query).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NGLDaemonRequest {
+    Query(NGLRequest),
+    /// Just the index rows, without hydrating them. Fast enough to run on every keystroke.
+    Search(NGLRequest),
+}
+
+/// What the daemon writes back for each request line, as a single line of JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NGLDaemonReply {
+    Ok(NGLQueryResult),
+    Hits(Vec<NGLSearchHit>),
+    Err(NglError),
+}
+
+/// `$XDG_RUNTIME_DIR/ngl.sock`, or a per-user socket in the temp dir without one.
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("ngl.sock"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_else(|_| "ngl".to_string());
+            std::env::temp_dir().join(format!("ngl-{}.sock", user))
+        }
+    }
+}
+
+/// Sends `request` to a running daemon and waits for its answer.
+pub async fn send(socket: &Path, request: &NGLRequest) -> Result<NGLQueryResult, NglError> {
+    match roundtrip(socket, &NGLDaemonRequest::Query(request.clone())).await? {
+        NGLDaemonReply::Ok(result) => Ok(result),
+        NGLDaemonReply::Err(e) => Err(e),
+        NGLDaemonReply::Hits(_) => Err(unexpected_reply()),
+    }
+}
+
+/// Asks a running daemon for the search index rows matching `request`.
+pub async fn search(socket: &Path, request: &NGLRequest) -> Result<Vec<NGLSearchHit>, NglError> {
+    match roundtrip(socket, &NGLDaemonRequest::Search(request.clone())).await? {
+        NGLDaemonReply::Hits(hits) => Ok(hits),
+        NGLDaemonReply::Err(e) => Err(e),
+        NGLDaemonReply::Ok(_) => Err(unexpected_reply()),
+    }
+}
+
+async fn roundtrip(socket: &Path, request: &NGLDaemonRequest) -> Result<NGLDaemonReply, NglError> {
+    let stream = UnixStream::connect(socket).await?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+
+    let mut reply = String::new();
+    BufReader::new(reader).read_line(&mut reply).await?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+fn unexpected_reply() -> NglError {
+    NglError::Parse("the daemon answered a different kind of request".to_string())
+}
+
+/// Serves queries on `socket` until interrupted, keeping `db` and `registry` loaded between them.
+/// Each connection sends one `NGLRequest` per line and gets one `NGLDaemonReply` per line back.
+pub async fn serve(
+    db: DatabaseConnection,
+    registry: ProviderRegistry,
+    socket: &Path,
+    status: broadcast::Sender<StatusEvent>,
+) -> Result<(), NglError> {
+    if socket.exists() {
+        if UnixStream::connect(socket).await.is_ok() {
+            return Err(NglError::Config(format!(
+                "a daemon is already listening on {}",
+                socket.display()
+            )));
+        }
+        // Left behind by a daemon that didn't shut down cleanly.
+        std::fs::remove_file(socket)?;
+    }
+
+    let listener = UnixListener::bind(socket)?;
+
+    let refresher = tokio::spawn(refresh_on_schedule(
+        db.clone(),
+        registry.clone(),
+        status.clone(),
+    ));
+
+    let result = loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(conn) => conn,
+                    Err(e) => break Err(e.into()),
+                };
+                tokio::spawn(handle_connection(
+                    stream,
+                    db.clone(),
+                    registry.clone(),
+                    status.clone(),
+                ));
+            }
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        }
+    };
+
+    refresher.abort();
+    let _ = std::fs::remove_file(socket);
+    result
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    db: DatabaseConnection,
+    registry: ProviderRegistry,
+    status: broadcast::Sender<StatusEvent>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        // A bare NGLRequest is a query, as it was before there was more than one kind of request.
+        let request = serde_json::from_str::<NGLDaemonRequest>(&line)
+            .or_else(|_| serde_json::from_str::<NGLRequest>(&line).map(NGLDaemonRequest::Query));
+        let reply = match request {
+            Ok(request) => answer(&db, request, &registry, &status).await,
+            Err(e) => NGLDaemonReply::Err(e.into()),
+        };
+
+        let Ok(mut out) = serde_json::to_string(&reply) else {
+            break;
+        };
+        out.push('\n');
+        if writer.write_all(out.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn answer(
+    db: &DatabaseConnection,
+    request: NGLDaemonRequest,
+    registry: &ProviderRegistry,
+    status: &broadcast::Sender<StatusEvent>,
+) -> NGLDaemonReply {
+    let result = match request {
+        NGLDaemonRequest::Query(request) => query::answer(db, request, registry, status)
+            .await
+            .map(NGLDaemonReply::Ok),
+        NGLDaemonRequest::Search(request) => query::hits(db, request, registry, status)
+            .await
+            .map(NGLDaemonReply::Hits),
+    };
+    result.unwrap_or_else(NGLDaemonReply::Err)
+}
+
+/// Re-syncs whatever is cached once it expires, so queries rarely have to wait on a sync.
+/// Kinds nobody has asked for yet are left alone.
+async fn refresh_on_schedule(
+    db: DatabaseConnection,
+    registry: ProviderRegistry,
+    status: broadcast::Sender<StatusEvent>,
+) {
+    let mut interval = tokio::time::interval(REFRESH_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let cached = match sync::cached_kinds(&db).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                eprintln!("Warning: failed to read the sync cache: {}", e);
+                continue;
+            }
+        };
+        let registered: Vec<String> = registry
+            .providers()
+            .await
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+
+        for (provider, kinds) in cached {
+            if !registered.contains(&provider) {
+                continue;
+            }
+            let request = NGLRequest {
+                search_term: None,
+                providers: Some(vec![provider]),
+                kinds: Some(kinds),
+                sync_mode: utils::default_sync_mode(),
+                on_locked: NGLLockPolicy::Skip,
+            };
+            match registry.sync(&db, request, &status).await {
+                Ok(report) => {
+                    for (provider, error) in report.failures() {
+                        eprintln!("Warning: failed to refresh {}: {}", provider, error);
+                    }
+                }
+                Err(e) => eprintln!("Warning: scheduled refresh failed: {}", e),
+            }
+        }
+    }
+}
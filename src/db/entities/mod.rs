@@ -8,6 +8,7 @@ pub mod option;
 pub mod package;
 pub mod provider;
 pub mod provider_kind_cache;
+pub mod sync_run;
 pub mod r#type;
 
 pub trait NGLDataEntity: sea_orm::ActiveModelTrait {
@@ -17,6 +17,7 @@ pub struct Model {
     pub default_value: Option<String>,
     pub format: DocumentationFormat,
     pub data: String,
+    pub source_url: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
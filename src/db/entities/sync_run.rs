@@ -0,0 +1,39 @@
+use sea_orm::entity::prelude::*;
+
+use crate::schema::{NGLDataKind, NGLSyncOutcome};
+
+/// One attempt at syncing one kind of data for a provider.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "sync_runs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub provider_name: String,
+    pub kind: NGLDataKind,
+    pub started_at: DateTimeWithTimeZone,
+    pub finished_at: DateTimeWithTimeZone,
+    pub duration_ms: i64,
+    /// Rows inserted for this kind before the run finished or failed.
+    pub rows: i64,
+    pub source_revision: Option<String>,
+    pub outcome: NGLSyncOutcome,
+    pub error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::provider::Entity",
+        from = "Column::ProviderName",
+        to = "super::provider::Column::Name"
+    )]
+    Provider,
+}
+
+impl Related<super::provider::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Provider.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
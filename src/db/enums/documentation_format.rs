@@ -2,6 +2,7 @@ use sea_orm::{DeriveActiveEnum, EnumIter};
 
 #[derive(Clone, Debug, PartialEq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Text")]
+#[allow(clippy::upper_case_acronyms)]
 pub enum DocumentationFormat {
     #[sea_orm(string_value = "html")]
     HTML,
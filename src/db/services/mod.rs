@@ -1,17 +1,28 @@
 #![allow(unused)]
 use sea_orm::{
     ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, FromQueryResult,
-    QueryFilter, Statement,
+    QueryFilter, QueryOrder, QuerySelect, Statement, TransactionTrait,
 };
 
 use crate::{
-    db::entities::{NGLDataEntity, example, function, guide, guide_xref, option, package, r#type},
+    db::entities::{
+        NGLDataEntity, example, function, guide, guide_xref, option, package, sync_run, r#type,
+    },
     schema::{
         ExampleData, FunctionData, GuideData, GuideRef, NGLData, NGLDataKind, NGLDataVariant,
-        NGLRaw, NGLRequest, NGLResponse, OptionData, PackageData, TypeData,
+        NGLRaw, NGLRequest, NGLResponse, NGLSearchHit, NGLSyncRun, OptionData, PackageData,
+        TypeData,
     },
 };
 
+#[derive(FromQueryResult)]
+struct SearchHitRow {
+    entity_id: i32,
+    kind: String,
+    provider_name: String,
+    name: String,
+}
+
 #[derive(FromQueryResult)]
 struct SearchResult {
     entity_id: i32,
@@ -19,9 +30,10 @@ struct SearchResult {
     provider_name: String,
 }
 
-pub async fn insert<T>(db: &DatabaseConnection, models: Vec<T>) -> Result<(), DbErr>
+pub async fn insert<T, C>(db: &C, models: Vec<T>) -> Result<(), DbErr>
 where
     T: NGLDataEntity,
+    C: ConnectionTrait,
 {
     let mut remaining = models;
     const CHUNK_SIZE: usize = 150;
@@ -38,74 +50,149 @@ where
     Ok(())
 }
 
+/// Rebuilds the search index in one transaction, so searches never see it half empty.
 #[allow(dead_code)]
 pub async fn populate_fts5(db: &DatabaseConnection) -> Result<(), DbErr> {
-    db.execute(Statement::from_string(
-        db.get_database_backend(),
+    let txn = db.begin().await?;
+    txn.execute(Statement::from_string(
+        txn.get_database_backend(),
         "DELETE FROM ngl_search".to_owned(),
     ))
     .await?;
 
-    db.execute(Statement::from_string(
-        db.get_database_backend(),
+    txn.execute(Statement::from_string(
+        txn.get_database_backend(),
         "INSERT INTO ngl_search (entity_id, kind, provider_name, title, content)
          SELECT id, 'Function', provider_name, name, '' FROM functions"
             .to_owned(),
     ))
     .await?;
 
-    db.execute(Statement::from_string(
-        db.get_database_backend(),
+    txn.execute(Statement::from_string(
+        txn.get_database_backend(),
         "INSERT INTO ngl_search (entity_id, kind, provider_name, title, content)
          SELECT id, 'Example', provider_name, '', data FROM examples"
             .to_owned(),
     ))
     .await?;
 
-    db.execute(Statement::from_string(
-        db.get_database_backend(),
+    txn.execute(Statement::from_string(
+        txn.get_database_backend(),
         "INSERT INTO ngl_search (entity_id, kind, provider_name, title, content)
          SELECT id, 'Guide', provider_name, title, '' FROM guides"
             .to_owned(),
     ))
     .await?;
 
-    db.execute(Statement::from_string(
-        db.get_database_backend(),
+    txn.execute(Statement::from_string(
+        txn.get_database_backend(),
         "INSERT INTO ngl_search (entity_id, kind, provider_name, title, content)
          SELECT id, 'Option', provider_name, name, '' FROM options"
             .to_owned(),
     ))
     .await?;
 
-    db.execute(Statement::from_string(
-        db.get_database_backend(),
+    txn.execute(Statement::from_string(
+        txn.get_database_backend(),
         "INSERT INTO ngl_search (entity_id, kind, provider_name, title, content)
          SELECT id, 'Package', provider_name, name, name FROM packages"
             .to_owned(),
     ))
     .await?;
 
-    db.execute(Statement::from_string(
-        db.get_database_backend(),
+    txn.execute(Statement::from_string(
+        txn.get_database_backend(),
         "INSERT INTO ngl_search (entity_id, kind, provider_name, title, content)
          SELECT id, 'Type', provider_name, name, data FROM types"
             .to_owned(),
     ))
     .await?;
 
-    Ok(())
+    txn.commit().await
+}
+
+/// Prefix match on the search term, everything without one.
+fn match_expression(search_term: Option<&str>) -> String {
+    search_term
+        .map(|s| format!("\"{}\"*", s.replace("\"", "\"\"")))
+        .unwrap_or_else(|| "*".to_string())
+}
+
+/// Ranked index rows matching the request, without hydrating them. Kinds and providers filter
+/// the same way as in [`query_data`], except examples are listed on their own when asked for.
+pub async fn search_hits(
+    db: &DatabaseConnection,
+    request: &NGLRequest,
+    limit: Option<u64>,
+) -> Result<Vec<NGLSearchHit>, DbErr> {
+    if request.kinds.as_ref().is_some_and(|k| k.is_empty())
+        || request.providers.as_ref().is_some_and(|p| p.is_empty())
+    {
+        return Ok(vec![]);
+    }
+
+    let mut sql = "SELECT entity_id, kind, provider_name, \
+         CASE WHEN title = '' THEN substr(content, 1, 200) ELSE title END AS name \
+         FROM ngl_search WHERE ngl_search MATCH ?"
+        .to_string();
+    let mut values: Vec<sea_orm::Value> =
+        vec![match_expression(request.search_term.as_deref()).into()];
+
+    if let Some(kinds) = &request.kinds {
+        sql.push_str(&format!(
+            " AND kind IN ({})",
+            vec!["?"; kinds.len()].join(",")
+        ));
+        values.extend(kinds.iter().map(|k| format!("{:?}", k).into()));
+    }
+    if let Some(providers) = &request.providers {
+        sql.push_str(&format!(
+            " AND provider_name IN ({})",
+            vec!["?"; providers.len()].join(",")
+        ));
+        values.extend(providers.iter().map(|p| p.clone().into()));
+    }
+    sql.push_str(" ORDER BY rank");
+    if let Some(limit) = limit {
+        sql.push_str(" LIMIT ?");
+        values.push((limit as i64).into());
+    }
+
+    let rows = SearchHitRow::find_by_statement(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let kind = NGLDataKind::all()
+                .into_iter()
+                .find(|k| format!("{:?}", k) == row.kind)?;
+            Some(NGLSearchHit {
+                kind,
+                provider_name: row.provider_name,
+                id: row.entity_id,
+                name: row
+                    .name
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            })
+        })
+        .collect())
 }
 
 pub async fn query_data(
     db: &DatabaseConnection,
     request: &NGLRequest,
 ) -> Result<Vec<NGLResponse>, DbErr> {
-    let search_term = request
-        .search_term
-        .as_ref()
-        .map(|s| format!("\"{}\"*", s.replace("\"", "\"\"")))
-        .unwrap_or_else(|| "*".to_string());
+    let search_term = match_expression(request.search_term.as_deref());
 
     let requested_kinds = request.kinds.as_ref();
 
@@ -181,7 +268,7 @@ pub async fn query_data(
 
         provider_data
             .entry(result.provider_name)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(ngl_data);
     }
 
@@ -325,6 +412,7 @@ async fn fetch_option(
             default_value: model.default_value,
             description: Some(model.data),
             example: None,
+            source_url: model.source_url,
         }),
     })
 }
@@ -370,3 +458,294 @@ async fn fetch_type(
         }),
     })
 }
+
+/// Recorded sync runs, newest first, optionally narrowed to some providers.
+pub async fn sync_history(
+    db: &DatabaseConnection,
+    providers: Option<&[String]>,
+    limit: Option<u64>,
+) -> Result<Vec<NGLSyncRun>, DbErr> {
+    let mut query = sync_run::Entity::find()
+        .order_by_desc(sync_run::Column::StartedAt)
+        .order_by_desc(sync_run::Column::Id);
+    if let Some(providers) = providers {
+        query = query.filter(sync_run::Column::ProviderName.is_in(providers.iter().cloned()));
+    }
+
+    Ok(query
+        .limit(limit)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|run| NGLSyncRun {
+            provider_name: run.provider_name,
+            kind: run.kind,
+            started_at: run.started_at.with_timezone(&chrono::Utc),
+            finished_at: run.finished_at.with_timezone(&chrono::Utc),
+            duration_ms: run.duration_ms,
+            rows: run.rows,
+            source_revision: run.source_revision,
+            outcome: run.outcome,
+            error: run.error,
+        })
+        .collect())
+}
+
+/// Entries of `kind` named exactly `name`, grouped by provider. Functions also match on their
+/// aliases, guides on their title or link, examples have no name and never match.
+pub async fn lookup(
+    db: &DatabaseConnection,
+    kind: NGLDataKind,
+    name: &str,
+    providers: Option<&[String]>,
+) -> Result<Vec<NGLResponse>, DbErr> {
+    let providers: Option<Vec<String>> = providers.map(|p| p.to_vec());
+    let hits: Vec<(i32, String)> = match kind {
+        NGLDataKind::Function => {
+            // Aliases are stored as a JSON array of names.
+            let mut query = function::Entity::find().filter(
+                function::Column::Name
+                    .eq(name)
+                    .or(function::Column::Aliases.contains(format!("\"{}\"", name))),
+            );
+            if let Some(providers) = &providers {
+                query = query.filter(function::Column::ProviderName.is_in(providers.clone()));
+            }
+            query
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|m| (m.id, m.provider_name))
+                .collect()
+        }
+        NGLDataKind::Option => {
+            let mut query = option::Entity::find().filter(option::Column::Name.eq(name));
+            if let Some(providers) = &providers {
+                query = query.filter(option::Column::ProviderName.is_in(providers.clone()));
+            }
+            query
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|m| (m.id, m.provider_name))
+                .collect()
+        }
+        NGLDataKind::Package => {
+            let mut query = package::Entity::find().filter(package::Column::Name.eq(name));
+            if let Some(providers) = &providers {
+                query = query.filter(package::Column::ProviderName.is_in(providers.clone()));
+            }
+            query
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|m| (m.id, m.provider_name))
+                .collect()
+        }
+        NGLDataKind::Type => {
+            let mut query = r#type::Entity::find().filter(r#type::Column::Name.eq(name));
+            if let Some(providers) = &providers {
+                query = query.filter(r#type::Column::ProviderName.is_in(providers.clone()));
+            }
+            query
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|m| (m.id, m.provider_name))
+                .collect()
+        }
+        NGLDataKind::Guide => {
+            let mut query = guide::Entity::find().filter(
+                guide::Column::Title
+                    .eq(name)
+                    .or(guide::Column::Link.eq(name)),
+            );
+            if let Some(providers) = &providers {
+                query = query.filter(guide::Column::ProviderName.is_in(providers.clone()));
+            }
+            query
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|m| (m.id, m.provider_name))
+                .collect()
+        }
+        NGLDataKind::Example => vec![],
+    };
+
+    let mut provider_data: std::collections::HashMap<String, Vec<NGLData>> =
+        std::collections::HashMap::new();
+    for (id, provider_name) in hits {
+        if let Some(data) = fetch(db, kind, id).await? {
+            provider_data.entry(provider_name).or_default().push(data);
+        }
+    }
+
+    Ok(provider_data
+        .into_iter()
+        .map(|(provider_name, matches)| NGLResponse {
+            provider_name,
+            matches,
+        })
+        .collect())
+}
+
+/// A single entry by kind and id, with its examples stitched in. None if it doesn't exist.
+pub async fn fetch(
+    db: &DatabaseConnection,
+    kind: NGLDataKind,
+    id: i32,
+) -> Result<Option<NGLData>, DbErr> {
+    let result = match kind {
+        NGLDataKind::Function => fetch_function(db, id, true).await,
+        NGLDataKind::Example => fetch_example(db, id).await,
+        NGLDataKind::Guide => fetch_guide(db, id, true).await,
+        NGLDataKind::Option => fetch_option(db, id, true).await,
+        NGLDataKind::Package => fetch_package(db, id, true).await,
+        NGLDataKind::Type => fetch_type(db, id, true).await,
+    };
+    match result {
+        Ok(data) => Ok(Some(data)),
+        Err(DbErr::RecordNotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Up to `limit` distinct names of `kind` starting with `prefix`, in order, each with its type
+/// or signature when there is one. Only functions, options, packages and types have names.
+pub async fn names_with_prefix(
+    db: &DatabaseConnection,
+    kind: NGLDataKind,
+    prefix: &str,
+    limit: u64,
+) -> Result<Vec<(String, Option<String>)>, DbErr> {
+    let names: Vec<(String, Option<String>)> = match kind {
+        NGLDataKind::Function => function::Entity::find()
+            .filter(function::Column::Name.starts_with(prefix))
+            .order_by_asc(function::Column::Name)
+            .limit(limit)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| (m.name, m.signature))
+            .collect(),
+        NGLDataKind::Option => option::Entity::find()
+            .filter(option::Column::Name.starts_with(prefix))
+            .order_by_asc(option::Column::Name)
+            .limit(limit)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| (m.name, m.type_signature))
+            .collect(),
+        NGLDataKind::Package => package::Entity::find()
+            .filter(package::Column::Name.starts_with(prefix))
+            .order_by_asc(package::Column::Name)
+            .limit(limit)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| (m.name, m.version))
+            .collect(),
+        NGLDataKind::Type => r#type::Entity::find()
+            .filter(r#type::Column::Name.starts_with(prefix))
+            .order_by_asc(r#type::Column::Name)
+            .limit(limit)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| (m.name, None))
+            .collect(),
+        NGLDataKind::Guide | NGLDataKind::Example => vec![],
+    };
+
+    // LIKE treats `_` in the prefix as a wildcard, and several providers can share a name.
+    let mut seen = std::collections::HashSet::new();
+    Ok(names
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(prefix) && seen.insert(name.clone()))
+        .collect())
+}
+
+/// Where the option `name` is declared, as reported by providers that ship declarations
+/// (options.json), paired with the provider. Entries are paths relative to the source tree
+/// or URLs.
+pub async fn option_declarations(
+    db: &DatabaseConnection,
+    name: &str,
+) -> Result<Vec<(String, String)>, DbErr> {
+    let models = option::Entity::find()
+        .filter(option::Column::Name.eq(name))
+        .all(db)
+        .await?;
+
+    let mut declarations = Vec::new();
+    for model in models {
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(&model.data) else {
+            continue;
+        };
+        let Some(decls) = entry.get("declarations").and_then(|d| d.as_array()) else {
+            continue;
+        };
+        for decl in decls {
+            let decl = match decl {
+                serde_json::Value::String(path) => Some(path.clone()),
+                other => other
+                    .get("url")
+                    .or_else(|| other.get("name"))
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+            };
+            if let Some(decl) = decl.map(|decl| (model.provider_name.clone(), decl))
+                && !declarations.contains(&decl)
+            {
+                declarations.push(decl);
+            }
+        }
+    }
+    Ok(declarations)
+}
+
+/// Guides titled or linked `title`, or the top of each guide tree (guides that aren't anyone's
+/// sub-guide) without one, paired with their provider.
+pub async fn guide_refs(
+    db: &DatabaseConnection,
+    title: Option<&str>,
+    providers: Option<&[String]>,
+) -> Result<Vec<(String, GuideRef)>, DbErr> {
+    let mut query = guide::Entity::find().order_by_asc(guide::Column::Id);
+    query = match title {
+        Some(title) => query.filter(
+            guide::Column::Title
+                .eq(title)
+                .or(guide::Column::Link.eq(title)),
+        ),
+        None => query.filter(
+            guide::Column::Id.not_in_subquery(
+                sea_orm::sea_query::Query::select()
+                    .column(guide_xref::Column::SubGuideId)
+                    .from(guide_xref::Entity)
+                    .to_owned(),
+            ),
+        ),
+    };
+    if let Some(providers) = providers {
+        query = query.filter(guide::Column::ProviderName.is_in(providers.to_vec()));
+    }
+
+    Ok(query
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| {
+            (
+                m.provider_name,
+                GuideRef {
+                    id: m.id,
+                    link: Some(m.link),
+                    title: Some(m.title),
+                },
+            )
+        })
+        .collect())
+}
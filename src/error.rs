@@ -0,0 +1,56 @@
+use sea_orm::DbErr;
+use serde::{Deserialize, Serialize};
+
+/// Everything that can go wrong while syncing or querying.
+/// Variants carry messages rather than source errors so a failure can be
+/// cloned into a sync report and serialized alongside query results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, thiserror::Error)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+pub enum NglError {
+    /// A source could not be fetched.
+    #[error("network error: {0}")]
+    Network(String),
+    /// A source was fetched but its contents were not what the provider expected.
+    #[error("parse error: {0}")]
+    Parse(String),
+    #[error("database error: {0}")]
+    Database(String),
+    #[error("io error: {0}")]
+    Io(String),
+    /// A config file (e.g. templates.json) is missing pieces or malformed.
+    #[error("config error: {0}")]
+    Config(String),
+    #[error(
+        "unknown provider(s): {}. Registered providers: {}",
+        unknown.join(", "),
+        registered.join(", ")
+    )]
+    UnknownProvider {
+        unknown: Vec<String>,
+        registered: Vec<String>,
+    },
+}
+
+impl From<DbErr> for NglError {
+    fn from(e: DbErr) -> Self {
+        NglError::Database(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for NglError {
+    fn from(e: reqwest::Error) -> Self {
+        NglError::Network(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for NglError {
+    fn from(e: serde_json::Error) -> Self {
+        NglError::Parse(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for NglError {
+    fn from(e: std::io::Error) -> Self {
+        NglError::Io(e.to_string())
+    }
+}
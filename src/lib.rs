@@ -1,29 +1,101 @@
 pub mod cli;
+pub mod config;
+pub mod daemon;
 pub mod db;
+pub mod error;
+pub mod lsp;
+pub mod mcp;
 pub mod providers;
+pub mod query;
 pub mod registry;
+pub mod render;
 pub mod schema;
+#[cfg(feature = "serve")]
+pub mod serve;
 pub mod utils;
 
+pub use error::NglError;
+pub use providers::channel::{CountsSnapShot, StatusEvent, SyncPhase, status_channel};
+pub use providers::records::{
+    ExampleRecord, FunctionRecord, GuideLink, GuideRecord, Language, OptionRecord, PackageRecord,
+    TypeRecord,
+};
+pub use registry::{ProviderRegistry, RegisteredProvider};
 pub use schema::{
-    ExampleData, FunctionData, GuideData, NGLData, NGLDataKind, NGLDataVariant, NGLRaw, NGLRequest,
-    NGLResponse, OptionData, PackageData, TypeData,
+    ExampleData, FunctionData, GuideData, NGLData, NGLDataKind, NGLDataVariant, NGLFreshnessState,
+    NGLProviderFreshness, NGLProviderSyncResult, NGLQueryResult, NGLRaw, NGLRequest, NGLResponse,
+    NGLSearchHit, NGLSyncMode, NGLSyncOutcome, NGLSyncReport, NGLSyncRun, NGLSyncStatus,
+    OptionData, PackageData, TypeData,
 };
 
-use registry::ProviderRegistry;
-use sea_orm::DbErr;
+use crate::{config::Config, db::services::sync_history};
+use tokio::sync::broadcast;
+
+/// Syncs whatever the request needs and answers it.
+/// Setting `NGL_OFFLINE` forces [`NGLSyncMode::Offline`] regardless of the request.
+/// With [`NGLSyncMode::StaleWhileRevalidate`] the refresh of stale providers keeps
+/// running on the caller's runtime after this returns, so a later query sees the fresh data.
+/// A provider failing to sync doesn't fail the query, it shows up in the result's `sync_report`.
+pub async fn query(request: NGLRequest) -> Result<NGLQueryResult, NglError> {
+    query_with_status(request, &status_channel()).await
+}
+
+/// Same as [`query`], broadcasting sync progress on `status`.
+/// Subscribe to it first, e.g. with `ngl::cli::progress::run_json_lines`.
+pub async fn query_with_status(
+    request: NGLRequest,
+    status: &broadcast::Sender<StatusEvent>,
+) -> Result<NGLQueryResult, NglError> {
+    let registry = ProviderRegistry::from_env();
+    query_with_registry(request, &registry, status).await
+}
 
-use crate::db::services::query_data;
+/// Same as [`query_with_status`], syncing the providers of `registry` instead of the
+/// configured ones. This is how your own providers take part in a query:
+///
+/// ```ignore
+/// let registry = ProviderRegistry::from_env().register(Box::new(MyProvider::new()));
+/// let result = ngl::query_with_registry(request, &registry, &ngl::status_channel()).await?;
+/// ```
+pub async fn query_with_registry(
+    mut request: NGLRequest,
+    registry: &ProviderRegistry,
+    status: &broadcast::Sender<StatusEvent>,
+) -> Result<NGLQueryResult, NglError> {
+    let config = Config::from_env();
+    utils::configure_network(config.settings.network.clone());
+    let database_url = database_url(&config)?;
 
-pub async fn query(request: NGLRequest) -> Result<Vec<NGLResponse>, DbErr> {
-    let database_url =
-        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://ngl.db?mode=rwc".to_string());
+    if offline_from_env() || config.settings.network.offline == Some(true) {
+        request.sync_mode = NGLSyncMode::Offline;
+    }
 
     let db = db::establish_connection(&database_url).await?;
 
-    ProviderRegistry::sync(&db, request.clone()).await?;
+    query::answer(&db, request, registry, status).await
+}
+
+/// Recorded sync runs, newest first. `providers` narrows the history down,
+/// `limit` caps the number of runs returned.
+pub async fn sync_runs(
+    providers: Option<Vec<String>>,
+    limit: Option<u64>,
+) -> Result<Vec<NGLSyncRun>, NglError> {
+    let db = db::establish_connection(&database_url(&Config::from_env())?).await?;
 
-    let response = query_data(&db, &request).await?;
+    Ok(sync_history(&db, providers.as_deref(), limit).await?)
+}
+
+/// `DATABASE_URL`, or the database from the user's config.
+fn database_url(config: &Config) -> Result<String, NglError> {
+    match std::env::var("DATABASE_URL") {
+        Ok(url) => Ok(url),
+        Err(_) => config.database_url(),
+    }
+}
 
-    Ok(response)
+fn offline_from_env() -> bool {
+    std::env::var("NGL_OFFLINE")
+        .map(|v| !matches!(v.to_lowercase().as_str(), "" | "0" | "false" | "no" | "off"))
+        .unwrap_or(false)
 }
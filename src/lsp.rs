@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+
+use sea_orm::DatabaseConnection;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+use crate::{
+    db::services::{lookup, names_with_prefix, option_declarations, sync_history},
+    error::NglError,
+    render,
+    schema::{NGLDataKind, NGLDataVariant},
+};
+
+/// Completions returned per request, the client asks again as the prefix grows.
+const COMPLETION_LIMIT: u64 = 200;
+
+/// Larger messages are skipped rather than buffered, even a big document is far smaller.
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+
+const NIXPKGS_BLOB: &str = "https://github.com/NixOS/nixpkgs/blob/";
+/// Providers whose option declarations are paths inside nixpkgs.
+const NIXPKGS_PROVIDERS: &[&str] = &["nixpkgs", "nixos"];
+
+/// What the name under the cursor refers to, decided by how it's spelled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    /// `lib.*` and `builtins.*`
+    Function,
+    /// `pkgs.*`
+    Package,
+    /// Anything else, with or without a leading `config.`
+    Option,
+}
+
+impl Target {
+    fn of(word: &str) -> (Self, &str) {
+        if word.starts_with("lib.") || word.starts_with("builtins.") {
+            (Target::Function, word)
+        } else if let Some(attr) = word.strip_prefix("pkgs.") {
+            (Target::Package, attr)
+        } else {
+            (Target::Option, word.strip_prefix("config.").unwrap_or(word))
+        }
+    }
+
+    fn kind(self) -> NGLDataKind {
+        match self {
+            Target::Function => NGLDataKind::Function,
+            Target::Package => NGLDataKind::Package,
+            Target::Option => NGLDataKind::Option,
+        }
+    }
+}
+
+/// Language server on stdin/stdout, answering hover, completion and go to definition from the
+/// database. It never syncs; run `ngl sync` or keep `ngl daemon` running to keep data fresh.
+/// Everything else is left to the Nix language server running next to it.
+pub async fn serve(db: DatabaseConnection) -> Result<(), NglError> {
+    let mut reader = BufReader::new(tokio::io::stdin());
+    let mut stdout = tokio::io::stdout();
+    let mut server = Server {
+        db,
+        documents: HashMap::new(),
+    };
+
+    while let Some(message) = read_message(&mut reader).await? {
+        let message = match message {
+            Ok(message) => message,
+            // The id is unknown when the message couldn't be read.
+            Err((code, message)) => {
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": code, "message": message },
+                });
+                write_message(&mut stdout, &response).await?;
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or_default().to_string();
+        let id = message.get("id").cloned();
+
+        if method == "exit" {
+            break;
+        }
+
+        let result = server.handle(&method, &message["params"]).await;
+        // Notifications don't get an answer, even when handling them failed.
+        let Some(id) = id else {
+            continue;
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        write_message(&mut stdout, &response).await?;
+    }
+    Ok(())
+}
+
+/// Reads the next message, `None` at the end of input. A message that can't be read is
+/// skipped and comes back as the error to answer it with.
+async fn read_message<R: AsyncBufReadExt + Unpin>(
+    reader: &mut R,
+) -> Result<Option<Result<Value, (i64, String)>>, NglError> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Ok(Some(Err((
+            INVALID_REQUEST,
+            "message without Content-Length".to_string(),
+        ))));
+    };
+    if length > MAX_MESSAGE_BYTES {
+        tokio::io::copy(
+            &mut (&mut *reader).take(length as u64),
+            &mut tokio::io::sink(),
+        )
+        .await?;
+        return Ok(Some(Err((
+            INVALID_REQUEST,
+            format!("message of {} bytes is too large", length),
+        ))));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(
+        serde_json::from_slice(&body).map_err(|e| (PARSE_ERROR, e.to_string())),
+    ))
+}
+
+async fn write_message<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    message: &Value,
+) -> Result<(), NglError> {
+    let body = serde_json::to_vec(message)?;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+type HandlerResult = Result<Value, (i64, String)>;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INTERNAL_ERROR: i64 = -32603;
+
+struct Server {
+    db: DatabaseConnection,
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    async fn handle(&mut self, method: &str, params: &Value) -> HandlerResult {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // Full document sync, the documents are only read around the cursor.
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                    "definitionProvider": true,
+                },
+                "serverInfo": { "name": "ngl", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "initialized" | "$/cancelRequest" | "$/setTrace" => Ok(Value::Null),
+            "shutdown" => Ok(Value::Null),
+            "textDocument/didOpen" => {
+                let doc = &params["textDocument"];
+                if let (Some(uri), Some(text)) = (doc["uri"].as_str(), doc["text"].as_str()) {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                Ok(Value::Null)
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                // Full sync, so the last change holds the whole document.
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                Ok(Value::Null)
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    self.documents.remove(uri);
+                }
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => self.hover(params).await.map_err(internal),
+            "textDocument/completion" => self.completion(params).await.map_err(internal),
+            "textDocument/definition" => self.definition(params).await.map_err(internal),
+            _ if method.starts_with("$/") => Ok(Value::Null),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method: {}", method))),
+        }
+    }
+
+    fn cursor(&self, params: &Value) -> Option<Cursor<'_>> {
+        let text = self
+            .documents
+            .get(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        Some(Cursor::new(text.lines().nth(line)?, line, character))
+    }
+
+    async fn hover(&self, params: &Value) -> Result<Value, NglError> {
+        let Some(cursor) = self.cursor(params) else {
+            return Ok(Value::Null);
+        };
+        let (target, name) = Target::of(cursor.word());
+        let docs: Vec<String> = lookup(&self.db, target.kind(), name, None)
+            .await?
+            .iter()
+            .flat_map(|response| response.matches.iter().map(render::markdown))
+            .collect();
+        if docs.is_empty() {
+            return Ok(Value::Null);
+        }
+
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": docs.join("\n\n---\n\n") },
+            "range": cursor.word_range(),
+        }))
+    }
+
+    async fn completion(&self, params: &Value) -> Result<Value, NglError> {
+        let Some(cursor) = self.cursor(params) else {
+            return Ok(Value::Null);
+        };
+        let typed = cursor.typed();
+        if typed.is_empty() {
+            return Ok(Value::Null);
+        }
+        let (target, prefix) = Target::of(typed);
+        // Whatever was stripped off the typed text is put back in front of each name.
+        let lead = &typed[..typed.len() - prefix.len()];
+
+        let names = names_with_prefix(&self.db, target.kind(), prefix, COMPLETION_LIMIT).await?;
+        let items: Vec<Value> = names
+            .iter()
+            .map(|(name, detail)| {
+                json!({
+                    "label": name,
+                    // Function, Field, Module
+                    "kind": match target {
+                        Target::Function => 3,
+                        Target::Option => 5,
+                        Target::Package => 9,
+                    },
+                    "detail": detail,
+                    "textEdit": {
+                        "range": cursor.typed_range(),
+                        "newText": format!("{}{}", lead, name),
+                    },
+                })
+            })
+            .collect();
+
+        Ok(json!({
+            "isIncomplete": items.len() as u64 >= COMPLETION_LIMIT,
+            "items": items,
+        }))
+    }
+
+    async fn definition(&self, params: &Value) -> Result<Value, NglError> {
+        let Some(cursor) = self.cursor(params) else {
+            return Ok(Value::Null);
+        };
+        let (target, name) = Target::of(cursor.word());
+
+        let urls: Vec<String> = match target {
+            Target::Option => {
+                let mut urls = Vec::new();
+                for (provider, decl) in option_declarations(&self.db, name).await? {
+                    let revision = self.source_revision(&provider).await?;
+                    urls.extend(declaration_url(&decl, &provider, revision.as_deref()));
+                }
+                urls
+            }
+            Target::Function | Target::Package => lookup(&self.db, target.kind(), name, None)
+                .await?
+                .iter()
+                .flat_map(|response| response.matches.iter())
+                .filter_map(|data| match &data.data {
+                    NGLDataVariant::Function(f) => f.source_code_url.clone(),
+                    NGLDataVariant::Package(p) => p.source_code_url.clone(),
+                    _ => None,
+                })
+                .collect(),
+        };
+
+        let locations: Vec<Value> = urls.iter().map(|url| location(url)).collect();
+        Ok(match locations.len() {
+            0 => Value::Null,
+            _ => Value::Array(locations),
+        })
+    }
+}
+
+impl Server {
+    /// The revision of the provider's source its last sync recorded, if any.
+    async fn source_revision(&self, provider: &str) -> Result<Option<String>, NglError> {
+        Ok(sync_history(&self.db, Some(&[provider.to_string()]), None)
+            .await?
+            .into_iter()
+            .find_map(|run| run.source_revision))
+    }
+}
+
+fn internal(e: NglError) -> (i64, String) {
+    (INTERNAL_ERROR, e.to_string())
+}
+
+/// The name under the cursor on one line, LSP positions count UTF-16 code units.
+struct Cursor<'a> {
+    line: &'a str,
+    line_number: usize,
+    start: usize,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a str, line_number: usize, character: usize) -> Self {
+        let mut units = 0;
+        let offset = line
+            .char_indices()
+            .find(|(_, c)| {
+                let before = units;
+                units += c.len_utf16();
+                before >= character
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(line.len());
+
+        let is_name = |c: char| c.is_ascii_alphanumeric() || "_'-.".contains(c);
+        let start = line[..offset]
+            .rfind(|c| !is_name(c))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let end = line[offset..]
+            .find(|c| !is_name(c))
+            .map(|i| offset + i)
+            .unwrap_or(line.len());
+
+        Self {
+            line,
+            line_number,
+            start,
+            offset,
+            end,
+        }
+    }
+
+    /// The whole name around the cursor, e.g. for hover.
+    fn word(&self) -> &'a str {
+        self.line[self.start..self.end].trim_matches('.')
+    }
+
+    /// The part of the name before the cursor, e.g. for completion.
+    fn typed(&self) -> &'a str {
+        &self.line[self.start..self.offset]
+    }
+
+    fn position(&self, byte: usize) -> Value {
+        json!({
+            "line": self.line_number,
+            "character": self.line[..byte].encode_utf16().count(),
+        })
+    }
+
+    fn word_range(&self) -> Value {
+        json!({ "start": self.position(self.start), "end": self.position(self.end) })
+    }
+
+    fn typed_range(&self) -> Value {
+        json!({ "start": self.position(self.start), "end": self.position(self.offset) })
+    }
+}
+
+/// URLs are used as they are. Paths, relative or in the store, are only known to be inside
+/// nixpkgs for the nixpkgs and NixOS providers, and only point at the right file at the
+/// `revision` their last sync recorded. Other paths can't be located.
+fn declaration_url(decl: &str, provider: &str, revision: Option<&str>) -> Option<String> {
+    if decl.contains("://") {
+        return Some(decl.to_string());
+    }
+    let revision = revision.filter(|_| NIXPKGS_PROVIDERS.contains(&provider))?;
+    let path = match decl.strip_prefix("/nix/store/") {
+        // /nix/store/<hash>-source/nixos/modules/...
+        Some(store_path) => store_path.split_once('/').map_or(store_path, |(_, p)| p),
+        None => decl.trim_start_matches('/'),
+    };
+    Some(format!("{}{}/{}", NIXPKGS_BLOB, revision, path))
+}
+
+/// A location for `url`, opened from a local nixpkgs checkout when `NGL_NIXPKGS_PATH` is set
+/// since most editors can't open a GitHub blob URL. A `#L<n>` anchor becomes the range.
+fn location(url: &str) -> Value {
+    let (url, line) = match url.rsplit_once("#L") {
+        Some((url, anchor)) => {
+            let digits: String = anchor.chars().take_while(char::is_ascii_digit).collect();
+            (url, digits.parse::<u64>().unwrap_or(1).saturating_sub(1))
+        }
+        None => (url, 0),
+    };
+
+    let uri = match (
+        std::env::var("NGL_NIXPKGS_PATH").ok(),
+        url.strip_prefix(NIXPKGS_BLOB),
+    ) {
+        // Drop the revision, the checkout is whatever it is.
+        (Some(checkout), Some(rest)) => match rest.split_once('/') {
+            Some((_, path)) => format!("file://{}/{}", checkout.trim_end_matches('/'), path),
+            None => url.to_string(),
+        },
+        _ => url.to_string(),
+    };
+
+    let position = json!({ "line": line, "character": 0 });
+    json!({ "uri": uri, "range": { "start": position, "end": position } })
+}
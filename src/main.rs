@@ -1,50 +1,225 @@
 mod cli;
+mod config;
+mod daemon;
 mod db;
+mod error;
+mod lsp;
+mod mcp;
 mod providers;
+mod query;
 mod registry;
+mod render;
 mod schema;
+#[cfg(feature = "serve")]
+mod serve;
 mod utils;
 
 use clap::{CommandFactory, Parser};
-use cli::Cli;
+use cli::{Cli, Command, OutputFormat};
+use config::Config;
 use db::services::query_data;
+use error::NglError;
 use registry::ProviderRegistry;
-use schema::{NGLDataKind, NGLRequest};
+use schema::{
+    NGLDataKind, NGLFreshnessState, NGLProviderFreshness, NGLRequest, NGLSyncMode, NGLSyncReport,
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+
+    // Runs from shell startup files, so it shouldn't touch (or create) a database.
+    if let Some(Command::ShellInit(args)) = &cli.command {
+        cli::fzf::shell_init(args.shell);
+        return Ok(());
+    }
+
+    let settings = Config::load(cli.config.as_deref(), cli.profile.as_deref())?;
+    let (config_path, profile) = (cli.config.clone(), cli.profile.clone());
+    utils::configure_network(settings.settings.network.clone());
+    let database_url = match cli.database_url.clone() {
+        Some(url) => url,
+        None => settings.database_url()?,
+    };
+    let registry = ProviderRegistry::from_config(settings.provider_config().as_ref());
+    let config_kinds = settings
+        .settings
+        .kinds
+        .as_ref()
+        .map(|kinds| {
+            kinds
+                .iter()
+                .map(|kind| cli::from_config("kinds", kind))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+    let config_output = settings
+        .settings
+        .output
+        .as_deref()
+        .map(|output| cli::from_config::<OutputFormat>("output", output))
+        .transpose()?;
+    let socket = cli
+        .socket
+        .clone()
+        .unwrap_or_else(daemon::default_socket_path);
+
+    if let Some(command) = cli.command.take() {
+        let db = db::establish_connection(&database_url).await?;
+        return match command {
+            Command::Sync(mut args) => {
+                args.providers = args.providers.or(settings.settings.providers);
+                args.kinds = args.kinds.or(config_kinds);
+                cli::sync::run(&db, args, registry, cli.if_locked.into(), cli.progress).await
+            }
+            Command::Status(args) => cli::status::run(&db, args).await,
+            Command::Daemon => {
+                let (status, _renderer) = cli::progress::spawn(cli.progress);
+                eprintln!("Listening on {}", socket.display());
+                daemon::serve(db, registry, &socket, status).await?;
+                Ok(())
+            }
+            Command::Lsp => {
+                lsp::serve(db).await?;
+                Ok(())
+            }
+            Command::Preview(args) => cli::fzf::preview(&db, args).await,
+            Command::ShellInit(_) => unreachable!("handled before connecting"),
+            Command::Tui(mut args) => {
+                args.providers = args.providers.or(settings.settings.providers);
+                args.kinds = args.kinds.or(config_kinds);
+                cli::tui::run(&db, args, registry, cli.if_locked.into(), cli.progress).await
+            }
+            Command::Mcp => {
+                let (status, _renderer) = cli::progress::spawn(cli.progress);
+                mcp::serve(db, registry, status).await?;
+                Ok(())
+            }
+            #[cfg(feature = "serve")]
+            Command::Serve(args) => {
+                let (status, _renderer) = cli::progress::spawn(cli.progress);
+                eprintln!("Listening on http://{}", args.bind);
+                serve::serve(db, registry, args.bind, status).await?;
+                Ok(())
+            }
+        };
+    }
 
     if cli.search_term.is_none() {
         Cli::command().print_help()?;
         return Ok(());
     }
 
-    let database_url = cli
-        .database_url
-        .clone()
-        .unwrap_or_else(|| "sqlite://ngl.db?mode=rwc".to_string());
+    // The daemon at the default socket answers from its own database, so a query that picks one
+    // (directly or through a config or profile) only goes there when the socket is named too.
+    let picks_database =
+        cli.database_url.is_some() || cli.config.is_some() || cli.profile.is_some();
+    let use_daemon = !cli.no_daemon && (cli.socket.is_some() || !picks_database) && socket.exists();
+    let progress_format = cli.progress;
+    let output = cli.output.or(config_output).unwrap_or(OutputFormat::Json);
+    cli.providers = cli.providers.or(settings.settings.providers);
+    cli.kinds = cli.kinds.or(config_kinds);
+    let request: NGLRequest = cli.into();
 
-    let db = db::establish_connection(&database_url).await?;
+    if output == OutputFormat::Fzf {
+        let socket = use_daemon.then_some(socket.as_path());
+        return cli::fzf::search(&database_url, socket, request, registry, progress_format).await;
+    }
 
-    let mut request: NGLRequest = cli.into();
-    if request.kinds.is_none() {
-        request.kinds = Some(vec![
-            NGLDataKind::Function,
-            NGLDataKind::Package,
-            NGLDataKind::Option,
-            NGLDataKind::Example,
-            NGLDataKind::Guide,
-            NGLDataKind::Type,
-        ]);
+    if use_daemon {
+        match daemon::send(&socket, &request).await {
+            Ok(result) => {
+                print_warnings(&result.sync_report, &result.stale_providers);
+                cli::output::print(&result.responses, output)?;
+                return Ok(());
+            }
+            // Most likely a socket left behind by a daemon that's gone, answer it ourselves.
+            Err(NglError::Io(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
     }
 
-    ProviderRegistry::sync(&db, request.clone()).await?;
+    let db = db::establish_connection(&database_url).await?;
+
+    let (status, renderer) = cli::progress::spawn(progress_format);
+    let report = registry.sync(&db, request.clone(), &status).await;
+    drop(status);
+    renderer.await?;
+    let report = report?;
+
+    let stale = registry.stale_providers(&db, &request).await?;
+    print_warnings(&report, &stale);
+
+    if request.sync_mode == NGLSyncMode::StaleWhileRevalidate && !stale.is_empty() {
+        let providers: Vec<String> = stale.into_iter().map(|p| p.provider_name).collect();
+        if let Err(e) = refresh_in_background(
+            &database_url,
+            config_path.as_deref(),
+            profile.as_deref(),
+            &providers,
+            request.kinds.as_deref(),
+        ) {
+            eprintln!("Warning: failed to start the background refresh: {}", e);
+        }
+    }
 
     if let Some(ref _term) = request.search_term {
         let response = query_data(&db, &request).await?;
-        println!("{}", serde_json::to_string_pretty(&response)?);
+        cli::output::print(&response, output)?;
     }
 
     Ok(())
 }
+
+/// Starts `ngl sync` for `providers` in its own process and leaves it running, so the answer
+/// doesn't wait on the refresh. An interrupted sync keeps the data it was replacing.
+fn refresh_in_background(
+    database_url: &str,
+    config: Option<&std::path::Path>,
+    profile: Option<&str>,
+    providers: &[String],
+    kinds: Option<&[NGLDataKind]>,
+) -> std::io::Result<()> {
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    // Global options go after `sync`, before it they'd make clap read `sync` as a search term.
+    command
+        .env("DATABASE_URL", database_url)
+        .args(["sync", "--providers", &providers.join(",")])
+        .args(["--progress", "none", "--if-locked", "skip"]);
+    if let Some(config) = config {
+        command.arg("--config").arg(config);
+    }
+    if let Some(profile) = profile {
+        command.args(["--profile", profile]);
+    }
+    if let Some(kinds) = kinds {
+        let kinds: Vec<String> = kinds
+            .iter()
+            .map(|kind| format!("{:?}", kind).to_lowercase())
+            .collect();
+        command.args(["--kinds", &kinds.join(",")]);
+    }
+    command
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    // Its own process group, so closing the terminal or Ctrl-C in it doesn't stop the refresh.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    command.spawn()?;
+    Ok(())
+}
+
+fn print_warnings(report: &NGLSyncReport, stale: &[NGLProviderFreshness]) {
+    for (provider, error) in report.failures() {
+        eprintln!("Warning: failed to sync {}: {}", provider, error);
+    }
+    for provider in stale {
+        match provider.state {
+            NGLFreshnessState::NeverSynced => {
+                eprintln!("Warning: {} has never been synced", provider.provider_name)
+            }
+            _ => eprintln!("Warning: {} data is stale", provider.provider_name),
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::broadcast,
+};
+
+use crate::{
+    db::services::{fetch, guide_refs, lookup},
+    error::NglError,
+    providers::StatusEvent,
+    query,
+    registry::ProviderRegistry,
+    render,
+    schema::{GuideRef, NGLDataKind, NGLDataVariant, NGLLockPolicy, NGLRequest, NGLSyncReport},
+    utils,
+};
+
+/// Protocol revisions this server speaks, newest first. A client asking for one of them gets
+/// it back, anyone else gets the newest and decides for themselves.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Matches shown per provider when a search doesn't set `limit`.
+const DEFAULT_SEARCH_LIMIT: usize = 5;
+
+/// Longest entry handed back in one piece, guides can run to whole manual chapters.
+const MAX_ENTRY_CHARS: usize = 20_000;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Model Context Protocol server on stdin/stdout, one JSON-RPC message per line.
+/// Tools sync what they need like any other query, progress goes to `status`.
+pub async fn serve(
+    db: DatabaseConnection,
+    registry: ProviderRegistry,
+    status: broadcast::Sender<StatusEvent>,
+) -> Result<(), NglError> {
+    let server = Server {
+        db,
+        registry,
+        status,
+    };
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => {
+                let method = message["method"].as_str().unwrap_or_default();
+                let result = server.handle(method, &message["params"]).await;
+                // Notifications and replies to our (nonexistent) requests get no answer.
+                let Some(id) = message.get("id").filter(|_| !method.is_empty()) else {
+                    continue;
+                };
+                match result {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                }
+            }
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": e.to_string() },
+            }),
+        };
+
+        let mut out = serde_json::to_string(&response)?;
+        out.push('\n');
+        stdout.write_all(out.as_bytes()).await?;
+        stdout.flush().await?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    query: String,
+    kinds: Option<Vec<NGLDataKind>>,
+    providers: Option<Vec<String>>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct LookupArgs {
+    kind: NGLDataKind,
+    name: String,
+    providers: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct GuideArgs {
+    id: Option<i32>,
+    title: Option<String>,
+    providers: Option<Vec<String>>,
+}
+
+struct Server {
+    db: DatabaseConnection,
+    registry: ProviderRegistry,
+    status: broadcast::Sender<StatusEvent>,
+}
+
+impl Server {
+    async fn handle(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => {
+                let requested = params["protocolVersion"].as_str().unwrap_or_default();
+                let version = PROTOCOL_VERSIONS
+                    .into_iter()
+                    .find(|v| *v == requested)
+                    .unwrap_or(PROTOCOL_VERSIONS[0]);
+                Ok(json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "ngl", "version": env!("CARGO_PKG_VERSION") },
+                    "instructions": "Nix documentation from NGL's local index: functions, \
+                        NixOS and home-manager options, packages and manuals. Search first, \
+                        then look up exact names before relying on them.",
+                }))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => {
+                let name = params["name"].as_str().unwrap_or_default();
+                let args = params.get("arguments").cloned().unwrap_or(json!({}));
+                let text = match name {
+                    "search" => self.search(parse_args(args)?).await,
+                    "lookup" => self.lookup(parse_args(args)?).await,
+                    "guide" => self.guide(parse_args(args)?).await,
+                    _ => return Err((INVALID_PARAMS, format!("unknown tool: {}", name))),
+                };
+                // Failures are reported to the model rather than the client, it can retry.
+                Ok(match text {
+                    Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+                    Err(e) => json!({
+                        "content": [{ "type": "text", "text": e.to_string() }],
+                        "isError": true,
+                    }),
+                })
+            }
+            _ if method.starts_with("notifications/") => Ok(Value::Null),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method: {}", method))),
+        }
+    }
+
+    async fn search(&self, args: SearchArgs) -> Result<String, NglError> {
+        let request = NGLRequest {
+            search_term: Some(args.query.clone()),
+            providers: args.providers,
+            kinds: args.kinds,
+            sync_mode: utils::default_sync_mode(),
+            on_locked: NGLLockPolicy::default(),
+        };
+        let result = query::answer(&self.db, request, &self.registry, &self.status).await?;
+
+        let mut out = sync_notes(&result.sync_report);
+        for provider in &result.stale_providers {
+            out.push_str(&format!(
+                "Note: {} data may be out of date.\n\n",
+                provider.provider_name
+            ));
+        }
+        let limit = args.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        for response in &result.responses {
+            out.push_str(&format!(
+                "# {} ({} matches)\n\n",
+                response.provider_name,
+                response.matches.len()
+            ));
+            for data in response.matches.iter().take(limit) {
+                out.push_str(&truncate(render::markdown(data)));
+                out.push_str("\n\n");
+            }
+        }
+        if result.responses.is_empty() {
+            out.push_str(&format!("No matches for \"{}\".", args.query));
+        }
+        Ok(out.trim_end().to_string())
+    }
+
+    async fn lookup(&self, args: LookupArgs) -> Result<String, NglError> {
+        let mut out = self.sync(args.kind, args.providers.clone()).await?;
+        let responses = lookup(&self.db, args.kind, &args.name, args.providers.as_deref()).await?;
+
+        for response in &responses {
+            for data in &response.matches {
+                out.push_str(&format!("# {}\n\n", response.provider_name));
+                out.push_str(&truncate(render::markdown(data)));
+                out.push_str("\n\n");
+            }
+        }
+        if responses.is_empty() {
+            out.push_str(&format!(
+                "No {:?} named \"{}\". Try the search tool.",
+                args.kind, args.name
+            ));
+        }
+        Ok(out.trim_end().to_string())
+    }
+
+    async fn guide(&self, args: GuideArgs) -> Result<String, NglError> {
+        let mut out = self
+            .sync(NGLDataKind::Guide, args.providers.clone())
+            .await?;
+
+        let id = match (args.id, &args.title) {
+            (Some(id), _) => Some(id),
+            (None, title) => {
+                let refs =
+                    guide_refs(&self.db, title.as_deref(), args.providers.as_deref()).await?;
+                match (title, refs.as_slice()) {
+                    (Some(_), [(_, only)]) => Some(only.id),
+                    (Some(title), []) => {
+                        out.push_str(&format!("No guide titled \"{}\".", title));
+                        None
+                    }
+                    _ => {
+                        out.push_str(match title {
+                            Some(_) => "Several guides match, pick one by id:\n",
+                            None => "Guides, open one by id:\n",
+                        });
+                        for (provider, guide) in refs {
+                            out.push_str(&format!("\n- {} ({})", guide_line(&guide), provider));
+                        }
+                        None
+                    }
+                }
+            }
+        };
+
+        if let Some(id) = id {
+            let Some(data) = fetch(&self.db, NGLDataKind::Guide, id).await? else {
+                return Err(NglError::Parse(format!("no guide with id {}", id)));
+            };
+            if let NGLDataVariant::Guide(guide) = &data.data {
+                out.push_str(&format!(
+                    "# {}\n\n{}\n\nLink: {}",
+                    render::title(&data),
+                    truncate(render::raw_to_markdown(&guide.content)),
+                    guide.link
+                ));
+                if let Some(parent) = &guide.parent_guide {
+                    out.push_str(&format!("\n\nParent: {}", guide_line(parent)));
+                }
+                if !guide.sub_guides.is_empty() {
+                    out.push_str("\n\nSub-guides:\n");
+                    for sub in &guide.sub_guides {
+                        out.push_str(&format!("\n- {}", guide_line(sub)));
+                    }
+                }
+            }
+        }
+        Ok(out.trim_end().to_string())
+    }
+
+    /// Syncs `kind` the way a query for it would, returning notes on providers that failed.
+    async fn sync(
+        &self,
+        kind: NGLDataKind,
+        providers: Option<Vec<String>>,
+    ) -> Result<String, NglError> {
+        let request = NGLRequest {
+            search_term: None,
+            providers,
+            kinds: Some(vec![kind]),
+            sync_mode: utils::default_sync_mode(),
+            on_locked: NGLLockPolicy::default(),
+        };
+        let report = self.registry.sync(&self.db, request, &self.status).await?;
+        Ok(sync_notes(&report))
+    }
+}
+
+fn tools() -> Value {
+    let kinds = json!({
+        "type": "array",
+        "items": {
+            "type": "string",
+            "enum": ["Function", "Option", "Package", "Guide", "Example", "Type"],
+        },
+    });
+    let providers = json!({
+        "type": "array",
+        "items": { "type": "string" },
+        "description": "Only these providers, e.g. noogle, nixpkgs, nixos_manual",
+    });
+
+    json!([
+        {
+            "name": "search",
+            "description": "Full text search over Nix functions, options, packages, guides, \
+                examples and types. Results are grouped by provider, best matches first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "kinds": kinds,
+                    "providers": providers,
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Matches shown per provider, 5 by default",
+                    },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "lookup",
+            "description": "Full documentation of an option, function or package by its exact \
+                name, e.g. services.nginx.enable, lib.strings.concatStrings or hello.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "kind": { "type": "string", "enum": ["Option", "Function", "Package"] },
+                    "name": { "type": "string" },
+                    "providers": providers,
+                },
+                "required": ["kind", "name"],
+            },
+        },
+        {
+            "name": "guide",
+            "description": "Read a guide by id or title, with the ids of its parent and \
+                sub-guides. Without either, lists the top level guides.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": { "type": "integer" },
+                    "title": { "type": "string" },
+                    "providers": providers,
+                },
+            },
+        },
+    ])
+}
+
+fn parse_args<T: serde::de::DeserializeOwned>(args: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(args).map_err(|e| (INVALID_PARAMS, e.to_string()))
+}
+
+fn sync_notes(report: &NGLSyncReport) -> String {
+    report
+        .failures()
+        .map(|(provider, error)| format!("Note: failed to sync {}: {}\n\n", provider, error))
+        .collect()
+}
+
+fn guide_line(guide: &GuideRef) -> String {
+    format!(
+        "[{}] {}",
+        guide.id,
+        guide
+            .title
+            .as_deref()
+            .or(guide.link.as_deref())
+            .unwrap_or("untitled")
+    )
+}
+
+fn truncate(text: String) -> String {
+    match text.char_indices().nth(MAX_ENTRY_CHARS) {
+        Some((end, _)) => format!("{}\n\n(truncated)", &text[..end]),
+        None => text,
+    }
+}
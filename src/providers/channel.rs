@@ -1,15 +1,35 @@
 #![allow(unused)]
 
-use crate::db::{
-    entities::{example, function, guide, guide_xref, option, package, r#type},
-    services::insert,
+use crate::{
+    db::{
+        entities::{example, function, guide, guide_xref, option, package, r#type},
+        enums::{documentation_format::DocumentationFormat, language},
+        services::insert,
+    },
+    providers::{
+        records::{
+            ExampleRecord, FunctionRecord, GuideLink, GuideRecord, Language, OptionRecord,
+            PackageRecord, TypeRecord,
+        },
+        sync,
+    },
+    schema::{NGLDataKind, NGLRaw},
+};
+use sea_orm::{
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter,
+    sea_query::OnConflict,
 };
-use sea_orm::{ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
 use tokio::sync::{broadcast, mpsc};
 
-const BATCH_SIZE: usize = 300;
+const CHANNEL_SIZE: usize = 600;
+/// Counts are reported every this many records, rather than per record.
+const COUNTS_EVERY: usize = 300;
+const STATUS_CAPACITY: usize = 1024;
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize)]
 pub struct CountsSnapShot {
     pub functions: usize,
     pub examples: usize,
@@ -19,7 +39,34 @@ pub struct CountsSnapShot {
     pub types: usize,
 }
 
-#[derive(Debug, Clone)]
+impl CountsSnapShot {
+    pub fn get(&self, kind: NGLDataKind) -> usize {
+        match kind {
+            NGLDataKind::Function => self.functions,
+            NGLDataKind::Example => self.examples,
+            NGLDataKind::Guide => self.guides,
+            NGLDataKind::Option => self.options,
+            NGLDataKind::Package => self.packages,
+            NGLDataKind::Type => self.types,
+        }
+    }
+}
+
+/// What a provider is busy with. Providers announce phases as they go,
+/// `Indexing` is sent for them once `sync()` returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    Downloading,
+    Decompressing,
+    Parsing,
+    Indexing,
+}
+
+/// Progress of a sync, broadcast to whoever subscribed to the sender handed to
+/// [`crate::registry::ProviderRegistry::sync`]. Nothing is rendered by the library itself.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum StatusEvent {
     ProviderStarted {
         provider: String,
@@ -32,26 +79,58 @@ pub enum StatusEvent {
         provider: String,
         msg: String,
     },
+    Phase {
+        provider: String,
+        phase: SyncPhase,
+    },
+    /// How many records of `kind` the provider expects to emit.
+    Total {
+        provider: String,
+        kind: NGLDataKind,
+        total: usize,
+    },
+    /// Bytes of the source downloaded so far, `total_bytes` if the server said.
+    Download {
+        provider: String,
+        bytes: u64,
+        total_bytes: Option<u64>,
+    },
     ProviderFinished {
         provider: String,
         counts: CountsSnapShot,
     },
+    /// The provider was due a sync but didn't run one.
+    ProviderSkipped {
+        provider: String,
+        reason: String,
+    },
+    /// Every provider is done and the search index is being rebuilt.
+    Reindexing,
+}
+
+/// A sender for [`StatusEvent`]s. Call `subscribe()` on it before syncing to follow along,
+/// events sent while nobody is subscribed are dropped.
+pub fn status_channel() -> broadcast::Sender<StatusEvent> {
+    broadcast::channel(STATUS_CAPACITY).0
 }
 
+/// A record a provider hands to its [`EventChannel`], see [`crate::providers::records`].
 pub enum ProviderEvent {
-    Function(function::ActiveModel),
-    Example(example::ActiveModel),
-    Guide(guide::ActiveModel),
-    GuideXref(String, String),
-    Option(option::ActiveModel),
-    Package(package::ActiveModel),
-    Type(r#type::ActiveModel),
+    Function(FunctionRecord),
+    Example(ExampleRecord),
+    Guide(GuideRecord),
+    GuideXref(GuideLink),
+    Option(OptionRecord),
+    Package(PackageRecord),
+    Type(TypeRecord),
 }
 
 #[derive(Clone)]
 pub struct EventChannel {
+    provider: String,
     sender: mpsc::Sender<ProviderEvent>,
     status: broadcast::Sender<StatusEvent>,
+    source_revision: Arc<Mutex<Option<String>>>,
 }
 
 impl EventChannel {
@@ -59,10 +138,45 @@ impl EventChannel {
         let _ = self.sender.send(event).await;
     }
 
+    /// Records which upstream revision this sync's data came from,
+    /// so it shows up in the sync history.
+    pub fn set_source_revision(&self, revision: impl Into<String>) {
+        *self.source_revision.lock().unwrap() = Some(revision.into());
+    }
+
+    pub fn source_revision(&self) -> Option<String> {
+        self.source_revision.lock().unwrap().clone()
+    }
+
     pub fn status(&self, ev: StatusEvent) {
         let _ = self.status.send(ev);
     }
 
+    pub fn phase(&self, phase: SyncPhase) {
+        self.status(StatusEvent::Phase {
+            provider: self.provider.clone(),
+            phase,
+        });
+    }
+
+    /// Announces how many records of `kind` are coming, so progress can show
+    /// "n / total" and an ETA instead of a bare count.
+    pub fn set_total(&self, kind: NGLDataKind, total: usize) {
+        self.status(StatusEvent::Total {
+            provider: self.provider.clone(),
+            kind,
+            total,
+        });
+    }
+
+    pub fn download_progress(&self, bytes: u64, total_bytes: Option<u64>) {
+        self.status(StatusEvent::Download {
+            provider: self.provider.clone(),
+            bytes,
+            total_bytes,
+        });
+    }
+
     pub fn subscribe_status(&self) -> broadcast::Receiver<StatusEvent> {
         self.status.subscribe()
     }
@@ -70,22 +184,23 @@ impl EventChannel {
 
 pub fn create_event_channel(
     provider_name: String,
-    db: DatabaseConnection,
     status_tx: broadcast::Sender<StatusEvent>,
-) -> (EventChannel, tokio::task::JoinHandle<Result<(), DbErr>>) {
-    let (sender, receiver) = mpsc::channel(BATCH_SIZE * 2);
+) -> (EventChannel, tokio::task::JoinHandle<Collected>) {
+    let (sender, receiver) = mpsc::channel(CHANNEL_SIZE);
 
-    let handle = tokio::spawn(batch_consumer(
-        provider_name.clone(),
-        receiver,
-        db,
-        status_tx.clone(),
-    ));
+    // Sent before the collector is spawned so it precedes anything the provider reports.
+    let _ = status_tx.send(StatusEvent::ProviderStarted {
+        provider: provider_name.clone(),
+    });
+
+    let handle = tokio::spawn(collect(provider_name.clone(), receiver, status_tx.clone()));
 
     (
         EventChannel {
+            provider: provider_name,
             sender,
             status: status_tx,
+            source_revision: Arc::new(Mutex::new(None)),
         },
         handle,
     )
@@ -98,163 +213,231 @@ fn send_counts(status: &broadcast::Sender<StatusEvent>, provider: &str, counts:
     });
 }
 
-async fn batch_consumer(
+/// Everything a provider sent during one sync. Nothing is written until the sync is over, see
+/// [`Collected::replace`], so a failed sync leaves the old data alone.
+#[derive(Default)]
+pub struct Collected {
     provider_name: String,
-    mut receiver: mpsc::Receiver<ProviderEvent>,
-    db: DatabaseConnection,
-    status: broadcast::Sender<StatusEvent>,
-) -> Result<(), DbErr> {
-    let _ = status.send(StatusEvent::ProviderStarted {
-        provider: provider_name.clone(),
-    });
+    functions: Vec<function::ActiveModel>,
+    examples: Vec<example::ActiveModel>,
+    guides: Vec<guide::ActiveModel>,
+    options: Vec<option::ActiveModel>,
+    packages: Vec<package::ActiveModel>,
+    types: Vec<r#type::ActiveModel>,
+    guide_xrefs: Vec<(String, String)>,
+    pub counts: CountsSnapShot,
+}
 
-    let mut functions: Vec<function::ActiveModel> = Vec::new();
-    let mut examples: Vec<example::ActiveModel> = Vec::new();
-    let mut guides: Vec<guide::ActiveModel> = Vec::new();
-    let mut options: Vec<option::ActiveModel> = Vec::new();
-    let mut packages: Vec<package::ActiveModel> = Vec::new();
-    let mut types: Vec<r#type::ActiveModel> = Vec::new();
-    let mut guide_xrefs: Vec<(String, String)> = Vec::new();
+impl Collected {
+    /// Replaces the provider's rows of `kinds` with the collected ones. Run it inside a
+    /// transaction so readers see either the old rows or the new ones, never neither.
+    pub async fn replace<C: ConnectionTrait>(
+        self,
+        db: &C,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), DbErr> {
+        let provider_name = self.provider_name;
+        for kind in kinds {
+            sync::delete_provider_kind_data(db, kind, &provider_name).await?;
+        }
+
+        insert(db, self.functions).await?;
+        insert(db, self.examples).await?;
+        insert(db, self.guides).await?;
+        insert(db, self.options).await?;
+        insert(db, self.packages).await?;
+        insert(db, self.types).await?;
+
+        for (parent_link, child_link) in self.guide_xrefs {
+            // Links are only unique within a provider, two providers may index the same site.
+            let parent = guide::Entity::find()
+                .filter(guide::Column::ProviderName.eq(&provider_name))
+                .filter(guide::Column::Link.eq(&parent_link))
+                .one(db)
+                .await?;
+            let child = guide::Entity::find()
+                .filter(guide::Column::ProviderName.eq(&provider_name))
+                .filter(guide::Column::Link.eq(&child_link))
+                .one(db)
+                .await?;
+
+            if let (Some(p), Some(c)) = (parent, child) {
+                guide_xref::Entity::insert(guide_xref::ActiveModel {
+                    guide_id: Set(p.id),
+                    sub_guide_id: Set(c.id),
+                })
+                .on_conflict(
+                    OnConflict::columns([
+                        guide_xref::Column::GuideId,
+                        guide_xref::Column::SubGuideId,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .exec_without_returning(db)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
 
-    let mut counts = CountsSnapShot::default();
+async fn collect(
+    provider_name: String,
+    mut receiver: mpsc::Receiver<ProviderEvent>,
+    status: broadcast::Sender<StatusEvent>,
+) -> Collected {
+    let mut collected = Collected {
+        provider_name: provider_name.clone(),
+        ..Collected::default()
+    };
+    let mut received = 0;
 
     while let Some(event) = receiver.recv().await {
+        let counts = &mut collected.counts;
         match event {
-            ProviderEvent::Function(model) => {
-                functions.push(model);
-                if functions.len() >= BATCH_SIZE {
-                    let n = functions.len();
-                    insert(&db, functions.drain(..).collect()).await?;
-                    counts.functions += n;
-                    send_counts(&status, &provider_name, counts);
-                }
+            ProviderEvent::Function(record) => {
+                collected
+                    .functions
+                    .push(function_model(record, &provider_name));
+                counts.functions += 1;
             }
-
-            ProviderEvent::Example(model) => {
-                examples.push(model);
-                if examples.len() >= BATCH_SIZE {
-                    let n = examples.len();
-                    insert(&db, examples.drain(..).collect()).await?;
-                    counts.examples += n;
-                    send_counts(&status, &provider_name, counts);
-                }
+            ProviderEvent::Example(record) => {
+                collected
+                    .examples
+                    .push(example_model(record, &provider_name));
+                counts.examples += 1;
             }
-
-            ProviderEvent::Guide(model) => {
-                guides.push(model);
-                if guides.len() >= BATCH_SIZE {
-                    let n = guides.len();
-                    insert(&db, guides.drain(..).collect()).await?;
-                    counts.guides += n;
-                    send_counts(&status, &provider_name, counts);
-                }
+            ProviderEvent::Guide(record) => {
+                collected.guides.push(guide_model(record, &provider_name));
+                counts.guides += 1;
             }
-
-            ProviderEvent::GuideXref(parent_link, child_link) => {
-                guide_xrefs.push((parent_link, child_link));
+            ProviderEvent::GuideXref(link) => {
+                collected.guide_xrefs.push((link.parent, link.child));
+                continue;
             }
-
-            ProviderEvent::Option(model) => {
-                options.push(model);
-                if options.len() >= BATCH_SIZE {
-                    let n = options.len();
-                    insert(&db, options.drain(..).collect()).await?;
-                    counts.options += n;
-                    send_counts(&status, &provider_name, counts);
-                }
+            ProviderEvent::Option(record) => {
+                collected.options.push(option_model(record, &provider_name));
+                counts.options += 1;
             }
-
-            ProviderEvent::Package(model) => {
-                packages.push(model);
-                if packages.len() >= BATCH_SIZE {
-                    let n = packages.len();
-                    insert(&db, packages.drain(..).collect()).await?;
-                    counts.packages += n;
-                    send_counts(&status, &provider_name, counts);
-                }
+            ProviderEvent::Package(record) => {
+                collected
+                    .packages
+                    .push(package_model(record, &provider_name));
+                counts.packages += 1;
             }
-
-            ProviderEvent::Type(model) => {
-                types.push(model);
-                if types.len() >= BATCH_SIZE {
-                    let n = types.len();
-                    insert(&db, types.drain(..).collect()).await?;
-                    counts.types += n;
-                    send_counts(&status, &provider_name, counts);
-                }
+            ProviderEvent::Type(record) => {
+                collected.types.push(type_model(record, &provider_name));
+                counts.types += 1;
             }
         }
+        received += 1;
+        if received % COUNTS_EVERY == 0 {
+            send_counts(&status, &provider_name, *counts);
+        }
     }
 
-    if !functions.is_empty() {
-        let n = functions.len();
-        insert(&db, functions).await?;
-        counts.functions += n;
-        send_counts(&status, &provider_name, counts);
-    }
+    send_counts(&status, &provider_name, collected.counts);
+    let _ = status.send(StatusEvent::ProviderFinished {
+        provider: provider_name,
+        counts: collected.counts,
+    });
+    collected
+}
 
-    if !examples.is_empty() {
-        let n = examples.len();
-        insert(&db, examples).await?;
-        counts.examples += n;
-        send_counts(&status, &provider_name, counts);
+fn format_and_data(content: NGLRaw) -> (DocumentationFormat, String) {
+    match content {
+        NGLRaw::Markdown(data) => (DocumentationFormat::Markdown, data),
+        NGLRaw::HTML(data) => (DocumentationFormat::HTML, data),
+        NGLRaw::PlainText(data) => (DocumentationFormat::PlainText, data),
     }
+}
 
-    if !guides.is_empty() {
-        let n = guides.len();
-        insert(&db, guides).await?;
-        counts.guides += n;
-        send_counts(&status, &provider_name, counts);
+fn language_model(language: Language) -> language::Language {
+    match language {
+        Language::Nix => language::Language::Nix,
     }
+}
 
-    if !options.is_empty() {
-        let n = options.len();
-        insert(&db, options).await?;
-        counts.options += n;
-        send_counts(&status, &provider_name, counts);
+fn function_model(record: FunctionRecord, provider_name: &str) -> function::ActiveModel {
+    let (format, data) = format_and_data(record.content);
+    function::ActiveModel {
+        id: NotSet,
+        name: Set(record.name),
+        provider_name: Set(provider_name.to_string()),
+        format: Set(format),
+        signature: Set(record.signature),
+        data: Set(data),
+        source_url: Set(record.source_url),
+        source_code_url: Set(record.source_code_url),
+        // Stored as a JSON array, lookups match `"alias"` inside it.
+        aliases: Set((!record.aliases.is_empty())
+            .then(|| serde_json::to_string(&record.aliases).unwrap_or_default())),
     }
+}
 
-    if !packages.is_empty() {
-        let n = packages.len();
-        insert(&db, packages).await?;
-        counts.packages += n;
-        send_counts(&status, &provider_name, counts);
+fn example_model(record: ExampleRecord, provider_name: &str) -> example::ActiveModel {
+    example::ActiveModel {
+        id: NotSet,
+        provider_name: Set(provider_name.to_string()),
+        language: Set(record.language.map(language_model)),
+        data: Set(record.code),
+        source_kind: Set(record.source_kind),
+        source_link: Set(record.source_link),
     }
+}
 
-    if !types.is_empty() {
-        let n = types.len();
-        insert(&db, types).await?;
-        counts.types += n;
-        send_counts(&status, &provider_name, counts);
+fn guide_model(record: GuideRecord, provider_name: &str) -> guide::ActiveModel {
+    let (format, data) = format_and_data(record.content);
+    guide::ActiveModel {
+        id: NotSet,
+        link: Set(record.link),
+        provider_name: Set(provider_name.to_string()),
+        title: Set(record.title),
+        format: Set(format),
+        data: Set(data),
     }
+}
 
-    send_counts(&status, &provider_name, counts);
-
-    if !guide_xrefs.is_empty() {
-        for (parent_link, child_link) in guide_xrefs {
-            let parent = guide::Entity::find()
-                .filter(guide::Column::Link.eq(&parent_link))
-                .one(&db)
-                .await?;
-            let child = guide::Entity::find()
-                .filter(guide::Column::Link.eq(&child_link))
-                .one(&db)
-                .await?;
+fn option_model(record: OptionRecord, provider_name: &str) -> option::ActiveModel {
+    let (format, data) = format_and_data(record.content);
+    option::ActiveModel {
+        id: NotSet,
+        provider_name: Set(provider_name.to_string()),
+        name: Set(record.name),
+        type_signature: Set(record.type_signature),
+        default_value: Set(record.default_value),
+        format: Set(format),
+        data: Set(data),
+        source_url: Set(record.source_url),
+    }
+}
 
-            if let (Some(p), Some(c)) = (parent, child) {
-                guide_xref::Entity::insert(guide_xref::ActiveModel {
-                    guide_id: Set(p.id),
-                    sub_guide_id: Set(c.id),
-                })
-                .exec(&db)
-                .await?;
-            }
-        }
+fn package_model(record: PackageRecord, provider_name: &str) -> package::ActiveModel {
+    let (format, data) = format_and_data(record.content);
+    package::ActiveModel {
+        id: NotSet,
+        provider_name: Set(provider_name.to_string()),
+        name: Set(record.name),
+        version: Set(record.version),
+        format: Set(format),
+        data: Set(data),
+        description: Set(record.description),
+        homepage: Set(record.homepage),
+        license: Set(record.license),
+        source_code_url: Set(record.source_code_url),
+        broken: Set(record.broken),
+        unfree: Set(record.unfree),
     }
+}
 
-    let _ = status.send(StatusEvent::ProviderFinished {
-        provider: provider_name.clone(),
-        counts,
-    });
-    Ok(())
+fn type_model(record: TypeRecord, provider_name: &str) -> r#type::ActiveModel {
+    let (format, data) = format_and_data(record.content);
+    r#type::ActiveModel {
+        id: NotSet,
+        provider_name: Set(provider_name.to_string()),
+        name: Set(record.name),
+        format: Set(format),
+        data: Set(data),
+    }
 }
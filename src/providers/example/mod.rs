@@ -3,16 +3,12 @@
 // your life a little bit easier.
 #![allow(unused)]
 use crate::{
-    db::{
-        entities::{example, function, guide, option, package, r#type},
-        enums::{documentation_format::DocumentationFormat, language::Language},
-    },
-    providers::{EventChannel, Provider, ProviderEvent, ProviderInformation},
-    schema::NGLDataKind,
+    error::NglError,
+    providers::{EventChannel, FunctionRecord, Provider, ProviderEvent, ProviderInformation},
+    schema::{NGLDataKind, NGLRaw},
 };
 use async_trait::async_trait;
 use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
-use sea_orm::{ActiveValue::*, DbErr};
 use std::sync::Arc;
 
 static PROVIDER_NAME: &str = "example";
@@ -74,21 +70,27 @@ impl Provider for Example {
     ///       to one that satisfies the `kinds` of data coming in.
     ///       If Example, Options, Types come in, you need to emit those different kinds of data to the eventchannel,
     ///       as at this point any previously cached kinds of data will be deleted already.
-    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr> {
-        // We just sent a database-mapped entity model to the channel, now the background task's job is to buffer that
-        // and when the buffer gets full, insert the batch of models into the db!
-        // Your whole goal is to shape whatever data the `kinds` param is requesting into the correlating database
-        // rows. Have fun!
-        channel.send(ProviderEvent::Function(function::ActiveModel {
-            id: NotSet,
-            name: Set("banana".to_string()),
-            provider_name: Set(PROVIDER_NAME.to_string()),
-            format: Set(DocumentationFormat::Markdown),
-            signature: Set(Some("x, y -> v".to_string())),
-            data: Set("so much data!".to_string()),
-            source_url: Set(Some("example.com".to_string())),
-            source_code_url: Set(Some("some other thing preferrably with built in marker for where in the sourcecode this is :)".to_string())),
-            aliases: Set(Some("what other funcs are there".to_string())),
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        // We just sent a record to the channel, now the background task's job is to map it onto a database row,
+        // buffer that and when the buffer gets full, insert the batch into the db!
+        // Your whole goal is to shape whatever data the `kinds` param is requesting into the matching records
+        // from `providers::records`, no database code needed. Have fun!
+        //
+        // If you know up front how much you're about to emit, say so, the progress UI turns that into a bar with an ETA.
+        // Downloading through `utils::read_body` or `utils::fetch_source` reports bytes for you, and `channel.phase(..)`
+        // lets people know whether you're still parsing or already done.
+        channel.set_total(NGLDataKind::Function, 1);
+        channel.send(ProviderEvent::Function(FunctionRecord {
+            name: "banana".to_string(),
+            signature: Some("x, y -> v".to_string()),
+            content: NGLRaw::Markdown("so much data!".to_string()),
+            source_url: Some("example.com".to_string()),
+            source_code_url: Some("some other thing preferrably with built in marker for where in the sourcecode this is :)".to_string()),
+            aliases: vec!["what.other.funcs.are.there".to_string()],
         })).await;
         Ok(())
     }
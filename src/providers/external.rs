@@ -0,0 +1,212 @@
+// Providers that live outside of NGL: any executable that speaks the protocol below, so
+// tooling that already extracts docs (in Python, Go, ...) can feed NGL without any Rust.
+//
+// `<command> [args] --ngl-info` prints the provider's `ProviderInformation` as JSON:
+//
+//   {"name": "monorepo", "source": "https://git.example.com/monorepo",
+//    "kinds": ["Function", "Guide"], "sync_interval_hours": 24}
+//
+// It runs the first time a process syncs or lists its providers, and has five seconds to answer.
+//
+// `<command> [args] --ngl-sync <kind>...` prints one record per line, tagged with its kind and
+// shaped like the structs in `providers::records` (optional fields can be left out):
+//
+//   {"kind": "function", "name": "lib.foo", "content": {"Markdown": "Does foo"}}
+//   {"kind": "option", "name": "services.foo.enable", "type_signature": "boolean", "content": {"PlainText": "..."}}
+//   {"kind": "guide", "link": "https://docs.example.com/intro", "title": "Intro", "content": {"HTML": "<p>...</p>"}}
+//   {"kind": "guide_xref", "parent": "https://docs.example.com", "child": "https://docs.example.com/intro"}
+//   {"kind": "example", "code": "foo 1", "language": "nix", "source_kind": "Function", "source_link": null}
+//   {"kind": "revision", "revision": "a1b2c3d"}
+//
+// The kinds asked for are passed in lowercase (`function`, `guide`, ...), records of any other
+// kind are dropped. Exiting with a non zero status fails the sync with whatever went to stderr.
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+    process::Command,
+};
+
+use crate::{
+    error::NglError,
+    providers::{
+        EventChannel, ExampleRecord, FunctionRecord, GuideLink, GuideRecord, OptionRecord,
+        PackageRecord, Provider, ProviderEvent, ProviderInformation, SyncPhase, TypeRecord,
+    },
+    schema::NGLDataKind,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExternalProviderConfig {
+    /// Executable to run, looked up on `PATH` unless it's a path.
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl ExternalProviderConfig {
+    /// Resolves a relative command path like `./extract.py` against `dir`, bare names are
+    /// left for `PATH`.
+    pub fn relative_to(mut self, dir: &Path) -> Self {
+        if self.command.is_relative() && self.command.components().count() > 1 {
+            self.command = dir.join(&self.command);
+        }
+        self
+    }
+}
+
+/// One line of `--ngl-sync` output.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ExternalRecord {
+    Function(FunctionRecord),
+    Option(OptionRecord),
+    Package(PackageRecord),
+    Guide(GuideRecord),
+    GuideXref(GuideLink),
+    Example(ExampleRecord),
+    Type(TypeRecord),
+    Revision { revision: String },
+}
+
+pub struct ExternalProvider {
+    config: ExternalProviderConfig,
+    info: ProviderInformation,
+}
+
+/// How long `--ngl-info` may take before the provider is skipped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl ExternalProvider {
+    /// Runs the `--ngl-info` handshake, giving up after [`HANDSHAKE_TIMEOUT`].
+    pub async fn from_config(config: ExternalProviderConfig) -> Result<Self, NglError> {
+        let command = config.command.display().to_string();
+        let output = Command::new(&config.command)
+            .args(&config.args)
+            .arg("--ngl-info")
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(HANDSHAKE_TIMEOUT, output)
+            .await
+            .map_err(|_| {
+                NglError::Config(format!(
+                    "{} --ngl-info took longer than {}s",
+                    command,
+                    HANDSHAKE_TIMEOUT.as_secs()
+                ))
+            })?
+            .map_err(|e| NglError::Config(format!("{}: {}", command, e)))?;
+        if !output.status.success() {
+            return Err(NglError::Config(format!(
+                "{} --ngl-info {}: {}",
+                command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let info = serde_json::from_slice(&output.stdout)
+            .map_err(|e| NglError::Config(format!("{} --ngl-info: {}", command, e)))?;
+        Ok(Self { config, info })
+    }
+}
+
+#[async_trait]
+impl Provider for ExternalProvider {
+    fn get_info(&self) -> ProviderInformation {
+        self.info.clone()
+    }
+
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        let mut command = Command::new(&self.config.command);
+        command.args(&self.config.args);
+        run_records(
+            &self.config.command.display().to_string(),
+            command,
+            channel,
+            kinds,
+        )
+        .await
+    }
+}
+
+/// Runs `command` with `--ngl-sync <kinds>` appended and sends the records it prints that are
+/// of one of `kinds`. `label` names the command in errors.
+async fn run_records(
+    label: &str,
+    mut command: Command,
+    channel: &EventChannel,
+    kinds: &[NGLDataKind],
+) -> Result<(), NglError> {
+    let mut child = command
+        .arg("--ngl-sync")
+        .args(
+            kinds
+                .iter()
+                .map(|kind| format!("{:?}", kind).to_lowercase()),
+        )
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| NglError::Io(format!("{}: {}", label, e)))?;
+
+    // Drained alongside stdout so a chatty command can't block on a full pipe.
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stderr = tokio::spawn(async move {
+        let mut buf = String::new();
+        let _ = stderr.read_to_string(&mut buf).await;
+        buf
+    });
+
+    channel.phase(SyncPhase::Parsing);
+    let mut lines = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+    let mut number = 0;
+    while let Some(line) = lines.next_line().await? {
+        number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: ExternalRecord = serde_json::from_str(&line)
+            .map_err(|e| NglError::Parse(format!("{} line {}: {}", label, number, e)))?;
+        let (kind, event) = match record {
+            ExternalRecord::Function(r) => (NGLDataKind::Function, ProviderEvent::Function(r)),
+            ExternalRecord::Option(r) => (NGLDataKind::Option, ProviderEvent::Option(r)),
+            ExternalRecord::Package(r) => (NGLDataKind::Package, ProviderEvent::Package(r)),
+            ExternalRecord::Guide(r) => (NGLDataKind::Guide, ProviderEvent::Guide(r)),
+            ExternalRecord::GuideXref(r) => (NGLDataKind::Guide, ProviderEvent::GuideXref(r)),
+            ExternalRecord::Example(r) => (NGLDataKind::Example, ProviderEvent::Example(r)),
+            ExternalRecord::Type(r) => (NGLDataKind::Type, ProviderEvent::Type(r)),
+            ExternalRecord::Revision { revision } => {
+                channel.set_source_revision(revision);
+                continue;
+            }
+        };
+        if kinds.contains(&kind) {
+            channel.send(event).await;
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        let stderr = stderr.await.unwrap_or_default();
+        return Err(NglError::Io(format!(
+            "{} exited with {}: {}",
+            label,
+            status,
+            stderr.trim()
+        )));
+    }
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use crate::providers::{EventChannel, Provider};
 use crate::schema::NGLDataKind;
 use async_trait::async_trait;
-use sea_orm::DbErr;
+use crate::error::NglError;
 
 #[allow(unused)]
 pub struct HjemDocs;
@@ -14,7 +14,7 @@ impl Provider for HjemDocs {
         todo!()
     }
 
-    async fn sync(&mut self, _channel: &EventChannel, _kinds: &[NGLDataKind]) -> Result<(), DbErr> {
+    async fn sync(&mut self, _channel: &EventChannel, _kinds: &[NGLDataKind]) -> Result<(), NglError> {
         todo!()
     }
 }
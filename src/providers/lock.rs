@@ -0,0 +1,125 @@
+// Sync leases stored in the database, so two ngl processes sharing a database
+// don't both delete and re-insert the same provider's rows.
+//
+// A lease expires on its own if its holder dies, and is renewed in the
+// background for as long as the holder keeps it.
+use chrono::Utc;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, Statement};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const LEASE_SECONDS: i64 = 300;
+const RENEW_INTERVAL: Duration = Duration::from_secs(60);
+const WAIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long [`SyncLease::acquire`] waits before giving up: a holder that's still alive by
+/// then has renewed its lease at least once, so it's busy with a long sync rather than stuck.
+pub const MAX_WAIT: Duration = Duration::from_secs(LEASE_SECONDS as u64);
+
+/// Dropping a lease without [`SyncLease::release`] (a cancelled or panicking sync) stops its
+/// renewal, so it expires after `LEASE_SECONDS` instead of being held forever.
+pub struct SyncLease {
+    db: DatabaseConnection,
+    provider_name: String,
+    holder: String,
+    renewal: JoinHandle<()>,
+}
+
+impl SyncLease {
+    /// Takes the provider's lease if nobody holds it, or the holder let it expire.
+    pub async fn try_acquire(
+        db: &DatabaseConnection,
+        provider_name: &str,
+    ) -> Result<Option<SyncLease>, DbErr> {
+        let holder = format!(
+            "{}-{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+        let now = Utc::now().timestamp();
+
+        let result = db
+            .execute(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "INSERT INTO sync_locks (provider_name, holder, expires_at) VALUES (?, ?, ?)
+                 ON CONFLICT(provider_name) DO UPDATE
+                 SET holder = excluded.holder, expires_at = excluded.expires_at
+                 WHERE sync_locks.expires_at < ?",
+                [
+                    provider_name.into(),
+                    holder.clone().into(),
+                    (now + LEASE_SECONDS).into(),
+                    now.into(),
+                ],
+            ))
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let renewal = tokio::spawn(renew(db.clone(), provider_name.to_string(), holder.clone()));
+
+        Ok(Some(SyncLease {
+            db: db.clone(),
+            provider_name: provider_name.to_string(),
+            holder,
+            renewal,
+        }))
+    }
+
+    /// Waits until the current holder releases the lease (or lets it expire) and takes it.
+    /// Gives up with `None` after [`MAX_WAIT`].
+    pub async fn acquire(
+        db: &DatabaseConnection,
+        provider_name: &str,
+    ) -> Result<Option<SyncLease>, DbErr> {
+        let deadline = tokio::time::Instant::now() + MAX_WAIT;
+        loop {
+            if let Some(lease) = Self::try_acquire(db, provider_name).await? {
+                return Ok(Some(lease));
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+        }
+    }
+
+    pub async fn release(self) -> Result<(), DbErr> {
+        self.renewal.abort();
+        self.db
+            .execute(Statement::from_sql_and_values(
+                self.db.get_database_backend(),
+                "DELETE FROM sync_locks WHERE provider_name = ? AND holder = ?",
+                [
+                    self.provider_name.as_str().into(),
+                    self.holder.as_str().into(),
+                ],
+            ))
+            .await?;
+        Ok(())
+    }
+}
+
+impl Drop for SyncLease {
+    fn drop(&mut self) {
+        self.renewal.abort();
+    }
+}
+
+async fn renew(db: DatabaseConnection, provider_name: String, holder: String) {
+    loop {
+        tokio::time::sleep(RENEW_INTERVAL).await;
+        let _ = db
+            .execute(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "UPDATE sync_locks SET expires_at = ? WHERE provider_name = ? AND holder = ?",
+                [
+                    (Utc::now().timestamp() + LEASE_SECONDS).into(),
+                    provider_name.clone().into(),
+                    holder.clone().into(),
+                ],
+            ))
+            .await;
+    }
+}
@@ -0,0 +1,927 @@
+// The `crawl` template: indexes a whole documentation site as guides. Starting from `source`
+// (a page, or a sitemap.xml listing pages) it follows links on the same site, and every
+// page's headings become a tree of guides: the page is a guide, each heading a sub-guide of
+// the heading above it, and a page is a sub-guide of the page it was first linked from.
+//
+// {
+//   "template": "crawl",
+//   "name": "nix-dev",
+//   "source": "https://nix.dev/",
+//   "kinds": ["guides", "examples"],
+//   "include": ["https://nix.dev/tutorials/*", "https://nix.dev/guides/*"],
+//   "exclude": ["*/_sources/*"],
+//   "max_depth": 3,
+//   "max_pages": 300,
+//   "delay_ms": 500,
+//   "content": "article"
+// }
+//
+// Patterns are matched against whole URLs, `*` matching anything. Without `include`, only
+// pages under the directory of `source` are crawled. `max_depth` counts links followed from
+// the start pages, `delay_ms` is the pause between requests (or the site's Crawl-delay, if
+// longer) and `content` selects the part of each page holding the docs, `main`, `article` or
+// else `body` by default. Paths robots.txt disallows are skipped, sitemaps included, for the
+// group naming the configured user agent's product token (`ngl` by default) or else for `*`.
+// If robots.txt fails with a server error nothing is crawled. Pages answered with 429 or 503
+// are retried after the `Retry-After` the server asks for, up to a minute. Links resolve
+// against the address a page was redirected to.
+use std::{
+    collections::{HashSet, VecDeque},
+    time::Duration,
+};
+
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Deserialize;
+
+use crate::error::NglError;
+use crate::providers::{
+    EventChannel, ExampleRecord, GuideLink, GuideRecord, ProviderEvent, ProviderInformation,
+    StatusEvent, SyncPhase,
+};
+use crate::schema::{NGLDataKind, NGLRaw};
+use crate::utils::{
+    extract_examples_html, html_to_markdown, http_client, read_body, retries, user_agent,
+};
+
+use super::{ConfigProvider, TemplateProviderConfig};
+
+#[derive(Debug, Deserialize)]
+struct CrawlOptions {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+    #[serde(default = "default_max_pages")]
+    max_pages: usize,
+    #[serde(default = "default_delay_ms")]
+    delay_ms: u64,
+    content: Option<String>,
+}
+
+fn default_max_depth() -> usize {
+    3
+}
+
+fn default_max_pages() -> usize {
+    200
+}
+
+fn default_delay_ms() -> u64 {
+    250
+}
+
+pub struct CrawlProvider {
+    info: ProviderInformation,
+    options: CrawlOptions,
+    content: Vec<Selector>,
+}
+
+/// A heading and everything up to the next one.
+struct Section {
+    level: u8,
+    link: String,
+    title: String,
+    html: String,
+}
+
+struct Page {
+    /// The page itself, with the content before its first heading. Titled by its first `h1`.
+    intro: Section,
+    sections: Vec<Section>,
+    links: Vec<Url>,
+}
+
+/// The rules from robots.txt that apply to us.
+#[derive(Default)]
+struct Robots {
+    /// `(allow, pattern)`
+    rules: Vec<(bool, String)>,
+    delay: Option<Duration>,
+}
+
+impl CrawlProvider {
+    pub fn from_config(cfg: &TemplateProviderConfig) -> Result<Self, NglError> {
+        let options: CrawlOptions = cfg.options()?;
+        Url::parse(&cfg.source).map_err(|e| {
+            NglError::Config(format!(
+                "template provider '{}': source must be a URL: {}",
+                cfg.name, e
+            ))
+        })?;
+        let content = match &options.content {
+            Some(selector) => vec![Selector::parse(selector).map_err(|e| {
+                NglError::Config(format!(
+                    "template provider '{}': invalid selector '{}': {:?}",
+                    cfg.name, selector, e
+                ))
+            })?],
+            None => ["main", "article", "body"]
+                .iter()
+                .map(|s| Selector::parse(s).unwrap())
+                .collect(),
+        };
+        Ok(Self {
+            info: cfg.to_provider_info(Some(&["guide", "guides", "example", "examples"])),
+            options,
+            content,
+        })
+    }
+
+    /// Whether `url` is one of the pages to crawl, robots.txt aside.
+    fn in_scope(&self, url: &Url, root: &Url) -> bool {
+        if url.scheme() != root.scheme()
+            || url.host_str() != root.host_str()
+            || url.port_or_known_default() != root.port_or_known_default()
+        {
+            return false;
+        }
+        let url = url.as_str();
+        let included = if self.options.include.is_empty() {
+            let dir = &root.as_str()[..root.as_str().rfind('/').map_or(0, |i| i + 1)];
+            url.starts_with(dir)
+        } else {
+            self.options.include.iter().any(|p| glob_match(p, url))
+        };
+        included && !self.options.exclude.iter().any(|p| glob_match(p, url))
+    }
+
+    async fn crawl(
+        &self,
+        channel: &EventChannel,
+        send_guides: bool,
+        send_examples: bool,
+    ) -> Result<(), NglError> {
+        let client = http_client()?;
+        let source = Url::parse(&self.info.source)
+            .map_err(|e| NglError::Config(format!("{}: {}", self.info.source, e)))?;
+
+        let robots = self.robots(&client, &source, channel).await;
+        let delay =
+            Duration::from_millis(self.options.delay_ms).max(robots.delay.unwrap_or_default());
+
+        let seeds = if source.path().ends_with(".xml") {
+            self.sitemap(&client, &source, &robots, channel, delay)
+                .await?
+        } else {
+            vec![source.clone()]
+        };
+
+        let mut seen: HashSet<Url> = seeds.iter().cloned().collect();
+        let mut queue: VecDeque<(Url, usize, Option<String>)> =
+            seeds.into_iter().map(|url| (url, 0, None)).collect();
+        let mut crawled = 0;
+        while let Some((url, depth, parent)) = queue.pop_front() {
+            if crawled >= self.options.max_pages {
+                self.message(
+                    channel,
+                    format!("stopping at max_pages ({})", self.options.max_pages),
+                );
+                break;
+            }
+            if !self.in_scope(&url, &source) || !robots.allows(&url) {
+                continue;
+            }
+            if crawled > 0 {
+                tokio::time::sleep(delay).await;
+            }
+
+            let requested = url;
+            let (url, html) = match fetch(&client, &requested, channel).await {
+                Ok(Some(fetched)) if fetched.content_type.contains("html") => {
+                    (fetched.url, fetched.body)
+                }
+                Ok(_) => continue,
+                // The start page has to work, a broken link elsewhere shouldn't sink the crawl.
+                Err(e) if crawled == 0 => return Err(e),
+                Err(e) => {
+                    self.message(channel, format!("skipping {}: {}", requested, e));
+                    continue;
+                }
+            };
+            // Past the start page, a redirect has to stay in the crawl and land on a page
+            // that isn't already queued or crawled under its own address.
+            if crawled > 0
+                && url != requested
+                && (!self.in_scope(&url, &source)
+                    || !robots.allows(&url)
+                    || !seen.insert(url.clone()))
+            {
+                continue;
+            }
+            crawled += 1;
+
+            channel.phase(SyncPhase::Parsing);
+            let page = self.parse_page(&html, &url);
+            if depth < self.options.max_depth {
+                for link in page.links.iter() {
+                    if seen.insert(link.clone()) {
+                        queue.push_back((link.clone(), depth + 1, Some(page.intro.link.clone())));
+                    }
+                }
+            }
+            self.send_page(channel, page, parent, send_guides, send_examples)
+                .await;
+        }
+
+        if crawled == 0 {
+            return Err(NglError::Parse(format!(
+                "no pages to crawl from {}",
+                self.info.source
+            )));
+        }
+        self.message(channel, format!("crawled {} pages", crawled));
+        Ok(())
+    }
+
+    /// The site's robots.txt rules. A missing or unreadable one allows everything, a server
+    /// error disallows everything since the site may be shielding itself.
+    async fn robots(
+        &self,
+        client: &reqwest::Client,
+        source: &Url,
+        channel: &EventChannel,
+    ) -> Robots {
+        let Ok(url) = source.join("/robots.txt") else {
+            return Robots::default();
+        };
+        match get(client, &url).await {
+            Ok(resp) if resp.status().is_server_error() => {
+                self.message(
+                    channel,
+                    format!("{} failed with {}, not crawling", url, resp.status()),
+                );
+                Robots::disallow_all()
+            }
+            Ok(resp) if resp.status().is_success() => match read_body(resp, channel).await {
+                Ok(body) => Robots::parse(&String::from_utf8_lossy(&body), &agent_token()),
+                Err(_) => Robots::default(),
+            },
+            _ => Robots::default(),
+        }
+    }
+
+    /// The pages listed in a sitemap, following sitemap indexes one level down. Sitemaps
+    /// robots.txt keeps us out of aren't read.
+    async fn sitemap(
+        &self,
+        client: &reqwest::Client,
+        url: &Url,
+        robots: &Robots,
+        channel: &EventChannel,
+        delay: Duration,
+    ) -> Result<Vec<Url>, NglError> {
+        if !robots.allows(url) {
+            return Err(NglError::Network(format!(
+                "{} is disallowed by robots.txt",
+                url
+            )));
+        }
+        let xml = fetch(client, url, channel)
+            .await?
+            .ok_or_else(|| NglError::Network(format!("{} not found", url)))?
+            .body;
+        let mut pages = Vec::new();
+        for loc in sitemap_locations(&xml) {
+            if !loc.path().ends_with(".xml") {
+                pages.push(loc);
+                continue;
+            }
+            if !robots.allows(&loc) {
+                self.message(
+                    channel,
+                    format!("skipping {}: disallowed by robots.txt", loc),
+                );
+                continue;
+            }
+            tokio::time::sleep(delay).await;
+            match fetch(client, &loc, channel).await {
+                Ok(Some(fetched)) => pages.extend(
+                    sitemap_locations(&fetched.body)
+                        .into_iter()
+                        .filter(|page| !page.path().ends_with(".xml")),
+                ),
+                Ok(None) => {}
+                Err(e) => self.message(channel, format!("skipping {}: {}", loc, e)),
+            }
+        }
+        Ok(pages)
+    }
+
+    fn parse_page(&self, html: &str, url: &Url) -> Page {
+        let document = Html::parse_document(html);
+        let links = document
+            .select(&Selector::parse("a[href]").unwrap())
+            .filter_map(|a| url.join(a.value().attr("href")?).ok())
+            .filter(|link| matches!(link.scheme(), "http" | "https"))
+            .map(|mut link| {
+                link.set_fragment(None);
+                link
+            })
+            .collect();
+
+        let page_title = document
+            .select(&Selector::parse("title").unwrap())
+            .next()
+            .map(|title| collapse(&title.text().collect::<String>()))
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| url.to_string());
+        let mut sections = vec![Section {
+            level: 0,
+            link: url.to_string(),
+            title: page_title,
+            html: String::new(),
+        }];
+        if let Some(content) = self
+            .content
+            .iter()
+            .find_map(|selector| document.select(selector).next())
+        {
+            split_sections(content, url, &mut sections);
+        }
+
+        // The first h1 names the page rather than starting a section of it.
+        let mut sections = sections.into_iter();
+        let mut intro = sections.next().expect("the page section is always there");
+        let mut sections: Vec<Section> = sections.collect();
+        if sections.first().is_some_and(|s| s.level == 1) {
+            let h1 = sections.remove(0);
+            intro.title = h1.title;
+            intro.html.push_str(&h1.html);
+        }
+        Page {
+            intro,
+            sections,
+            links,
+        }
+    }
+
+    async fn send_page(
+        &self,
+        channel: &EventChannel,
+        page: Page,
+        parent: Option<String>,
+        send_guides: bool,
+        send_examples: bool,
+    ) {
+        let page_link = page.intro.link.clone();
+        if send_guides && let Some(parent) = parent {
+            channel
+                .send(ProviderEvent::GuideXref(GuideLink {
+                    parent,
+                    child: page_link.clone(),
+                }))
+                .await;
+        }
+
+        // Each heading hangs off the closest heading above it with a lower level.
+        let mut ancestors: Vec<(u8, String)> = vec![(0, page_link)];
+        for (index, section) in std::iter::once(page.intro).chain(page.sections).enumerate() {
+            if index > 0 {
+                while ancestors
+                    .last()
+                    .is_some_and(|(level, _)| *level >= section.level)
+                {
+                    ancestors.pop();
+                }
+                if send_guides && let Some((_, parent)) = ancestors.last() {
+                    channel
+                        .send(ProviderEvent::GuideXref(GuideLink {
+                            parent: parent.clone(),
+                            child: section.link.clone(),
+                        }))
+                        .await;
+                }
+                ancestors.push((section.level, section.link.clone()));
+            }
+
+            if send_examples {
+                for example in extract_examples_html(&section.html) {
+                    channel
+                        .send(ProviderEvent::Example(ExampleRecord {
+                            code: example.data,
+                            language: example.language,
+                            source_kind: Some(NGLDataKind::Guide),
+                            source_link: Some(section.link.clone()),
+                        }))
+                        .await;
+                }
+            }
+            if send_guides {
+                channel
+                    .send(ProviderEvent::Guide(GuideRecord {
+                        link: section.link,
+                        title: section.title,
+                        content: NGLRaw::Markdown(html_to_markdown(&section.html)),
+                    }))
+                    .await;
+            }
+        }
+    }
+
+    fn message(&self, channel: &EventChannel, msg: String) {
+        channel.status(StatusEvent::Message {
+            provider: self.info.name.clone(),
+            msg,
+        });
+    }
+}
+
+impl ConfigProvider for CrawlProvider {
+    fn provider_info(&self) -> &ProviderInformation {
+        &self.info
+    }
+
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        let wanted = |kind| kinds.contains(&kind) && self.info.kinds.contains(&kind);
+        let send_guides = wanted(NGLDataKind::Guide);
+        let send_examples = wanted(NGLDataKind::Example);
+        if send_guides || send_examples {
+            self.crawl(channel, send_guides, send_examples).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A fetched page.
+struct Fetched {
+    /// Where redirects ended up, what relative links resolve against.
+    url: Url,
+    content_type: String,
+    body: String,
+}
+
+/// Fetches `url`, `None` when it doesn't exist.
+async fn fetch(
+    client: &reqwest::Client,
+    url: &Url,
+    channel: &EventChannel,
+) -> Result<Option<Fetched>, NglError> {
+    let resp = get(client, url).await?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        return Err(NglError::Network(format!(
+            "HTTP error {} fetching {}",
+            resp.status(),
+            url
+        )));
+    }
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    let url = resp.url().clone();
+    let body = read_body(resp, channel).await?;
+    Ok(Some(Fetched {
+        url,
+        content_type,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    }))
+}
+
+/// Longest `Retry-After` that is waited out, a server asking for more is given up on.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Sends a GET for `url`. Answers of 429 or 503 are retried, after the server's `Retry-After`
+/// or a growing pause without one, until the configured retries run out.
+async fn get(client: &reqwest::Client, url: &Url) -> Result<reqwest::Response, NglError> {
+    let mut attempt = 1;
+    loop {
+        let resp = client.get(url.clone()).send().await?;
+        let status = resp.status();
+        if attempt >= retries()
+            || !matches!(
+                status,
+                reqwest::StatusCode::TOO_MANY_REQUESTS | reqwest::StatusCode::SERVICE_UNAVAILABLE
+            )
+        {
+            return Ok(resp);
+        }
+        let wait = match retry_after(&resp) {
+            Some(wait) if wait > MAX_RETRY_AFTER => return Ok(resp),
+            Some(wait) => wait,
+            None => Duration::from_secs(2u64.pow(attempt.min(6))),
+        };
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+/// The response's `Retry-After`, given in seconds or as an HTTP date.
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    let value = resp
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.to_utc() - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// The product token of our user agent, `ngl` for `NGL/0.1 (Nix Global Lookup)`, which is
+/// what robots.txt groups name.
+fn agent_token() -> String {
+    user_agent()
+        .split(['/', ' '])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Walks `element` in document order, starting a new section at every heading and adding
+/// everything else to the current one.
+fn split_sections(element: ElementRef, url: &Url, sections: &mut Vec<Section>) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => sections
+                .last_mut()
+                .expect("the page section is always there")
+                .html
+                .push_str(&html_escape::encode_text(&text.text)),
+            Node::Element(el) => {
+                let child = ElementRef::wrap(child).expect("an element node");
+                let level = match el.name() {
+                    "h1" => 1,
+                    "h2" => 2,
+                    "h3" => 3,
+                    "h4" => 4,
+                    "h5" => 5,
+                    "h6" => 6,
+                    _ => 0,
+                };
+                if level > 0 {
+                    let title = collapse(&child.text().collect::<String>());
+                    let mut link = url.clone();
+                    link.set_fragment(Some(&heading_id(child, &title)));
+                    sections.push(Section {
+                        level,
+                        link: link.to_string(),
+                        title,
+                        html: String::new(),
+                    });
+                } else if child
+                    .descendants()
+                    .filter_map(ElementRef::wrap)
+                    .any(|e| matches!(e.value().name(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6"))
+                {
+                    split_sections(child, url, sections);
+                } else {
+                    sections
+                        .last_mut()
+                        .expect("the page section is always there")
+                        .html
+                        .push_str(&child.html());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The heading's anchor: its id, an anchor inside it, or its text as a slug.
+fn heading_id(heading: ElementRef, title: &str) -> String {
+    if let Some(id) = heading.value().id() {
+        return id.to_string();
+    }
+    let anchor = heading
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .find_map(|e| e.value().id().or_else(|| e.value().attr("name")));
+    match anchor {
+        Some(id) => id.to_string(),
+        None => title
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-"),
+    }
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn sitemap_locations(xml: &str) -> Vec<Url> {
+    xml.split("<loc>")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("</loc>"))
+        .filter_map(|(loc, _)| {
+            Url::parse(html_escape::decode_html_entities(loc.trim()).as_ref()).ok()
+        })
+        .collect()
+}
+
+impl Robots {
+    fn disallow_all() -> Self {
+        Self {
+            rules: vec![(false, "/".to_string())],
+            delay: None,
+        }
+    }
+
+    /// The rules for `agent`, a lowercase product token.
+    fn parse(text: &str, agent: &str) -> Self {
+        // Groups are runs of user-agent lines followed by their rules. A group naming us
+        // replaces the `*` one.
+        let mut ours = Robots::default();
+        let mut everyone = Robots::default();
+        let mut named_us = false;
+        let (mut agents, mut in_rules): (Vec<String>, bool) = (Vec::new(), false);
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_lowercase());
+                continue;
+            }
+            in_rules = true;
+            let for_us = agents.iter().any(|name| name == agent);
+            if for_us {
+                named_us = true;
+            }
+            let target = if for_us {
+                &mut ours
+            } else if agents.iter().any(|agent| agent == "*") {
+                &mut everyone
+            } else {
+                continue;
+            };
+            match key.as_str() {
+                "allow" if !value.is_empty() => target.rules.push((true, value.to_string())),
+                "disallow" if !value.is_empty() => target.rules.push((false, value.to_string())),
+                "crawl-delay" => {
+                    target.delay = value.parse::<f64>().ok().map(Duration::from_secs_f64)
+                }
+                _ => {}
+            }
+        }
+        if named_us { ours } else { everyone }
+    }
+
+    /// The longest matching rule wins, allow wins a tie.
+    fn allows(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| match pattern.strip_suffix('$') {
+                Some(exact) => glob_match(exact, &path),
+                None => glob_match(&format!("{}*", pattern), &path),
+            })
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://docs.example.com")
+            .unwrap()
+            .join(path)
+            .unwrap()
+    }
+
+    /// The sections of the `<main>` in `html`, as `(level, link, title)`.
+    fn sections(html: &str) -> Vec<(u8, String, String)> {
+        let document = Html::parse_document(html);
+        let main = document
+            .select(&Selector::parse("main").unwrap())
+            .next()
+            .unwrap();
+        let mut sections = vec![Section {
+            level: 0,
+            link: url("/page").to_string(),
+            title: "Page".to_string(),
+            html: String::new(),
+        }];
+        split_sections(main, &url("/page"), &mut sections);
+        sections
+            .into_iter()
+            .map(|s| (s.level, s.link, s.title))
+            .collect()
+    }
+
+    #[test]
+    fn glob_matches_stars_anywhere() {
+        assert!(glob_match(
+            "https://nix.dev/*",
+            "https://nix.dev/tutorials/first"
+        ));
+        assert!(glob_match("*/_sources/*", "https://nix.dev/_sources/a.txt"));
+        assert!(glob_match("/a*b*c", "/a-b-c"));
+        assert!(glob_match("/exact", "/exact"));
+        assert!(!glob_match("/exact", "/exact/more"));
+        assert!(!glob_match("/a*b*c", "/a-c-b"));
+        // The end of the pattern can't reuse what the middle already matched.
+        assert!(!glob_match("/a*aa", "/aa"));
+    }
+
+    #[test]
+    fn robots_disallow_prefixes_for_everyone() {
+        let robots = Robots::parse(
+            "# comment\nUser-agent: *\nDisallow: /private # trailing\nCrawl-delay: 2.5\n",
+            "ngl",
+        );
+        assert!(robots.allows(&url("/docs/intro")));
+        assert!(!robots.allows(&url("/private")));
+        assert!(!robots.allows(&url("/private/notes")));
+        assert_eq!(robots.delay, Some(Duration::from_millis(2500)));
+        assert!(Robots::default().allows(&url("/anything")));
+        assert!(!Robots::disallow_all().allows(&url("/")));
+    }
+
+    #[test]
+    fn robots_wildcards_and_end_anchors() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /*.pdf$\nDisallow: /search*q=\n",
+            "ngl",
+        );
+        assert!(!robots.allows(&url("/files/manual.pdf")));
+        assert!(robots.allows(&url("/files/manual.pdf.html")));
+        // The query is part of what's matched, so it keeps `$` from matching.
+        assert!(robots.allows(&url("/files/manual.pdf?download=1")));
+        assert!(!robots.allows(&url("/search?page=2&q=nix")));
+        assert!(robots.allows(&url("/search")));
+    }
+
+    #[test]
+    fn robots_longest_match_wins_and_allow_wins_ties() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /docs\nAllow: /docs/public\nAllow: /same\nDisallow: /same\n",
+            "ngl",
+        );
+        assert!(robots.allows(&url("/docs/public/intro")));
+        assert!(!robots.allows(&url("/docs/private")));
+        assert!(robots.allows(&url("/same/page")));
+
+        let robots = Robots::parse(
+            "User-agent: *\nAllow: /docs\nDisallow: /docs/drafts\n",
+            "ngl",
+        );
+        assert!(robots.allows(&url("/docs/intro")));
+        assert!(!robots.allows(&url("/docs/drafts/next")));
+    }
+
+    #[test]
+    fn robots_group_naming_us_replaces_everyone() {
+        let text =
+            "User-agent: *\nDisallow: /\n\nUser-agent: other\nUser-agent: NGL\nDisallow: /tmp\n";
+        let robots = Robots::parse(text, "ngl");
+        assert!(robots.allows(&url("/docs")));
+        assert!(!robots.allows(&url("/tmp/scratch")));
+
+        // An empty Disallow in our group still counts as naming us.
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: ngl\nDisallow:\n",
+            "ngl",
+        );
+        assert!(robots.allows(&url("/docs")));
+    }
+
+    #[test]
+    fn robots_agent_names_match_whole_tokens() {
+        let text = "User-agent: nglbot\nDisallow: /\n\nUser-agent: *\nDisallow: /private\n";
+        let robots = Robots::parse(text, "ngl");
+        assert!(robots.allows(&url("/docs")));
+        assert!(!robots.allows(&url("/private")));
+    }
+
+    #[test]
+    fn robots_groups_end_at_the_next_user_agent_after_rules() {
+        let text = "User-agent: ngl\nDisallow: /a\nUser-agent: other\nDisallow: /b\n";
+        let robots = Robots::parse(text, "ngl");
+        assert!(!robots.allows(&url("/a")));
+        assert!(robots.allows(&url("/b")));
+    }
+
+    #[test]
+    fn splits_sections_at_headings_in_nested_elements() {
+        let html = r#"<html><body><main>
+            <p>intro</p>
+            <h1>Title</h1>
+            <section><h2 id="install">Installing</h2><p>text</p>
+              <h3><a name="from-source"></a>From source</h3></section>
+            <h2>Next   steps!</h2>
+        </main></body></html>"#;
+        assert_eq!(
+            sections(html),
+            vec![
+                (0, url("/page").to_string(), "Page".to_string()),
+                (1, url("/page#title").to_string(), "Title".to_string()),
+                (
+                    2,
+                    url("/page#install").to_string(),
+                    "Installing".to_string()
+                ),
+                (
+                    3,
+                    url("/page#from-source").to_string(),
+                    "From source".to_string()
+                ),
+                (
+                    2,
+                    url("/page#next-steps").to_string(),
+                    "Next steps!".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn section_html_goes_to_the_heading_above_it() {
+        let document = Html::parse_document(
+            "<main><p>intro</p><h2>One</h2><pre>code</pre>text &amp; more</main>",
+        );
+        let main = document
+            .select(&Selector::parse("main").unwrap())
+            .next()
+            .unwrap();
+        let mut sections = vec![Section {
+            level: 0,
+            link: String::new(),
+            title: String::new(),
+            html: String::new(),
+        }];
+        split_sections(main, &url("/page"), &mut sections);
+        assert_eq!(sections[0].html, "<p>intro</p>");
+        assert_eq!(sections[1].html, "<pre>code</pre>text &amp; more");
+    }
+
+    #[test]
+    fn heading_ids_prefer_the_id_then_an_anchor_then_the_title() {
+        let document = Html::parse_fragment(
+            r#"<h2 id="own">Own</h2><h2><a id="inner">Inner</a></h2><h2>C++ &amp; Nix: 2.0</h2>"#,
+        );
+        let ids: Vec<String> = document
+            .select(&Selector::parse("h2").unwrap())
+            .map(|h| heading_id(h, &collapse(&h.text().collect::<String>())))
+            .collect();
+        assert_eq!(ids, ["own", "inner", "c-nix-2-0"]);
+    }
+
+    #[test]
+    fn reads_sitemap_locations() {
+        let xml = r#"<?xml version="1.0"?>
+            <urlset>
+              <url><loc> https://docs.example.com/a?x=1&amp;y=2 </loc></url>
+              <url><loc>https://docs.example.com/b</loc><lastmod>2024-01-01</lastmod></url>
+              <url><loc>not a url</loc></url>
+              <sitemap><loc>https://docs.example.com/more.xml</loc></sitemap>
+            </urlset>"#;
+        assert_eq!(
+            sitemap_locations(xml),
+            vec![url("/a?x=1&y=2"), url("/b"), url("/more.xml"),]
+        );
+    }
+}
@@ -0,0 +1,344 @@
+// The `html_selectors` template: options scraped from any HTML page with CSS selectors from
+// the config, for doc sites that `renderdocs` and `ndg_options_html` don't fit. `renderdocs`
+// pages, written as selectors:
+//
+// {
+//   "template": "html_selectors",
+//   "name": "plasma-manager",
+//   "source": "https://nix-community.github.io/plasma-manager/options.xhtml",
+//   "kinds": ["options", "examples"],
+//   "records": "dl.variablelist > dt",
+//   "group_siblings": true,
+//   "fields": {
+//     "name": "code.option",
+//     "description": "dd",
+//     "anchor": { "selector": "a[id]", "attribute": "id" }
+//   }
+// }
+//
+// Every element matching `records` is one option. With `group_siblings` the following sibling
+// elements, up to the next record, belong to it too (for `dt`/`dd` lists or a heading followed
+// by paragraphs). A field is a selector searched in the record, or
+// `{ "selector": ..., "attribute": ... }` to read an attribute instead of the text; without a
+// selector the attribute is read off the record element itself. Fields are `name` (required),
+// `type`, `default`, `description` (its HTML, converted to markdown), `example` and `anchor`,
+// which makes the option's `source_url` `<source>#<anchor>`.
+//
+// Examples come from the `example` field and code blocks in the description.
+use scraper::{ElementRef, Html, Selector};
+use serde::Deserialize;
+
+use crate::error::NglError;
+use crate::providers::{
+    EventChannel, ExampleRecord, OptionRecord, ProviderEvent, ProviderInformation, SyncPhase,
+};
+use crate::schema::{NGLDataKind, NGLRaw};
+use crate::utils::{ExtractedExample, extract_examples_html, fetch_source, html_to_markdown};
+
+use super::{ConfigProvider, TemplateProviderConfig};
+
+#[derive(Debug, Deserialize)]
+struct HtmlSelectorsOptions {
+    records: String,
+    #[serde(default)]
+    group_siblings: bool,
+    fields: FieldOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldOptions {
+    name: FieldSpec,
+    #[serde(rename = "type")]
+    option_type: Option<FieldSpec>,
+    default: Option<FieldSpec>,
+    description: Option<FieldSpec>,
+    example: Option<FieldSpec>,
+    anchor: Option<FieldSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FieldSpec {
+    Selector(String),
+    Attribute {
+        selector: Option<String>,
+        attribute: Option<String>,
+    },
+}
+
+/// A field spec with its selector parsed.
+struct Field {
+    selector: Option<Selector>,
+    attribute: Option<String>,
+}
+
+struct Fields {
+    name: Field,
+    option_type: Option<Field>,
+    default: Option<Field>,
+    description: Option<Field>,
+    example: Option<Field>,
+    anchor: Option<Field>,
+}
+
+pub struct HtmlSelectorsProvider {
+    info: ProviderInformation,
+    records: Selector,
+    group_siblings: bool,
+    fields: Fields,
+}
+
+struct ScrapedOption {
+    record: OptionRecord,
+    examples: Vec<ExtractedExample>,
+}
+
+impl HtmlSelectorsProvider {
+    pub fn from_config(cfg: &TemplateProviderConfig) -> Result<Self, NglError> {
+        let options: HtmlSelectorsOptions = cfg.options()?;
+        let field = |spec: FieldSpec| Field::parse(spec, &cfg.name);
+        let fields = options.fields;
+        Ok(Self {
+            info: cfg.to_provider_info(Some(&["option", "options", "example", "examples"])),
+            records: parse_selector(&options.records, &cfg.name)?,
+            group_siblings: options.group_siblings,
+            fields: Fields {
+                name: field(fields.name)?,
+                option_type: fields.option_type.map(field).transpose()?,
+                default: fields.default.map(field).transpose()?,
+                description: fields.description.map(field).transpose()?,
+                example: fields.example.map(field).transpose()?,
+                anchor: fields.anchor.map(field).transpose()?,
+            },
+        })
+    }
+
+    fn scrape(&self, html: &str) -> Vec<ScrapedOption> {
+        let document = Html::parse_document(html);
+        let base = self
+            .info
+            .source
+            .split_once('#')
+            .map_or(self.info.source.as_str(), |(base, _)| base);
+
+        let mut options = Vec::new();
+        for record in document.select(&self.records) {
+            let mut parts = vec![record];
+            if self.group_siblings {
+                parts.extend(
+                    record
+                        .next_siblings()
+                        .filter_map(ElementRef::wrap)
+                        .take_while(|sibling| !self.records.matches(sibling)),
+                );
+            }
+
+            let Some(name) = self.fields.name.text(&parts, false) else {
+                continue;
+            };
+            let description = self
+                .fields
+                .description
+                .as_ref()
+                .and_then(|field| field.html(&parts));
+            let mut examples = description
+                .as_deref()
+                .map(extract_examples_html)
+                .unwrap_or_default();
+            if let Some(field) = &self.fields.example
+                && let Some(element) = field.find(&parts)
+            {
+                // Code blocks keep their language, anything else is taken as plain code.
+                let mut found = extract_examples_html(&element.html());
+                if let Some(text) = field.text(&parts, true).filter(|_| found.is_empty()) {
+                    found.push(ExtractedExample {
+                        language: None,
+                        data: text,
+                    });
+                }
+                examples.extend(found);
+            }
+
+            let source_url = self
+                .fields
+                .anchor
+                .as_ref()
+                .and_then(|field| field.text(&parts, false))
+                .map(|anchor| match anchor.strip_prefix('#') {
+                    Some(fragment) => format!("{}#{}", base, fragment),
+                    None if anchor.contains("://") => anchor,
+                    None => format!("{}#{}", base, anchor),
+                });
+
+            options.push(ScrapedOption {
+                record: OptionRecord {
+                    name,
+                    type_signature: self
+                        .fields
+                        .option_type
+                        .as_ref()
+                        .and_then(|field| field.text(&parts, false)),
+                    default_value: self
+                        .fields
+                        .default
+                        .as_ref()
+                        .and_then(|field| field.text(&parts, true)),
+                    content: NGLRaw::Markdown(
+                        description
+                            .map(|html| html_to_markdown(&html))
+                            .unwrap_or_default(),
+                    ),
+                    source_url,
+                },
+                examples,
+            });
+        }
+        options
+    }
+
+    async fn parse_options(
+        &self,
+        channel: &EventChannel,
+        send_options: bool,
+        send_examples: bool,
+    ) -> Result<(), NglError> {
+        let html = fetch_source(&self.info.source, channel).await?;
+
+        channel.phase(SyncPhase::Parsing);
+        let options = self.scrape(&html);
+        if options.is_empty() {
+            return Err(NglError::Parse(format!(
+                "no records matching the configured selectors in {}",
+                self.info.source
+            )));
+        }
+        if send_options {
+            channel.set_total(NGLDataKind::Option, options.len());
+        }
+        if send_examples {
+            channel.set_total(
+                NGLDataKind::Example,
+                options.iter().map(|opt| opt.examples.len()).sum(),
+            );
+        }
+
+        for option in options {
+            if send_examples {
+                let link = option
+                    .record
+                    .source_url
+                    .clone()
+                    .unwrap_or_else(|| option.record.name.clone());
+                for example in option.examples {
+                    channel
+                        .send(ProviderEvent::Example(ExampleRecord {
+                            code: example.data,
+                            language: example.language,
+                            source_kind: Some(NGLDataKind::Option),
+                            source_link: Some(link.clone()),
+                        }))
+                        .await;
+                }
+            }
+            if send_options {
+                channel.send(ProviderEvent::Option(option.record)).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ConfigProvider for HtmlSelectorsProvider {
+    fn provider_info(&self) -> &ProviderInformation {
+        &self.info
+    }
+
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        let wanted = |kind| kinds.contains(&kind) && self.info.kinds.contains(&kind);
+        let send_options = wanted(NGLDataKind::Option);
+        let send_examples = wanted(NGLDataKind::Example);
+        if send_options || send_examples {
+            self.parse_options(channel, send_options, send_examples)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl Field {
+    fn parse(spec: FieldSpec, provider: &str) -> Result<Self, NglError> {
+        let (selector, attribute) = match spec {
+            FieldSpec::Selector(selector) => (Some(selector), None),
+            FieldSpec::Attribute {
+                selector,
+                attribute,
+            } => (selector, attribute),
+        };
+        if selector.is_none() && attribute.is_none() {
+            return Err(NglError::Config(format!(
+                "template provider '{}': a field needs a selector or an attribute",
+                provider
+            )));
+        }
+        Ok(Self {
+            selector: selector
+                .map(|selector| parse_selector(&selector, provider))
+                .transpose()?,
+            attribute,
+        })
+    }
+
+    /// The first element in the record the field points at.
+    fn find<'a>(&self, parts: &[ElementRef<'a>]) -> Option<ElementRef<'a>> {
+        let Some(selector) = &self.selector else {
+            return parts.first().copied();
+        };
+        parts.iter().find_map(|part| {
+            if selector.matches(part) {
+                Some(*part)
+            } else {
+                part.select(selector).next()
+            }
+        })
+    }
+
+    /// The attribute, or the element's text with whitespace collapsed unless `verbatim`.
+    fn text(&self, parts: &[ElementRef], verbatim: bool) -> Option<String> {
+        let element = self.find(parts)?;
+        let text = match &self.attribute {
+            Some(attribute) => element.value().attr(attribute)?.trim().to_string(),
+            None if verbatim => element.text().collect::<String>().trim().to_string(),
+            // Joined before collapsing, text split across inline elements (`<code>a</code>.b`)
+            // stays in one piece.
+            None => element
+                .text()
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// The attribute, or the element's inner HTML.
+    fn html(&self, parts: &[ElementRef]) -> Option<String> {
+        match &self.attribute {
+            Some(_) => self.text(parts, true),
+            None => Some(self.find(parts)?.inner_html()).filter(|html| !html.trim().is_empty()),
+        }
+    }
+}
+
+fn parse_selector(selector: &str, provider: &str) -> Result<Selector, NglError> {
+    Selector::parse(selector).map_err(|e| {
+        NglError::Config(format!(
+            "template provider '{}': invalid selector '{}': {:?}",
+            provider, selector, e
+        ))
+    })
+}
@@ -0,0 +1,697 @@
+// The `json_mapping` template: maps any JSON dump onto options, functions, packages or guides
+// with a selector for the record list and a mapping per field, no code needed.
+//
+// `ndg_search_options` written as a mapping (hjem's search data is a list of
+// `{"title": "Option: ...", "content": "..."}`, titles without the prefix aren't options):
+//
+// {
+//   "template": "json_mapping",
+//   "name": "hjem",
+//   "source": "https://hjem.feel-co.org/assets/search-data.json",
+//   "kinds": ["options"],
+//   "records": "$[*]",
+//   "kind": "option",
+//   "fields": {
+//     "name": { "path": "title", "transforms": [{ "require_prefix": "Option: " }, "decode_html"] },
+//     "content": { "path": "content", "transforms": ["decode_html"] }
+//   }
+// }
+//
+// and roughly `options_json`, where options are keyed by name:
+//
+//   "records": "$.*",
+//   "kind": "option",
+//   "fields": { "name": "@key", "type_signature": "type", "default_value": "default.text",
+//               "content": "description" }
+//
+// Selectors are a small JSONPath subset: `$` is the document, `.key` or `['key']` a field,
+// `[0]` an index and `*` / `[*]` every element of an array or object. Field paths are relative
+// to the record; `@` is the record itself and `@key` the key it had in its parent object.
+// A field is a path, or `{ "path": ..., "transforms": [...] }`, or `{ "value": ... }` for a
+// constant. A record whose required fields (`name`, or a guide's `link` and `title`) come out
+// empty is skipped.
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::NglError;
+use crate::providers::{
+    EventChannel, ExampleRecord, FunctionRecord, GuideRecord, OptionRecord, PackageRecord,
+    ProviderEvent, ProviderInformation, SyncPhase,
+};
+use crate::schema::{NGLDataKind, NGLRaw};
+use crate::utils::{
+    extract_examples_html, extract_examples_markdown, fetch_source, html_to_markdown,
+};
+
+use super::{ConfigProvider, TemplateProviderConfig};
+
+#[derive(Debug, Deserialize)]
+struct JsonMappingOptions {
+    /// Selects the records, e.g. `$.options[*]`.
+    records: String,
+    /// What each record becomes.
+    kind: MappedKind,
+    /// Format of the mapped `content`.
+    #[serde(default)]
+    format: ContentFormat,
+    fields: HashMap<String, FieldMapping>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MappedKind {
+    Option,
+    Function,
+    Package,
+    Guide,
+}
+
+impl MappedKind {
+    fn data_kind(self) -> NGLDataKind {
+        match self {
+            MappedKind::Option => NGLDataKind::Option,
+            MappedKind::Function => NGLDataKind::Function,
+            MappedKind::Package => NGLDataKind::Package,
+            MappedKind::Guide => NGLDataKind::Guide,
+        }
+    }
+
+    fn fields(self) -> (&'static [&'static str], &'static [&'static str]) {
+        match self {
+            MappedKind::Option => (
+                &["name"],
+                &[
+                    "name",
+                    "type_signature",
+                    "default_value",
+                    "content",
+                    "source_url",
+                ],
+            ),
+            MappedKind::Function => (
+                &["name"],
+                &[
+                    "name",
+                    "signature",
+                    "content",
+                    "source_url",
+                    "source_code_url",
+                    "aliases",
+                ],
+            ),
+            MappedKind::Package => (
+                &["name"],
+                &[
+                    "name",
+                    "version",
+                    "content",
+                    "description",
+                    "homepage",
+                    "license",
+                    "source_code_url",
+                    "broken",
+                    "unfree",
+                ],
+            ),
+            MappedKind::Guide => (&["link", "title"], &["link", "title", "content"]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ContentFormat {
+    #[default]
+    Markdown,
+    Html,
+    Plain,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FieldMapping {
+    Path(String),
+    Mapped {
+        path: Option<String>,
+        value: Option<Value>,
+        #[serde(default)]
+        transforms: Vec<Transform>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Transform {
+    /// Removes the prefix if it's there.
+    StripPrefix(String),
+    StripSuffix(String),
+    /// Removes the prefix, and empties the field if it isn't there.
+    RequirePrefix(String),
+    Prepend(String),
+    Append(String),
+    Trim,
+    HtmlToMarkdown,
+    /// Decodes entities like `&lt;`.
+    DecodeHtml,
+    /// Joins an array into one string with the given separator.
+    Join(String),
+    /// The value as JSON text.
+    Json,
+}
+
+/// A parsed field mapping.
+struct Field {
+    source: FieldSource,
+    transforms: Vec<Transform>,
+}
+
+enum FieldSource {
+    Path(Vec<Step>),
+    Key,
+    Value(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+pub struct JsonMappingProvider {
+    info: ProviderInformation,
+    records: Vec<Step>,
+    kind: MappedKind,
+    format: ContentFormat,
+    fields: HashMap<String, Field>,
+}
+
+impl JsonMappingProvider {
+    pub fn from_config(cfg: &TemplateProviderConfig) -> Result<Self, NglError> {
+        let options: JsonMappingOptions = cfg.options()?;
+        let invalid =
+            |msg: String| NglError::Config(format!("template provider '{}': {}", cfg.name, msg));
+
+        let (required, known) = options.kind.fields();
+        let mut fields = HashMap::new();
+        for (name, mapping) in options.fields {
+            if !known.contains(&name.as_str()) {
+                return Err(invalid(format!(
+                    "unknown field '{}', {:?} records have: {}",
+                    name,
+                    options.kind,
+                    known.join(", ")
+                )));
+            }
+            let field = Field::parse(mapping).map_err(|e| invalid(format!("{}: {}", name, e)))?;
+            fields.insert(name, field);
+        }
+        if let Some(missing) = required.iter().find(|name| !fields.contains_key(**name)) {
+            return Err(invalid(format!("field '{}' needs a mapping", missing)));
+        }
+
+        Ok(Self {
+            info: cfg.to_provider_info(None),
+            records: parse_path(&options.records)
+                .map_err(|e| invalid(format!("records: {}", e)))?,
+            kind: options.kind,
+            format: options.format,
+            fields,
+        })
+    }
+
+    fn string(&self, name: &str, key: Option<&str>, record: &Value) -> Option<String> {
+        let value = self.fields.get(name)?.resolve(key, record);
+        match value {
+            Value::Null => None,
+            Value::String(s) if s.is_empty() => None,
+            Value::String(s) => Some(s),
+            other => Some(other.to_string()),
+        }
+    }
+
+    fn strings(&self, name: &str, key: Option<&str>, record: &Value) -> Vec<String> {
+        match self
+            .fields
+            .get(name)
+            .map(|field| field.resolve(key, record))
+        {
+            Some(Value::Array(values)) => values.iter().filter_map(as_string).collect(),
+            Some(value) => as_string(&value).into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn flag(&self, name: &str, key: Option<&str>, record: &Value) -> bool {
+        match self
+            .fields
+            .get(name)
+            .map(|field| field.resolve(key, record))
+        {
+            Some(Value::Bool(b)) => b,
+            Some(Value::String(s)) => s == "true",
+            _ => false,
+        }
+    }
+
+    fn content(&self, text: String) -> NGLRaw {
+        match self.format {
+            ContentFormat::Markdown => NGLRaw::Markdown(text),
+            ContentFormat::Html => NGLRaw::HTML(text),
+            ContentFormat::Plain => NGLRaw::PlainText(text),
+        }
+    }
+
+    /// The record as an event, with the identifier examples from it should link to.
+    fn event(&self, key: Option<&str>, record: &Value) -> Option<(ProviderEvent, String)> {
+        let content = self.string("content", key, record).unwrap_or_default();
+        Some(match self.kind {
+            MappedKind::Option => {
+                let name = self.string("name", key, record)?;
+                let option = OptionRecord {
+                    name: name.clone(),
+                    type_signature: self.string("type_signature", key, record),
+                    default_value: self.string("default_value", key, record),
+                    content: self.content(content),
+                    source_url: self.string("source_url", key, record),
+                };
+                (ProviderEvent::Option(option), name)
+            }
+            MappedKind::Function => {
+                let name = self.string("name", key, record)?;
+                let function = FunctionRecord {
+                    name: name.clone(),
+                    signature: self.string("signature", key, record),
+                    content: self.content(content),
+                    source_url: self.string("source_url", key, record),
+                    source_code_url: self.string("source_code_url", key, record),
+                    aliases: self.strings("aliases", key, record),
+                };
+                (ProviderEvent::Function(function), name)
+            }
+            MappedKind::Package => {
+                let name = self.string("name", key, record)?;
+                let package = PackageRecord {
+                    name: name.clone(),
+                    version: self.string("version", key, record),
+                    content: self.content(content),
+                    description: self.string("description", key, record),
+                    homepage: self.string("homepage", key, record),
+                    license: self.string("license", key, record),
+                    source_code_url: self.string("source_code_url", key, record),
+                    broken: self.flag("broken", key, record),
+                    unfree: self.flag("unfree", key, record),
+                };
+                (ProviderEvent::Package(package), name)
+            }
+            MappedKind::Guide => {
+                let link = self.string("link", key, record)?;
+                let guide = GuideRecord {
+                    link: link.clone(),
+                    title: self.string("title", key, record)?,
+                    content: self.content(content),
+                };
+                (ProviderEvent::Guide(guide), link)
+            }
+        })
+    }
+
+    async fn parse_records(
+        &self,
+        channel: &EventChannel,
+        send_records: bool,
+        send_examples: bool,
+    ) -> Result<(), NglError> {
+        let json = fetch_source(&self.info.source, channel).await?;
+        channel.phase(SyncPhase::Parsing);
+        let document: Value = serde_json::from_str(&json)
+            .map_err(|e| NglError::Parse(format!("Failed to parse {}: {}", self.info.source, e)))?;
+
+        let records = select(&self.records, &document);
+        if send_records {
+            channel.set_total(self.kind.data_kind(), records.len());
+        }
+        for (key, record) in records {
+            let Some((event, link)) = self.event(key.as_deref(), record) else {
+                continue;
+            };
+            if send_examples {
+                let content = match &event {
+                    ProviderEvent::Option(r) => &r.content,
+                    ProviderEvent::Function(r) => &r.content,
+                    ProviderEvent::Package(r) => &r.content,
+                    ProviderEvent::Guide(r) => &r.content,
+                    _ => unreachable!("json_mapping only maps the kinds above"),
+                };
+                let examples = match content {
+                    NGLRaw::Markdown(text) => extract_examples_markdown(text),
+                    NGLRaw::HTML(text) => extract_examples_html(text),
+                    NGLRaw::PlainText(_) => Vec::new(),
+                };
+                for example in examples {
+                    channel
+                        .send(ProviderEvent::Example(ExampleRecord {
+                            code: example.data,
+                            language: example.language,
+                            source_kind: Some(self.kind.data_kind()),
+                            source_link: Some(link.clone()),
+                        }))
+                        .await;
+                }
+            }
+            if send_records {
+                channel.send(event).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ConfigProvider for JsonMappingProvider {
+    fn provider_info(&self) -> &ProviderInformation {
+        &self.info
+    }
+
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        let wanted = |kind| kinds.contains(&kind) && self.info.kinds.contains(&kind);
+        let send_records = wanted(self.kind.data_kind());
+        let send_examples = wanted(NGLDataKind::Example);
+        if send_records || send_examples {
+            self.parse_records(channel, send_records, send_examples)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl Field {
+    fn parse(mapping: FieldMapping) -> Result<Self, String> {
+        let (path, value, transforms) = match mapping {
+            FieldMapping::Path(path) => (Some(path), None, Vec::new()),
+            FieldMapping::Mapped {
+                path,
+                value,
+                transforms,
+            } => (path, value, transforms),
+        };
+        let source = match (path, value) {
+            (Some(_), Some(_)) => return Err("takes a path or a value, not both".to_string()),
+            (None, Some(value)) => FieldSource::Value(value),
+            (Some(path), None) if path == "@key" => FieldSource::Key,
+            (Some(path), None) => FieldSource::Path(parse_path(&path)?),
+            (None, None) => return Err("needs a path or a value".to_string()),
+        };
+        Ok(Self { source, transforms })
+    }
+
+    fn resolve(&self, key: Option<&str>, record: &Value) -> Value {
+        let value = match &self.source {
+            FieldSource::Key => key
+                .map(|k| Value::String(k.to_string()))
+                .unwrap_or_default(),
+            FieldSource::Value(value) => value.clone(),
+            FieldSource::Path(steps) if steps.contains(&Step::Wildcard) => Value::Array(
+                select(steps, record)
+                    .into_iter()
+                    .map(|(_, v)| v.clone())
+                    .collect(),
+            ),
+            FieldSource::Path(steps) => select(steps, record)
+                .first()
+                .map(|(_, v)| (*v).clone())
+                .unwrap_or_default(),
+        };
+        self.transforms
+            .iter()
+            .fold(value, |value, t| t.apply(value))
+    }
+}
+
+impl Transform {
+    fn apply(&self, value: Value) -> Value {
+        let text = match (self, value) {
+            (Transform::Json, Value::Null) => return Value::Null,
+            (Transform::Json, value) => return Value::String(value.to_string()),
+            (Transform::Join(separator), Value::Array(values)) => {
+                let parts: Vec<String> = values.iter().filter_map(as_string).collect();
+                return Value::String(parts.join(separator));
+            }
+            (_, Value::String(text)) => text,
+            (_, value) => return value,
+        };
+        Value::String(match self {
+            Transform::StripPrefix(prefix) => match text.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest.to_string(),
+                None => text,
+            },
+            Transform::StripSuffix(suffix) => match text.strip_suffix(suffix.as_str()) {
+                Some(rest) => rest.to_string(),
+                None => text,
+            },
+            Transform::RequirePrefix(prefix) => match text.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest.to_string(),
+                None => return Value::Null,
+            },
+            Transform::Prepend(prefix) => format!("{}{}", prefix, text),
+            Transform::Append(suffix) => format!("{}{}", text, suffix),
+            Transform::Trim => text.trim().to_string(),
+            Transform::HtmlToMarkdown => html_to_markdown(&text),
+            Transform::DecodeHtml => html_escape::decode_html_entities(&text).into_owned(),
+            Transform::Join(_) | Transform::Json => text,
+        })
+    }
+}
+
+fn as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Parses a selector like `$.data.options[*]` or `meta['long.key'][0]`.
+fn parse_path(path: &str) -> Result<Vec<Step>, String> {
+    let path = path.trim();
+    let path = path.strip_prefix('$').unwrap_or(path);
+    if path.is_empty() || path == "@" {
+        return Ok(Vec::new());
+    }
+
+    let mut steps = Vec::new();
+    let mut chars = path.chars().peekable();
+    let mut expect_name = !path.starts_with(['.', '[']);
+    while expect_name || chars.peek().is_some() {
+        if !expect_name {
+            match chars.next() {
+                Some('.') => {}
+                Some('[') => {
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(format!("unclosed '[' in '{}'", path)),
+                        }
+                    }
+                    let inner = inner.trim();
+                    steps.push(if inner == "*" {
+                        Step::Wildcard
+                    } else if let Some(key) = inner
+                        .strip_prefix('\'')
+                        .and_then(|k| k.strip_suffix('\''))
+                        .or_else(|| inner.strip_prefix('"').and_then(|k| k.strip_suffix('"')))
+                    {
+                        Step::Key(key.to_string())
+                    } else {
+                        Step::Index(
+                            inner
+                                .parse()
+                                .map_err(|_| format!("bad index [{}] in '{}'", inner, path))?,
+                        )
+                    });
+                    continue;
+                }
+                Some(c) => return Err(format!("unexpected '{}' in '{}'", c, path)),
+                None => break,
+            }
+        }
+        expect_name = false;
+
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| !matches!(c, '.' | '[' | ']')) {
+            name.push(c);
+        }
+        match name.as_str() {
+            "" => return Err(format!("empty field name in '{}'", path)),
+            "*" => steps.push(Step::Wildcard),
+            _ => steps.push(Step::Key(name)),
+        }
+    }
+    Ok(steps)
+}
+
+/// Everything `steps` reaches from `value`, with the object key each match was found under.
+fn select<'a>(steps: &[Step], value: &'a Value) -> Vec<(Option<String>, &'a Value)> {
+    let mut current = vec![(None, value)];
+    for step in steps {
+        current = current
+            .into_iter()
+            .flat_map(|(_, value)| match (step, value) {
+                (Step::Key(key), Value::Object(map)) => map
+                    .get(key)
+                    .map(|v| (Some(key.clone()), v))
+                    .into_iter()
+                    .collect(),
+                (Step::Index(i), Value::Array(values)) => {
+                    values.get(*i).map(|v| (None, v)).into_iter().collect()
+                }
+                (Step::Wildcard, Value::Array(values)) => {
+                    values.iter().map(|v| (None, v)).collect()
+                }
+                (Step::Wildcard, Value::Object(map)) => {
+                    map.iter().map(|(k, v)| (Some(k.clone()), v)).collect()
+                }
+                _ => Vec::new(),
+            })
+            .collect();
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn keys(steps: &[&str]) -> Vec<Step> {
+        steps.iter().map(|k| Step::Key(k.to_string())).collect()
+    }
+
+    #[test]
+    fn parses_fields_indexes_and_wildcards() {
+        assert_eq!(parse_path("$").unwrap(), vec![]);
+        assert_eq!(parse_path("@").unwrap(), vec![]);
+        assert_eq!(parse_path("$[*]").unwrap(), vec![Step::Wildcard]);
+        assert_eq!(parse_path("$.*").unwrap(), vec![Step::Wildcard]);
+        assert_eq!(parse_path("$[0]").unwrap(), vec![Step::Index(0)]);
+        assert_eq!(
+            parse_path("default.text").unwrap(),
+            keys(&["default", "text"])
+        );
+        assert_eq!(
+            parse_path("$.data.options[*]").unwrap(),
+            vec![
+                Step::Key("data".to_string()),
+                Step::Key("options".to_string()),
+                Step::Wildcard,
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_quoted_keys() {
+        assert_eq!(
+            parse_path("$['quoted.key']").unwrap(),
+            keys(&["quoted.key"])
+        );
+        assert_eq!(
+            parse_path("$[\"quoted.key\"]").unwrap(),
+            keys(&["quoted.key"])
+        );
+        assert_eq!(
+            parse_path("meta['long.key'][0]").unwrap(),
+            vec![
+                Step::Key("meta".to_string()),
+                Step::Key("long.key".to_string()),
+                Step::Index(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        assert!(parse_path("$.a..b").is_err());
+        assert!(parse_path("$.").is_err());
+        assert!(parse_path("$[x]").is_err());
+        assert!(parse_path("$[-1]").is_err());
+        assert!(parse_path("$[0").is_err());
+        assert!(parse_path("$.a]").is_err());
+    }
+
+    #[test]
+    fn selects_array_elements() {
+        let value = json!({ "options": [{ "name": "a" }, { "name": "b" }] });
+        let names: Vec<&Value> = select(&parse_path("$.options[*].name").unwrap(), &value)
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect();
+        assert_eq!(names, vec![&json!("a"), &json!("b")]);
+
+        let second = select(&parse_path("$.options[1]").unwrap(), &value);
+        assert_eq!(second, vec![(None, &json!({ "name": "b" }))]);
+        assert!(select(&parse_path("$.options[2]").unwrap(), &value).is_empty());
+    }
+
+    #[test]
+    fn selects_object_values_with_their_keys() {
+        let value = json!({ "services.foo.enable": { "type": "boolean" } });
+        let selected = select(&parse_path("$.*").unwrap(), &value);
+        assert_eq!(
+            selected,
+            vec![(
+                Some("services.foo.enable".to_string()),
+                &json!({ "type": "boolean" })
+            )]
+        );
+        let quoted = select(
+            &parse_path("$['services.foo.enable'].type").unwrap(),
+            &value,
+        );
+        assert_eq!(quoted, vec![(Some("type".to_string()), &json!("boolean"))]);
+    }
+
+    #[test]
+    fn selects_nothing_on_mismatched_shapes() {
+        let value = json!({ "a": [1, 2] });
+        assert!(select(&parse_path("$.a.b").unwrap(), &value).is_empty());
+        assert!(select(&parse_path("$[0]").unwrap(), &value).is_empty());
+        assert!(select(&parse_path("$.missing").unwrap(), &value).is_empty());
+    }
+
+    #[test]
+    fn resolves_key_fields() {
+        let value = json!({ "services.foo.enable": { "type": "boolean" } });
+        let field = Field::parse(FieldMapping::Path("@key".to_string())).unwrap();
+        let (key, record) = select(&parse_path("$.*").unwrap(), &value).remove(0);
+        assert_eq!(
+            field.resolve(key.as_deref(), record),
+            json!("services.foo.enable")
+        );
+        assert_eq!(field.resolve(None, record), Value::Null);
+    }
+
+    #[test]
+    fn rejects_malformed_fields() {
+        let both = FieldMapping::Mapped {
+            path: Some("a".to_string()),
+            value: Some(json!(1)),
+            transforms: Vec::new(),
+        };
+        assert!(Field::parse(both).is_err());
+        let neither = FieldMapping::Mapped {
+            path: None,
+            value: None,
+            transforms: Vec::new(),
+        };
+        assert!(Field::parse(neither).is_err());
+        assert!(Field::parse(FieldMapping::Path("a[".to_string())).is_err());
+    }
+}
@@ -0,0 +1,758 @@
+// The `mdbook` template: an mdBook as guides, one per chapter and one per heading inside it,
+// hung together in the book's chapter hierarchy.
+//
+// {
+//   "template": "mdbook",
+//   "name": "nixvim-book",
+//   "source": "https://nix-community.github.io/nixvim/",
+//   "kinds": ["guides", "examples"]
+// }
+//
+// `source` is either the book's sources (a directory or URL holding `SUMMARY.md`, or `src/`
+// with it inside) or the built book (holding `searchindex.json`), locally or where it's
+// published. Sources give the chapters' markdown; a built book only has the text of each
+// section in its search index, so code blocks, and the examples taken from them, are lost.
+// The chapter hierarchy comes from `SUMMARY.md`, or the built book's sidebar.
+//
+// For sources, `"url": "https://example.com/book/"` makes guides link to the published pages
+// instead of the markdown files.
+use std::collections::HashMap;
+
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde::Deserialize;
+
+use crate::error::NglError;
+use crate::providers::{
+    EventChannel, ExampleRecord, GuideLink, GuideRecord, ProviderEvent, ProviderInformation,
+    SyncPhase,
+};
+use crate::schema::{NGLDataKind, NGLRaw};
+use crate::utils::{extract_examples_markdown, fetch_source, is_url};
+
+use super::{ConfigProvider, TemplateProviderConfig};
+
+#[derive(Debug, Deserialize)]
+struct MdBookOptions {
+    /// Where the book is published, for links from guides read from sources.
+    url: Option<String>,
+}
+
+pub struct MdBookProvider {
+    info: ProviderInformation,
+    source: String,
+    url: Option<String>,
+}
+
+/// An entry of the table of contents.
+struct Chapter {
+    title: String,
+    /// Relative to the book, `None` for draft chapters.
+    path: Option<String>,
+    /// Index of the enclosing chapter.
+    parent: Option<usize>,
+}
+
+/// A guide and the link of the guide it belongs under.
+struct BookGuide {
+    link: String,
+    title: String,
+    content: NGLRaw,
+    parent: Option<String>,
+}
+
+/// One entry of mdBook's search index, a heading and the text up to the next one.
+struct IndexedSection {
+    url: String,
+    title: String,
+    body: String,
+}
+
+impl MdBookProvider {
+    pub fn from_config(cfg: &TemplateProviderConfig) -> Result<Self, NglError> {
+        let options: MdBookOptions = cfg.options()?;
+        let source = if is_url(&cfg.source) {
+            cfg.source.clone()
+        } else {
+            cfg.dir.join(&cfg.source).to_string_lossy().into_owned()
+        };
+        Ok(Self {
+            info: cfg.to_provider_info(Some(&["guide", "guides", "example", "examples"])),
+            source,
+            url: options.url,
+        })
+    }
+
+    async fn parse_book(
+        &self,
+        channel: &EventChannel,
+        send_guides: bool,
+        send_examples: bool,
+    ) -> Result<(), NglError> {
+        let guides = match self.find_summary(channel).await {
+            Some((src, summary)) => self.read_sources(channel, &src, &summary).await?,
+            None => self.read_build(channel).await?,
+        };
+
+        if send_guides {
+            channel.set_total(NGLDataKind::Guide, guides.len());
+        }
+        for guide in guides {
+            if send_examples && let NGLRaw::Markdown(markdown) = &guide.content {
+                for example in extract_examples_markdown(markdown) {
+                    channel
+                        .send(ProviderEvent::Example(ExampleRecord {
+                            code: example.data,
+                            language: example.language,
+                            source_kind: Some(NGLDataKind::Guide),
+                            source_link: Some(guide.link.clone()),
+                        }))
+                        .await;
+                }
+            }
+            if !send_guides {
+                continue;
+            }
+            if let Some(parent) = guide.parent {
+                channel
+                    .send(ProviderEvent::GuideXref(GuideLink {
+                        parent,
+                        child: guide.link.clone(),
+                    }))
+                    .await;
+            }
+            channel
+                .send(ProviderEvent::Guide(GuideRecord {
+                    link: guide.link,
+                    title: guide.title,
+                    content: guide.content,
+                }))
+                .await;
+        }
+        Ok(())
+    }
+
+    /// The directory holding `SUMMARY.md` and its contents, if `source` is the book's sources.
+    async fn find_summary(&self, channel: &EventChannel) -> Option<(String, String)> {
+        for src in [self.source.clone(), join(&self.source, "src/")] {
+            let path = join(&src, "SUMMARY.md");
+            if !is_url(&path) && !std::path::Path::new(&path).is_file() {
+                continue;
+            }
+            if let Ok(summary) = fetch_source(&path, channel).await {
+                return Some((src, summary));
+            }
+        }
+        None
+    }
+
+    async fn read_sources(
+        &self,
+        channel: &EventChannel,
+        src: &str,
+        summary: &str,
+    ) -> Result<Vec<BookGuide>, NglError> {
+        channel.phase(SyncPhase::Parsing);
+        let chapters = parse_summary(summary);
+        if chapters.is_empty() {
+            return Err(NglError::Parse(format!("no chapters in {}SUMMARY.md", src)));
+        }
+
+        let mut links: Vec<Option<String>> = Vec::with_capacity(chapters.len());
+        let mut guides = Vec::new();
+        for chapter in &chapters {
+            let Some(path) = &chapter.path else {
+                links.push(None);
+                continue;
+            };
+            let link = match &self.url {
+                Some(url) => join(url, &html_path(path)),
+                None => join(src, path),
+            };
+            links.push(Some(link.clone()));
+            let markdown = fetch_source(&join(src, path), channel).await?;
+            channel.phase(SyncPhase::Parsing);
+
+            let parent = enclosing_link(&chapters, &links, chapter.parent);
+            guides.extend(split_markdown(&markdown, &link, &chapter.title, parent));
+        }
+        Ok(guides)
+    }
+
+    async fn read_build(&self, channel: &EventChannel) -> Result<Vec<BookGuide>, NglError> {
+        let index = match fetch_source(&join(&self.source, "searchindex.json"), channel).await {
+            Ok(index) => index,
+            Err(_) => {
+                let script = fetch_source(&join(&self.source, "searchindex.js"), channel)
+                    .await
+                    .map_err(|e| {
+                        NglError::Parse(format!(
+                            "{} has neither SUMMARY.md nor a search index: {}",
+                            self.source, e
+                        ))
+                    })?;
+                unwrap_search_script(&script)
+            }
+        };
+        // Newer books render the sidebar into toc.html, older ones into every page.
+        let toc = match fetch_source(&join(&self.source, "toc.html"), channel).await {
+            Ok(toc) => toc,
+            Err(_) => fetch_source(&join(&self.source, "index.html"), channel).await?,
+        };
+
+        channel.phase(SyncPhase::Parsing);
+        let sections = parse_search_index(&index)?;
+        let chapters = parse_sidebar(&toc);
+
+        let mut links: Vec<Option<String>> = Vec::with_capacity(chapters.len());
+        let mut by_page: HashMap<String, usize> = HashMap::new();
+        for (i, chapter) in chapters.iter().enumerate() {
+            links.push(chapter.path.as_ref().map(|path| join(&self.source, path)));
+            if let Some(path) = &chapter.path {
+                by_page.entry(path.clone()).or_insert(i);
+            }
+        }
+
+        // The first section of a page is the chapter itself, the rest are its headings.
+        let mut guides = Vec::new();
+        let mut seen_pages: HashMap<String, String> = HashMap::new();
+        for section in sections {
+            let (page, _) = section
+                .url
+                .split_once('#')
+                .unwrap_or((section.url.as_str(), ""));
+            let page_link = join(&self.source, page);
+            if let Some(chapter_link) = seen_pages.get(page) {
+                guides.push(BookGuide {
+                    link: join(&self.source, &section.url),
+                    title: section.title,
+                    content: NGLRaw::PlainText(section.body),
+                    parent: Some(chapter_link.clone()),
+                });
+                continue;
+            }
+
+            let chapter = by_page.get(page).map(|&i| &chapters[i]);
+            seen_pages.insert(page.to_string(), page_link.clone());
+            guides.push(BookGuide {
+                link: page_link,
+                title: chapter.map_or(section.title, |c| c.title.clone()),
+                content: NGLRaw::PlainText(section.body),
+                parent: chapter.and_then(|c| enclosing_link(&chapters, &links, c.parent)),
+            });
+        }
+        if guides.is_empty() {
+            return Err(NglError::Parse(format!(
+                "the search index of {} is empty",
+                self.source
+            )));
+        }
+        Ok(guides)
+    }
+}
+
+impl ConfigProvider for MdBookProvider {
+    fn provider_info(&self) -> &ProviderInformation {
+        &self.info
+    }
+
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        let wanted = |kind| kinds.contains(&kind) && self.info.kinds.contains(&kind);
+        let send_guides = wanted(NGLDataKind::Guide);
+        let send_examples = wanted(NGLDataKind::Example);
+        if send_guides || send_examples {
+            self.parse_book(channel, send_guides, send_examples).await?;
+        }
+        Ok(())
+    }
+}
+
+/// `path` relative to `base`, a URL or a directory.
+fn join(base: &str, path: &str) -> String {
+    if is_url(base) {
+        let base = if base.ends_with('/') {
+            base.to_string()
+        } else {
+            format!("{}/", base)
+        };
+        match Url::parse(&base).and_then(|base| base.join(path)) {
+            Ok(url) => url.to_string(),
+            Err(_) => format!("{}{}", base, path),
+        }
+    } else {
+        std::path::Path::new(base)
+            .join(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Where mdBook renders a chapter's markdown file.
+fn html_path(path: &str) -> String {
+    match path.strip_suffix("README.md") {
+        Some(dir) => format!("{}index.html", dir),
+        None => match path.strip_suffix(".md") {
+            Some(stem) => format!("{}.html", stem),
+            None => path.to_string(),
+        },
+    }
+}
+
+/// The link of the closest non-draft chapter at or above `index`.
+fn enclosing_link(
+    chapters: &[Chapter],
+    links: &[Option<String>],
+    mut index: Option<usize>,
+) -> Option<String> {
+    while let Some(i) = index {
+        if let Some(Some(link)) = links.get(i) {
+            return Some(link.clone());
+        }
+        index = chapters[i].parent;
+    }
+    None
+}
+
+/// The chapters of a `SUMMARY.md`, nested by the indentation of their list items.
+fn parse_summary(summary: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    // (indentation, chapter index) of the open list items.
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    for line in summary.lines() {
+        let trimmed = line.trim_start();
+        let indent: usize = line[..line.len() - trimmed.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        if trimmed.starts_with('#') {
+            // A part title closes every open chapter.
+            stack.clear();
+            continue;
+        }
+        let item = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+            .or_else(|| {
+                let digits = trimmed.find(|c: char| !c.is_ascii_digit())?;
+                trimmed[digits..].strip_prefix(". ").filter(|_| digits > 0)
+            });
+        let (entry, indent) = match item {
+            Some(item) => (item.trim(), indent),
+            // Prefix and suffix chapters are plain links outside the list.
+            None if trimmed.starts_with('[') => (trimmed.trim(), 0),
+            None => continue,
+        };
+        let Some((title, path)) = entry
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once("]("))
+            .and_then(|(title, rest)| Some((title, rest.strip_suffix(')')?)))
+        else {
+            continue;
+        };
+
+        while stack.last().is_some_and(|(open, _)| *open >= indent) {
+            stack.pop();
+        }
+        let path = path.trim();
+        chapters.push(Chapter {
+            title: title.to_string(),
+            path: (!path.is_empty()).then(|| path.split('#').next().unwrap_or(path).to_string()),
+            parent: stack.last().map(|(_, i)| *i),
+        });
+        stack.push((indent, chapters.len() - 1));
+    }
+    chapters
+}
+
+/// Splits a chapter at its headings. The chapter is titled `title` and keeps the text before
+/// its second heading, the other headings become guides under the heading above them.
+fn split_markdown(
+    markdown: &str,
+    link: &str,
+    title: &str,
+    parent: Option<String>,
+) -> Vec<BookGuide> {
+    let mut guides = vec![BookGuide {
+        link: link.to_string(),
+        title: title.to_string(),
+        content: NGLRaw::Markdown(String::new()),
+        parent,
+    }];
+    let mut ancestors: Vec<(usize, String)> = vec![(0, link.to_string())];
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut body = String::new();
+    let mut in_fence = false;
+    let mut first_heading = true;
+
+    let flush = |guides: &mut Vec<BookGuide>, body: &mut String| {
+        if let Some(NGLRaw::Markdown(content)) = guides.last_mut().map(|g| &mut g.content) {
+            content.push_str(body.trim());
+        }
+        body.clear();
+    };
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let level = line.chars().take_while(|c| *c == '#').count();
+        let heading = (!in_fence && (1..=6).contains(&level))
+            .then(|| line[level..].strip_prefix(' '))
+            .flatten();
+        let Some(heading) = heading else {
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        };
+
+        let (text, id) = heading_id(heading.trim().trim_end_matches('#').trim(), &mut ids);
+        if first_heading {
+            // The chapter's own heading, usually its title again.
+            first_heading = false;
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        }
+        flush(&mut guides, &mut body);
+
+        while ancestors.last().is_some_and(|(l, _)| *l >= level) {
+            ancestors.pop();
+        }
+        let section_link = format!("{}#{}", link, id);
+        guides.push(BookGuide {
+            link: section_link.clone(),
+            title: text,
+            content: NGLRaw::Markdown(format!("{}\n", line)),
+            parent: ancestors.last().map(|(_, link)| link.clone()),
+        });
+        ancestors.push((level, section_link));
+    }
+    flush(&mut guides, &mut body);
+    guides
+}
+
+/// A heading's text and the anchor mdBook gives it: `{#id}` if it has one, else its text
+/// lowercased with spaces turned into dashes, numbered when it repeats.
+fn heading_id(heading: &str, ids: &mut HashMap<String, usize>) -> (String, String) {
+    if let Some((text, rest)) = heading.rsplit_once("{#")
+        && let Some(id) = rest.strip_suffix('}')
+    {
+        return (text.trim().to_string(), id.trim().to_string());
+    }
+    let text: String = heading
+        .chars()
+        .filter(|c| !matches!(c, '`' | '*'))
+        .collect();
+    let slug: String = text
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '_' || c == '-' => Some(c.to_ascii_lowercase()),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect();
+    let count = ids.entry(slug.clone()).or_insert(0);
+    let id = match *count {
+        0 => slug,
+        n => format!("{}-{}", slug, n),
+    };
+    *count += 1;
+    (text, id)
+}
+
+/// The JSON inside `searchindex.js`, which wraps it in `Object.assign(window.search, ...)`,
+/// as an object literal or as a `JSON.parse('...')` string.
+fn unwrap_search_script(script: &str) -> String {
+    if let Some(start) = script.find("JSON.parse('")
+        && let Some(end) = script.rfind("')")
+    {
+        let mut json = String::new();
+        let mut chars = script[start + "JSON.parse('".len()..end].chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => json.extend(chars.next()),
+                c => json.push(c),
+            }
+        }
+        return json;
+    }
+    match (script.find('{'), script.rfind('}')) {
+        (Some(start), Some(end)) if start < end => script[start..=end].to_string(),
+        _ => script.to_string(),
+    }
+}
+
+fn parse_search_index(json: &str) -> Result<Vec<IndexedSection>, NglError> {
+    let index: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| NglError::Parse(format!("Failed to parse search index: {}", e)))?;
+    let urls = index["doc_urls"]
+        .as_array()
+        .ok_or_else(|| NglError::Parse("search index has no doc_urls".to_string()))?;
+    let docs = &index["index"]["documentStore"]["docs"];
+
+    Ok(urls
+        .iter()
+        .enumerate()
+        .filter_map(|(i, url)| {
+            let doc = &docs[i.to_string()];
+            Some(IndexedSection {
+                url: url.as_str()?.to_string(),
+                title: doc["title"].as_str().unwrap_or_default().to_string(),
+                body: doc["body"].as_str().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// The chapters of the sidebar, `ol.chapter`. A chapter's sub-chapters are an `ol.section` in
+/// the list item after its own.
+fn parse_sidebar(html: &str) -> Vec<Chapter> {
+    fn walk(list: ElementRef, parent: Option<usize>, chapters: &mut Vec<Chapter>) {
+        let link = Selector::parse(":scope > a[href]").unwrap();
+        let sublist = Selector::parse(":scope > ol").unwrap();
+        let number = Selector::parse("strong").unwrap();
+        let mut last = parent;
+        for item in list.child_elements().filter(|e| e.value().name() == "li") {
+            if let Some(a) = item.select(&link).next() {
+                let numbering: String =
+                    a.select(&number).flat_map(|strong| strong.text()).collect();
+                let text: String = a.text().collect::<String>();
+                let title = text.replacen(numbering.as_str(), "", 1);
+                let href = a.value().attr("href").unwrap_or_default();
+                chapters.push(Chapter {
+                    title: title.split_whitespace().collect::<Vec<_>>().join(" "),
+                    path: Some(href.split('#').next().unwrap_or(href).to_string()),
+                    parent,
+                });
+                last = Some(chapters.len() - 1);
+            }
+            for nested in item.select(&sublist) {
+                walk(nested, last, chapters);
+            }
+        }
+    }
+
+    let document = Html::parse_document(html);
+    let mut chapters = Vec::new();
+    if let Some(toc) = document
+        .select(&Selector::parse("ol.chapter").unwrap())
+        .next()
+    {
+        walk(toc, None, &mut chapters);
+    }
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters(chapters: Vec<Chapter>) -> Vec<(String, Option<String>, Option<usize>)> {
+        chapters
+            .into_iter()
+            .map(|c| (c.title, c.path, c.parent))
+            .collect()
+    }
+
+    fn chapter(
+        title: &str,
+        path: Option<&str>,
+        parent: Option<usize>,
+    ) -> (String, Option<String>, Option<usize>) {
+        (title.to_string(), path.map(String::from), parent)
+    }
+
+    #[test]
+    fn parses_nested_numbered_and_draft_chapters() {
+        let summary = "# Summary\n\
+            \n\
+            [Introduction](README.md)\n\
+            \n\
+            # Guide\n\
+            \n\
+            - [Getting started](guide/start.md)\n    \
+                - [Installing](guide/install.md#nix)\n        \
+                    - [From source](guide/source.md)\n    \
+                - [Draft]()\n\
+            - [Reference](ref.md)\n\
+            1. [Numbered](numbered.md)\n   \
+               2. [Nested numbered](numbered/nested.md)\n\
+            \n\
+            ---\n\
+            \n\
+            [Contributors](contributors.md)\n";
+        assert_eq!(
+            chapters(parse_summary(summary)),
+            vec![
+                chapter("Introduction", Some("README.md"), None),
+                chapter("Getting started", Some("guide/start.md"), None),
+                chapter("Installing", Some("guide/install.md"), Some(1)),
+                chapter("From source", Some("guide/source.md"), Some(2)),
+                chapter("Draft", None, Some(1)),
+                chapter("Reference", Some("ref.md"), None),
+                chapter("Numbered", Some("numbered.md"), None),
+                chapter("Nested numbered", Some("numbered/nested.md"), Some(6)),
+                chapter("Contributors", Some("contributors.md"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn part_titles_close_open_chapters() {
+        let summary = "- [One](one.md)\n    - [Two](two.md)\n# Part\n    - [Three](three.md)\n";
+        assert_eq!(
+            chapters(parse_summary(summary)),
+            vec![
+                chapter("One", Some("one.md"), None),
+                chapter("Two", Some("two.md"), Some(0)),
+                chapter("Three", Some("three.md"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_chapters_at_headings() {
+        let markdown = "# Getting started\n\
+            \n\
+            #hashtag, not a heading\n\
+            \n\
+            ## Install\n\
+            ```sh\n\
+            # not a heading either\n\
+            ```\n\
+            ### From source {#source}\n\
+            ## Install\n\
+            Again.\n";
+        let guides: Vec<(String, String, String, Option<String>)> = split_markdown(
+            markdown,
+            "book/start.html",
+            "Start",
+            Some("book/".to_string()),
+        )
+        .into_iter()
+        .map(|g| {
+            let NGLRaw::Markdown(content) = g.content else {
+                panic!("chapters are markdown");
+            };
+            (g.link, g.title, content, g.parent)
+        })
+        .collect();
+        let guide = |link: &str, title: &str, content: &str, parent: &str| {
+            (
+                link.to_string(),
+                title.to_string(),
+                content.to_string(),
+                Some(parent.to_string()),
+            )
+        };
+        assert_eq!(
+            guides,
+            vec![
+                guide(
+                    "book/start.html",
+                    "Start",
+                    "# Getting started\n\n#hashtag, not a heading",
+                    "book/",
+                ),
+                guide(
+                    "book/start.html#install",
+                    "Install",
+                    "## Install\n```sh\n# not a heading either\n```",
+                    "book/start.html",
+                ),
+                guide(
+                    "book/start.html#source",
+                    "From source",
+                    "### From source {#source}\n",
+                    "book/start.html#install",
+                ),
+                guide(
+                    "book/start.html#install-1",
+                    "Install",
+                    "## Install\nAgain.",
+                    "book/start.html",
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn heading_ids_follow_mdbook() {
+        let mut ids = HashMap::new();
+        let mut id = |heading| heading_id(heading, &mut ids);
+        assert_eq!(
+            id("Hello World"),
+            ("Hello World".to_string(), "hello-world".to_string())
+        );
+        assert_eq!(id("Hello World").1, "hello-world-1");
+        assert_eq!(id("hello   world").1, "hello---world");
+        assert_eq!(id("Hello World").1, "hello-world-2");
+        assert_eq!(
+            id("`lib.mkIf` and **more**"),
+            (
+                "lib.mkIf and more".to_string(),
+                "libmkif-and-more".to_string()
+            )
+        );
+        assert_eq!(
+            id("Custom {#my-id}"),
+            ("Custom".to_string(), "my-id".to_string())
+        );
+        // Explicit ids don't count towards repeats.
+        assert_eq!(id("my-id").1, "my-id");
+    }
+
+    #[test]
+    fn unwraps_search_scripts() {
+        assert_eq!(
+            unwrap_search_script(
+                r#"Object.assign(window.search, {"doc_urls":["a.html"],"x":{}});"#
+            ),
+            r#"{"doc_urls":["a.html"],"x":{}}"#
+        );
+        assert_eq!(
+            unwrap_search_script(
+                r#"window.search = Object.assign(window.search, JSON.parse('{"a":"it\'s \\n"}'));"#
+            ),
+            r#"{"a":"it's \n"}"#
+        );
+        assert_eq!(unwrap_search_script(r#"{"a":1}"#), r#"{"a":1}"#);
+
+        let script = r#"Object.assign(window.search, {"doc_urls":["guide/start.html#install"],
+            "index":{"documentStore":{"docs":{"0":{"title":"Install","body":"Run it."}}}}});"#;
+        let sections = parse_search_index(&unwrap_search_script(script)).unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].url, "guide/start.html#install");
+        assert_eq!(sections[0].title, "Install");
+        assert_eq!(sections[0].body, "Run it.");
+    }
+
+    #[test]
+    fn parses_the_sidebar() {
+        let html = r#"<html><body><nav><ol class="chapter">
+            <li class="chapter-item"><a href="index.html">Introduction</a></li>
+            <li class="chapter-item"><a href="guide/start.html"><strong aria-hidden="true">1.</strong> Getting started</a></li>
+            <li><ol class="section">
+                <li class="chapter-item"><a href="guide/install.html#nix"><strong>1.1.</strong> Installing</a></li>
+                <li><ol class="section">
+                    <li class="chapter-item"><a href="guide/source.html"><strong>1.1.1.</strong> From
+                        source</a></li>
+                </ol></li>
+            </ol></li>
+            <li class="chapter-item"><a href="ref.html"><strong>2.</strong> Reference</a></li>
+        </ol></nav></body></html>"#;
+        assert_eq!(
+            chapters(parse_sidebar(html)),
+            vec![
+                chapter("Introduction", Some("index.html"), None),
+                chapter("Getting started", Some("guide/start.html"), None),
+                chapter("Installing", Some("guide/install.html"), Some(1)),
+                chapter("From source", Some("guide/source.html"), Some(2)),
+                chapter("Reference", Some("ref.html"), None),
+            ]
+        );
+    }
+}
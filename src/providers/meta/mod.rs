@@ -2,21 +2,33 @@
 //
 // Massive credit to nix-search-tv for the idea of config based templates.
 // https://github.com/3timeslazy/nix-search-tv
+use crate::error::NglError;
 use crate::providers::EventChannel;
+use crate::providers::external::ExternalProviderConfig;
 use crate::providers::{Provider, ProviderInformation};
 use crate::schema::NGLDataKind;
-use serde::Deserialize;
-use std::path::PathBuf;
+use serde::{Deserialize, de::DeserializeOwned};
+use std::{collections::HashMap, path::PathBuf};
 
+mod crawl;
+mod html_selectors;
+mod json_mapping;
+mod mdbook;
 mod ndg_options_html;
 mod ndg_search_options;
 mod options_json;
 mod renderdocs;
+mod script;
 
+pub use crawl::CrawlProvider;
+pub use html_selectors::HtmlSelectorsProvider;
+pub use json_mapping::JsonMappingProvider;
+pub use mdbook::MdBookProvider;
 pub use ndg_options_html::NdgOptionsHtmlProvider;
 pub use ndg_search_options::NdgSearchOptionProvider;
 pub use options_json::OptionsJsonProvider;
 pub use renderdocs::RenderDocsProvider;
+pub use script::ScriptProvider;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct TemplateProviderConfig {
@@ -24,6 +36,13 @@ pub struct TemplateProviderConfig {
     pub name: String,
     pub source: String,
     pub kinds: Vec<String>,
+    /// Settings only some templates take, read with [`TemplateProviderConfig::options`].
+    #[serde(flatten)]
+    pub options: serde_json::Map<String, serde_json::Value>,
+    /// Directory of the file this came from, relative paths in `options` are resolved
+    /// against it.
+    #[serde(skip)]
+    pub dir: PathBuf,
 }
 
 impl TemplateProviderConfig {
@@ -38,16 +57,17 @@ impl TemplateProviderConfig {
                     "function" | "functions" => Some(NGLDataKind::Function),
                     "example" | "examples" => Some(NGLDataKind::Example),
                     "guide" | "guides" => Some(NGLDataKind::Guide),
+                    "package" | "packages" => Some(NGLDataKind::Package),
+                    "type" | "types" => Some(NGLDataKind::Type),
                     _ => None,
                 };
 
-                if let Some(allowed) = allowed_kinds {
-                    if !allowed.contains(&lower.as_str())
-                        && !allowed.iter().any(|a| lower.starts_with(a))
-                    {
-                        eprintln!("Warning: kind '{}' not supported for this template", k);
-                        return None;
-                    }
+                if let Some(allowed) = allowed_kinds
+                    && !allowed.contains(&lower.as_str())
+                    && !allowed.iter().any(|a| lower.starts_with(a))
+                {
+                    eprintln!("Warning: kind '{}' not supported for this template", k);
+                    return None;
                 }
 
                 if kind.is_none() {
@@ -65,6 +85,16 @@ impl TemplateProviderConfig {
             sync_interval_hours: Some(24),
         }
     }
+
+    /// The template specific settings as `T`.
+    pub fn options<T: DeserializeOwned>(&self) -> Result<T, NglError> {
+        serde_json::from_value(serde_json::Value::Object(self.options.clone())).map_err(|e| {
+            NglError::Config(format!(
+                "template provider '{}' ({}): {}",
+                self.name, self.template, e
+            ))
+        })
+    }
 }
 
 pub trait ConfigProvider: Send {
@@ -74,7 +104,7 @@ pub trait ConfigProvider: Send {
         &mut self,
         channel: &EventChannel,
         kinds: &[NGLDataKind],
-    ) -> impl std::future::Future<Output = Result<(), sea_orm::DbErr>> + Send;
+    ) -> impl std::future::Future<Output = Result<(), NglError>> + Send;
 }
 
 #[async_trait::async_trait]
@@ -87,7 +117,7 @@ impl<T: ConfigProvider> Provider for T {
         &mut self,
         channel: &EventChannel,
         kinds: &[NGLDataKind],
-    ) -> Result<(), sea_orm::DbErr> {
+    ) -> Result<(), NglError> {
         ConfigProvider::sync(self, channel, kinds).await
     }
 }
@@ -96,16 +126,31 @@ impl<T: ConfigProvider> Provider for T {
 pub struct MetaProviderConfig {
     #[serde(default)]
     pub template_providers: Vec<TemplateProviderConfig>,
+    /// Executables speaking the protocol in `providers::external`.
+    #[serde(default)]
+    pub external_providers: Vec<ExternalProviderConfig>,
+    /// Hours between syncs for every provider, overriding their own intervals.
+    #[serde(default)]
+    pub sync_interval_hours: Option<u32>,
+    /// Hours between syncs per provider name, these beat `sync_interval_hours`.
+    #[serde(default)]
+    pub sync_intervals: HashMap<String, u32>,
 }
 
 impl MetaProviderConfig {
-    pub fn from_file(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let contents = std::fs::read_to_string(path)?;
-        let config: MetaProviderConfig = serde_json::from_str(&contents)?;
+    pub fn from_file(path: &PathBuf) -> Result<Self, NglError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| NglError::Config(format!("{}: {}", path.display(), e)))?;
+        let mut config: Self = serde_json::from_str(&contents)
+            .map_err(|e| NglError::Config(format!("{}: {}", path.display(), e)))?;
+        let dir = path.parent().map(PathBuf::from).unwrap_or_default();
+        for template in &mut config.template_providers {
+            template.dir = dir.clone();
+        }
         Ok(config)
     }
 
-    #[allow(unused)]
+    #[allow(unused, clippy::should_implement_trait)]
     pub fn from_str(json: &str) -> Result<Self, serde_json::Error> {
         serde_json::from_str(json)
     }
@@ -120,7 +165,8 @@ impl MetaProvider {
         Self { config }
     }
 
-    pub fn from_file(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+    #[allow(unused)]
+    pub fn from_file(path: &PathBuf) -> Result<Self, NglError> {
         let config = MetaProviderConfig::from_file(path)?;
         Ok(Self::new(config))
     }
@@ -139,6 +185,41 @@ impl MetaProvider {
             "options_json" => Some(Box::new(OptionsJsonProvider::from_config(cfg))),
             "ndg_options_html" => Some(Box::new(NdgOptionsHtmlProvider::from_config(cfg))),
             "ndg_search_options" => Some(Box::new(NdgSearchOptionProvider::from_config(cfg))),
+            "crawl" => match CrawlProvider::from_config(cfg) {
+                Ok(provider) => Some(Box::new(provider)),
+                Err(e) => {
+                    eprintln!("Warning: {}, skipping", e);
+                    None
+                }
+            },
+            "html_selectors" => match HtmlSelectorsProvider::from_config(cfg) {
+                Ok(provider) => Some(Box::new(provider)),
+                Err(e) => {
+                    eprintln!("Warning: {}, skipping", e);
+                    None
+                }
+            },
+            "json_mapping" => match JsonMappingProvider::from_config(cfg) {
+                Ok(provider) => Some(Box::new(provider)),
+                Err(e) => {
+                    eprintln!("Warning: {}, skipping", e);
+                    None
+                }
+            },
+            "mdbook" => match MdBookProvider::from_config(cfg) {
+                Ok(provider) => Some(Box::new(provider)),
+                Err(e) => {
+                    eprintln!("Warning: {}, skipping", e);
+                    None
+                }
+            },
+            "script" => match ScriptProvider::from_config(cfg) {
+                Ok(provider) => Some(Box::new(provider)),
+                Err(e) => {
+                    eprintln!("Warning: {}, skipping", e);
+                    None
+                }
+            },
             unknown => {
                 eprintln!("Warning: unknown template type '{}', skipping", unknown);
                 None
@@ -1,13 +1,12 @@
-use crate::db::entities::{example, option};
-use crate::db::enums::documentation_format::DocumentationFormat;
-use crate::db::enums::language::Language;
-use crate::providers::{EventChannel, ProviderEvent, ProviderInformation};
-use crate::schema::NGLDataKind;
+use crate::error::NglError;
+use crate::providers::{
+    EventChannel, ExampleRecord, Language, OptionRecord, ProviderEvent, ProviderInformation,
+    SyncPhase,
+};
+use crate::schema::{NGLDataKind, NGLRaw};
 use crate::utils::fetch_source;
 use crate::utils::html_to_markdown;
 use scraper::{Element, ElementRef, Html, Selector};
-use sea_orm::ActiveValue::*;
-use sea_orm::DbErr;
 
 use super::{ConfigProvider, TemplateProviderConfig};
 
@@ -26,13 +25,19 @@ impl NdgOptionsHtmlProvider {
         &self,
         channel: &EventChannel,
         kinds: &[NGLDataKind],
-    ) -> Result<(), DbErr> {
-        let html = fetch_source(&self.info.source)
-            .await
-            .map_err(|e| DbErr::Custom(format!("Failed to fetch source: {}", e)))?;
+    ) -> Result<(), NglError> {
+        let html = fetch_source(&self.info.source, channel).await?;
 
+        channel.phase(SyncPhase::Parsing);
         let options = parse_ndg_html(&html)
-            .map_err(|e| DbErr::Custom(format!("Failed to parse HTML: {}", e)))?;
+            .map_err(|e| NglError::Parse(format!("Failed to parse HTML: {}", e)))?;
+        channel.set_total(
+            NGLDataKind::Example,
+            options.iter().map(|opt| opt.examples.len()).sum(),
+        );
+        if kinds.contains(&NGLDataKind::Option) {
+            channel.set_total(NGLDataKind::Option, options.len());
+        }
 
         for opt in options {
             let markdown = opt
@@ -42,26 +47,22 @@ impl NdgOptionsHtmlProvider {
                 .unwrap_or_default();
             for example in opt.examples {
                 channel
-                    .send(ProviderEvent::Example(example::ActiveModel {
-                        id: NotSet,
-                        provider_name: Set(self.info.name.clone()),
-                        language: Set(Some(Language::Nix)),
-                        data: Set(example),
-                        source_kind: Set(Some(NGLDataKind::Option)),
-                        source_link: Set(None),
+                    .send(ProviderEvent::Example(ExampleRecord {
+                        code: example,
+                        language: Some(Language::Nix),
+                        source_kind: Some(NGLDataKind::Option),
+                        source_link: None,
                     }))
                     .await;
             }
             if kinds.contains(&NGLDataKind::Option) {
                 channel
-                    .send(ProviderEvent::Option(option::ActiveModel {
-                        id: NotSet,
-                        provider_name: Set(self.info.name.clone()),
-                        name: Set(opt.name.clone()),
-                        type_signature: Set(opt.option_type.clone()),
-                        default_value: Set(opt.default.clone()),
-                        format: Set(DocumentationFormat::Markdown),
-                        data: Set(markdown),
+                    .send(ProviderEvent::Option(OptionRecord {
+                        name: opt.name.clone(),
+                        type_signature: opt.option_type.clone(),
+                        default_value: opt.default.clone(),
+                        content: NGLRaw::Markdown(markdown),
+                        source_url: None,
                     }))
                     .await;
             }
@@ -76,7 +77,11 @@ impl ConfigProvider for NdgOptionsHtmlProvider {
         &self.info
     }
 
-    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr> {
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
         self.parse_content(channel, kinds).await?;
         Ok(())
     }
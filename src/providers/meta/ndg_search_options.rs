@@ -1,16 +1,13 @@
 use html_escape::decode_html_entities;
-use sea_orm::{
-    ActiveValue::{NotSet, Set},
-    DbErr,
-};
 use serde::Deserialize;
 
 use crate::{
-    db::{entities::option, enums::documentation_format::DocumentationFormat::Markdown},
+    error::NglError,
     providers::{
-        EventChannel, ProviderInformation,
+        EventChannel, OptionRecord, ProviderInformation, SyncPhase,
         meta::{ConfigProvider, TemplateProviderConfig},
     },
+    schema::NGLRaw,
     utils::fetch_source,
 };
 
@@ -25,31 +22,35 @@ impl NdgSearchOptionProvider {
         }
     }
 
-    async fn parse_options(&self, channel: &EventChannel) -> Result<(), DbErr> {
-        let json_str = fetch_source(&self.info.source)
-            .await
-            .map_err(|e| DbErr::Custom(format!("Failed to fetch source: {}", e)))?;
+    async fn parse_options(&self, channel: &EventChannel) -> Result<(), NglError> {
+        let json_str = fetch_source(&self.info.source, channel).await?;
+        channel.phase(SyncPhase::Parsing);
         let options: Vec<OptionEntry> = serde_json::from_str(&json_str)
-            .map_err(|e| DbErr::Custom(format!("Failed to parse options.json: {}", e)))?;
+            .map_err(|e| NglError::Parse(format!("Failed to parse search data: {}", e)))?;
+        channel.set_total(
+            crate::NGLDataKind::Option,
+            options
+                .iter()
+                .filter(|entry| entry.title.starts_with("Option: "))
+                .count(),
+        );
 
         for option_entry in options {
             if let Some(option) = option_entry.title.strip_prefix("Option: ") {
                 channel
-                    .send(crate::providers::ProviderEvent::Option(
-                        option::ActiveModel {
-                            id: NotSet,
-                            provider_name: Set(self.info.name.clone()),
-                            name: Set(decode_html_entities(&option).into_owned()),
-                            type_signature: NotSet,
-                            default_value: NotSet,
-                            format: Set(Markdown),
-                            data: Set(decode_html_entities(&option_entry.content).into_owned()),
-                        },
-                    ))
+                    .send(crate::providers::ProviderEvent::Option(OptionRecord {
+                        name: decode_html_entities(&option).into_owned(),
+                        type_signature: None,
+                        default_value: None,
+                        content: NGLRaw::Markdown(
+                            decode_html_entities(&option_entry.content).into_owned(),
+                        ),
+                        source_url: None,
+                    }))
                     .await
             }
         }
-        return Ok(());
+        Ok(())
     }
 }
 
@@ -62,7 +63,7 @@ impl ConfigProvider for NdgSearchOptionProvider {
         &mut self,
         channel: &EventChannel,
         kinds: &[crate::NGLDataKind],
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), NglError> {
         if kinds.contains(&crate::NGLDataKind::Option)
             && self.info.kinds.contains(&crate::NGLDataKind::Option)
         {
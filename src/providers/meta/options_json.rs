@@ -1,10 +1,7 @@
-use crate::db::entities::option as option_entity;
-use crate::db::enums::documentation_format::DocumentationFormat;
-use crate::providers::{EventChannel, ProviderEvent, ProviderInformation};
-use crate::schema::NGLDataKind;
+use crate::error::NglError;
+use crate::providers::{EventChannel, OptionRecord, ProviderEvent, ProviderInformation, SyncPhase};
+use crate::schema::{NGLDataKind, NGLRaw};
 use crate::utils::fetch_source;
-use sea_orm::ActiveValue::*;
-use sea_orm::DbErr;
 use serde::Deserialize;
 use std::collections::HashMap;
 
@@ -21,13 +18,13 @@ impl OptionsJsonProvider {
         }
     }
 
-    async fn parse_options(&self, channel: &EventChannel) -> Result<(), DbErr> {
-        let json_str = fetch_source(&self.info.source)
-            .await
-            .map_err(|e| DbErr::Custom(format!("Failed to fetch source: {}", e)))?;
+    async fn parse_options(&self, channel: &EventChannel) -> Result<(), NglError> {
+        let json_str = fetch_source(&self.info.source, channel).await?;
 
+        channel.phase(SyncPhase::Parsing);
         let options: HashMap<String, OptionEntry> = serde_json::from_str(&json_str)
-            .map_err(|e| DbErr::Custom(format!("Failed to parse options.json: {}", e)))?;
+            .map_err(|e| NglError::Parse(format!("Failed to parse options.json: {}", e)))?;
+        channel.set_total(NGLDataKind::Option, options.len());
 
         for (name, opt) in options {
             let default_value = opt.default.as_ref().map(|d| {
@@ -41,14 +38,12 @@ impl OptionsJsonProvider {
             let data = serde_json::to_string(&opt).unwrap_or_default();
 
             channel
-                .send(ProviderEvent::Option(option_entity::ActiveModel {
-                    id: NotSet,
-                    provider_name: Set(self.info.name.clone()),
-                    name: Set(name.clone()),
-                    type_signature: Set(opt.option_type),
-                    default_value: Set(default_value),
-                    format: Set(DocumentationFormat::Markdown),
-                    data: Set(data),
+                .send(ProviderEvent::Option(OptionRecord {
+                    name: name.clone(),
+                    type_signature: opt.option_type,
+                    default_value,
+                    content: NGLRaw::Markdown(data),
+                    source_url: None,
                 }))
                 .await;
         }
@@ -62,7 +57,11 @@ impl ConfigProvider for OptionsJsonProvider {
         &self.info
     }
 
-    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr> {
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
         if kinds.contains(&NGLDataKind::Option) && self.info.kinds.contains(&NGLDataKind::Option) {
             self.parse_options(channel).await?
         }
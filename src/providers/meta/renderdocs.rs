@@ -1,11 +1,8 @@
-use crate::db::entities::option as option_entity;
-use crate::db::enums::documentation_format::DocumentationFormat;
-use crate::providers::{ProviderEvent, ProviderInformation, EventChannel};
-use crate::schema::NGLDataKind;
+use crate::error::NglError;
+use crate::providers::{EventChannel, OptionRecord, ProviderEvent, ProviderInformation, SyncPhase};
+use crate::schema::{NGLDataKind, NGLRaw};
 use crate::utils::{fetch_source, html_to_markdown};
 use scraper::{ElementRef, Html, Selector};
-use sea_orm::ActiveValue::*;
-use sea_orm::DbErr;
 
 use super::{ConfigProvider, TemplateProviderConfig};
 
@@ -20,28 +17,28 @@ impl RenderDocsProvider {
         }
     }
 
-    async fn parse_options(&self, channel: &EventChannel) -> Result<(), DbErr> {
-        let html = fetch_source(&self.info.source)
-            .await
-            .map_err(|e| DbErr::Custom(format!("Failed to fetch source: {}", e)))?;
+    async fn parse_options(&self, channel: &EventChannel) -> Result<(), NglError> {
+        let html = fetch_source(&self.info.source, channel).await?;
 
+        channel.phase(SyncPhase::Parsing);
         let options = parse_renderdocs_html(&html)
-            .map_err(|e| DbErr::Custom(format!("Failed to parse HTML: {}", e)))?;
+            .map_err(|e| NglError::Parse(format!("Failed to parse HTML: {}", e)))?;
+        channel.set_total(NGLDataKind::Option, options.len());
 
         for opt in options {
             let markdown = opt
                 .raw_html
                 .map(|h| html_to_markdown(&h))
                 .unwrap_or_default();
-            channel.send(ProviderEvent::Option(option_entity::ActiveModel {
-                id: NotSet,
-                provider_name: Set(self.info.name.clone()),
-                name: Set(opt.name),
-                type_signature: Set(opt.option_type),
-                default_value: Set(opt.default),
-                format: Set(DocumentationFormat::Markdown),
-                data: Set(markdown),
-            })).await;
+            channel
+                .send(ProviderEvent::Option(OptionRecord {
+                    name: opt.name,
+                    type_signature: opt.option_type,
+                    default_value: opt.default,
+                    content: NGLRaw::Markdown(markdown),
+                    source_url: None,
+                }))
+                .await;
         }
 
         Ok(())
@@ -53,7 +50,11 @@ impl ConfigProvider for RenderDocsProvider {
         &self.info
     }
 
-    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr> {
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
         if kinds.contains(&NGLDataKind::Option) && self.info.kinds.contains(&NGLDataKind::Option) {
             self.parse_options(channel).await?
         }
@@ -191,10 +192,10 @@ fn extract_properties(dd: &ElementRef) -> ExtractedProperties {
     }
 
     for link in dd.select(&table_link_selector) {
-        if let Some(href) = link.value().attr("href") {
-            if href.starts_with("http") {
-                props.declared_by.push(href.to_string());
-            }
+        if let Some(href) = link.value().attr("href")
+            && href.starts_with("http")
+        {
+            props.declared_by.push(href.to_string());
         }
     }
 
@@ -249,10 +250,7 @@ fn normalize_value(s: &str) -> String {
         s
     };
 
-    s.replace('"', "\"")
-        .replace('"', "\"")
-        .replace('\u{2018}', "'") // left single quote
-        .replace('\u{2019}', "'") // right single quote
+    s.replace(['\u{2018}', '\u{2019}'], "'") // curly single quotes
         .replace('–', "--")
         .replace('…', "..")
 }
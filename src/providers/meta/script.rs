@@ -0,0 +1,232 @@
+// The `script` template: NGL downloads `source` and hands it to a Rhai script, which turns it
+// into records. Meant for one-off sites (wiki exports, small project docs) that aren't worth a
+// template of their own:
+//
+// {
+//   "template": "script",
+//   "name": "team-wiki",
+//   "source": "https://wiki.example.com/export.html",
+//   "kinds": ["guides", "examples"],
+//   "script": "scripts/wiki.rhai"
+// }
+//
+// The script sees the downloaded text as `source` and the kinds being synced as `kinds`
+// (`"function"`, `"guide"`, ...). It sends records with `send_function`, `send_option`,
+// `send_guide` and `send_example`, each taking a map shaped like the matching struct in
+// `providers::records`; optional fields can be left out:
+//
+//   for line in source.split("\n") {
+//       let parts = line.split("|");
+//       send_guide(#{ link: parts[0], title: parts[1], content: #{ Markdown: parts[2] } });
+//   }
+//
+// Records of kinds that aren't being synced are dropped, and a malformed one fails the sync
+// with the script's line number.
+//
+// Scripts run with limits so a runaway loop or string can't hang or exhaust the sync. A template
+// can raise them with `"limits": { "max_operations": 500000000 }`; the others are
+// `max_call_levels`, `max_string_size` (bytes), `max_array_size` and `max_map_size`.
+use std::path::PathBuf;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope};
+use serde::{Deserialize, de::DeserializeOwned};
+use tokio::runtime::Handle;
+
+use crate::error::NglError;
+use crate::providers::{
+    EventChannel, ExampleRecord, FunctionRecord, GuideRecord, OptionRecord, ProviderEvent,
+    ProviderInformation, SyncPhase,
+};
+use crate::schema::NGLDataKind;
+use crate::utils::fetch_source;
+
+use super::{ConfigProvider, TemplateProviderConfig};
+
+#[derive(Debug, Deserialize)]
+struct ScriptOptions {
+    /// Relative to the template file.
+    script: PathBuf,
+    #[serde(default)]
+    limits: ScriptLimits,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ScriptLimits {
+    max_operations: u64,
+    max_call_levels: usize,
+    max_string_size: usize,
+    max_array_size: usize,
+    max_map_size: usize,
+}
+
+/// Roomy enough to walk a few megabytes of source, a few seconds of work at most.
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_operations: 100_000_000,
+            max_call_levels: 64,
+            max_string_size: 64 * 1024 * 1024,
+            max_array_size: 1_000_000,
+            max_map_size: 100_000,
+        }
+    }
+}
+
+impl ScriptLimits {
+    fn apply(&self, engine: &mut Engine) {
+        engine
+            .set_max_operations(self.max_operations)
+            .set_max_call_levels(self.max_call_levels)
+            .set_max_string_size(self.max_string_size)
+            .set_max_array_size(self.max_array_size)
+            .set_max_map_size(self.max_map_size);
+    }
+}
+
+pub struct ScriptProvider {
+    info: ProviderInformation,
+    script: PathBuf,
+    limits: ScriptLimits,
+}
+
+impl ScriptProvider {
+    pub fn from_config(cfg: &TemplateProviderConfig) -> Result<Self, NglError> {
+        let options: ScriptOptions = cfg.options()?;
+        let script = cfg.dir.join(options.script);
+        if !script.is_file() {
+            return Err(NglError::Config(format!(
+                "template provider '{}': script {} not found",
+                cfg.name,
+                script.display()
+            )));
+        }
+        Ok(Self {
+            info: cfg.to_provider_info(None),
+            script,
+            limits: options.limits,
+        })
+    }
+}
+
+impl ConfigProvider for ScriptProvider {
+    fn provider_info(&self) -> &ProviderInformation {
+        &self.info
+    }
+
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        let kinds: Vec<NGLDataKind> = kinds
+            .iter()
+            .filter(|kind| self.info.kinds.contains(kind))
+            .copied()
+            .collect();
+        if kinds.is_empty() {
+            return Ok(());
+        }
+
+        let source = fetch_source(&self.info.source, channel).await?;
+        let script = tokio::fs::read_to_string(&self.script).await?;
+        let label = self.script.display().to_string();
+        let limits = self.limits;
+        let channel = channel.clone();
+        channel.phase(SyncPhase::Parsing);
+        // Scripts run synchronously, so they get a blocking thread and wait on `send` there.
+        tokio::task::spawn_blocking(move || run(&label, &script, limits, source, channel, kinds))
+            .await
+            .map_err(|e| NglError::Parse(format!("script panicked: {}", e)))?
+    }
+}
+
+fn run(
+    label: &str,
+    script: &str,
+    limits: ScriptLimits,
+    source: String,
+    channel: EventChannel,
+    kinds: Vec<NGLDataKind>,
+) -> Result<(), NglError> {
+    let handle = Handle::current();
+    let mut engine = Engine::new();
+    limits.apply(&mut engine);
+    register_send::<FunctionRecord>(&mut engine, "send_function", &handle, &channel, &kinds);
+    register_send::<OptionRecord>(&mut engine, "send_option", &handle, &channel, &kinds);
+    register_send::<GuideRecord>(&mut engine, "send_guide", &handle, &channel, &kinds);
+    register_send::<ExampleRecord>(&mut engine, "send_example", &handle, &channel, &kinds);
+
+    let mut scope = Scope::new();
+    scope.push_constant("source", source);
+    scope.push_constant(
+        "kinds",
+        kinds
+            .iter()
+            .map(|kind| Dynamic::from(format!("{:?}", kind).to_lowercase()))
+            .collect::<Array>(),
+    );
+    engine
+        .run_with_scope(&mut scope, script)
+        .map_err(|e| NglError::Parse(format!("{}: {}", label, e)))
+}
+
+/// A record type the script can send, with the kind it's synced under.
+trait ScriptRecord: DeserializeOwned {
+    const KIND: NGLDataKind;
+
+    fn into_event(self) -> ProviderEvent;
+}
+
+impl ScriptRecord for FunctionRecord {
+    const KIND: NGLDataKind = NGLDataKind::Function;
+
+    fn into_event(self) -> ProviderEvent {
+        ProviderEvent::Function(self)
+    }
+}
+
+impl ScriptRecord for OptionRecord {
+    const KIND: NGLDataKind = NGLDataKind::Option;
+
+    fn into_event(self) -> ProviderEvent {
+        ProviderEvent::Option(self)
+    }
+}
+
+impl ScriptRecord for GuideRecord {
+    const KIND: NGLDataKind = NGLDataKind::Guide;
+
+    fn into_event(self) -> ProviderEvent {
+        ProviderEvent::Guide(self)
+    }
+}
+
+impl ScriptRecord for ExampleRecord {
+    const KIND: NGLDataKind = NGLDataKind::Example;
+
+    fn into_event(self) -> ProviderEvent {
+        ProviderEvent::Example(self)
+    }
+}
+
+/// Registers `name(record)`, which reads the map into an `R` and sends it on `channel` if its
+/// kind is being synced.
+fn register_send<R: ScriptRecord + 'static>(
+    engine: &mut Engine,
+    name: &str,
+    handle: &Handle,
+    channel: &EventChannel,
+    kinds: &[NGLDataKind],
+) {
+    let handle = handle.clone();
+    let channel = channel.clone();
+    let wanted = kinds.contains(&R::KIND);
+    engine.register_fn(name, move |record: Map| -> Result<(), Box<EvalAltResult>> {
+        let record: R = rhai::serde::from_dynamic(&record.into())?;
+        if wanted {
+            handle.block_on(channel.send(record.into_event()));
+        }
+        Ok(())
+    });
+}
@@ -1,13 +1,23 @@
+use serde::{Deserialize, Serialize};
+
 use crate::schema::NGLDataKind;
 
 pub mod channel;
+pub mod external;
+pub mod lock;
 pub mod sync;
 
 pub mod meta;
 pub mod provider;
+pub mod records;
 
-pub use channel::{EventChannel, ProviderEvent, create_event_channel};
+pub use channel::{EventChannel, ProviderEvent, StatusEvent, SyncPhase, create_event_channel};
 pub use provider::Provider;
+#[allow(unused)]
+pub use records::{
+    ExampleRecord, FunctionRecord, GuideLink, GuideRecord, Language, OptionRecord, PackageRecord,
+    TypeRecord,
+};
 
 #[cfg(feature = "example")]
 pub mod example;
@@ -21,7 +31,8 @@ pub mod nixos_manual;
 #[cfg(feature = "noogle")]
 pub mod noogle;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct ProviderInformation {
     /// This is what determines if your provider gets synced
     pub kinds: Vec<NGLDataKind>,
@@ -1,15 +1,15 @@
 #![allow(unused)]
 use async_trait::async_trait;
 use scraper::{ElementRef, Html, Selector};
-use sea_orm::{ActiveValue::*, DbErr};
 
 use crate::{
     NGLDataKind,
-    db::{
-        entities::{example, guide},
-        enums::documentation_format::DocumentationFormat,
+    error::NglError,
+    providers::{
+        EventChannel, ExampleRecord, GuideLink, GuideRecord, Provider, ProviderEvent,
+        ProviderInformation,
     },
-    providers::{EventChannel, Provider, ProviderEvent, ProviderInformation},
+    schema::NGLRaw,
     utils::{extract_examples_markdown, fetch_source, html_to_markdown},
 };
 
@@ -31,13 +31,15 @@ struct ParsedGuide {
 
 #[async_trait]
 impl Provider for NixosManual {
-    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr> {
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
         let include_guides = kinds.contains(&NGLDataKind::Guide);
         let include_examples = kinds.contains(&NGLDataKind::Example);
 
-        let html = fetch_source(URL)
-            .await
-            .map_err(|e| DbErr::Custom(format!("Failed to fetch NixOS manual: {}", e)))?;
+        let html = fetch_source(URL, channel).await?;
 
         let guides = parse_manual(&html);
 
@@ -49,7 +51,10 @@ impl Provider for NixosManual {
                 if let Some(ref parent_id) = guide.parent_id {
                     let parent_link = format!("{}#{}", URL, parent_id);
                     channel
-                        .send(ProviderEvent::GuideXref(parent_link, guide.link.clone()))
+                        .send(ProviderEvent::GuideXref(GuideLink {
+                            parent: parent_link,
+                            child: guide.link.clone(),
+                        }))
                         .await;
                 }
             }
@@ -63,13 +68,11 @@ impl Provider for NixosManual {
 
                 for example in examples {
                     channel
-                        .send(ProviderEvent::Example(example::ActiveModel {
-                            id: NotSet,
-                            provider_name: Set(PROVIDER_NAME.to_owned()),
-                            language: Set(example.language),
-                            data: Set(example.data),
-                            source_kind: Set(Some(NGLDataKind::Guide)),
-                            source_link: Set(Some(guide.link.to_owned())),
+                        .send(ProviderEvent::Example(ExampleRecord {
+                            code: example.data,
+                            language: example.language,
+                            source_kind: Some(NGLDataKind::Guide),
+                            source_link: Some(guide.link.to_owned()),
                         }))
                         .await;
                 }
@@ -77,13 +80,10 @@ impl Provider for NixosManual {
 
             if include_guides {
                 channel
-                    .send(ProviderEvent::Guide(guide::ActiveModel {
-                        id: NotSet,
-                        provider_name: Set(PROVIDER_NAME.to_owned()),
-                        title: Set(guide.title.clone()),
-                        format: Set(DocumentationFormat::HTML),
-                        data: Set(guide.content_markdown.to_owned()),
-                        link: Set(guide.link.clone()),
+                    .send(ProviderEvent::Guide(GuideRecord {
+                        link: guide.link.clone(),
+                        title: guide.title.clone(),
+                        content: NGLRaw::HTML(guide.content_markdown.to_owned()),
                     }))
                     .await;
             }
@@ -111,10 +111,10 @@ fn parse_manual(html: &str) -> Vec<ParsedGuide> {
     let selector = Selector::parse("div.preface, div.part, div.chapter, div.section").unwrap();
 
     for element in document.select(&selector) {
-        if let Some(guide) = parse_guide_element(&element) {
-            if seen_ids.insert(guide.id.clone()) {
-                guides.push(guide);
-            }
+        if let Some(guide) = parse_guide_element(&element)
+            && seen_ids.insert(guide.id.clone())
+        {
+            guides.push(guide);
         }
     }
 
@@ -181,14 +181,14 @@ fn extract_own_content(element: &ElementRef) -> String {
 
 fn extract_title_and_id(element: &ElementRef) -> Option<(String, String)> {
     for tag in ["h1", "h2", "h3", "h4"] {
-        if let Ok(sel) = Selector::parse(tag) {
-            if let Some(heading) = element.select(&sel).next() {
-                let id = heading.value().id()?.to_string();
-                let text: String = heading.text().collect::<Vec<_>>().join("");
-                let cleaned = text.trim().to_string();
-                if !cleaned.is_empty() {
-                    return Some((id, cleaned));
-                }
+        if let Ok(sel) = Selector::parse(tag)
+            && let Some(heading) = element.select(&sel).next()
+        {
+            let id = heading.value().id()?.to_string();
+            let text: String = heading.text().collect::<Vec<_>>().join("");
+            let cleaned = text.trim().to_string();
+            if !cleaned.is_empty() {
+                return Some((id, cleaned));
             }
         }
     }
@@ -198,21 +198,17 @@ fn extract_title_and_id(element: &ElementRef) -> Option<(String, String)> {
 fn find_parent_id(element: &ElementRef) -> Option<String> {
     let mut current = element.parent();
     while let Some(node) = current {
-        if let Some(el) = node.value().as_element() {
-            if let Some(class) = el.attr("class") {
-                if class.contains("part") || class.contains("chapter") || class.contains("section")
+        if let Some(el) = node.value().as_element()
+            && let Some(class) = el.attr("class")
+            && (class.contains("part") || class.contains("chapter") || class.contains("section"))
+            && let Some(el_ref) = ElementRef::wrap(node)
+        {
+            for tag in ["h1", "h2", "h3", "h4"] {
+                if let Ok(sel) = Selector::parse(tag)
+                    && let Some(heading) = el_ref.select(&sel).next()
+                    && let Some(id) = heading.value().id()
                 {
-                    if let Some(el_ref) = ElementRef::wrap(node) {
-                        for tag in ["h1", "h2", "h3", "h4"] {
-                            if let Ok(sel) = Selector::parse(tag) {
-                                if let Some(heading) = el_ref.select(&sel).next() {
-                                    if let Some(id) = heading.value().id() {
-                                        return Some(id.to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
+                    return Some(id.to_string());
                 }
             }
         }
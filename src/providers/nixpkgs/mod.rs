@@ -1,10 +1,10 @@
-use crate::providers::{EventChannel, Provider, ProviderEvent};
-use crate::schema::NGLDataKind;
+use crate::error::NglError;
+use crate::providers::{EventChannel, PackageRecord, Provider, ProviderEvent, SyncPhase};
+use crate::schema::{NGLDataKind, NGLRaw};
+use crate::utils::{http_client, read_body};
 use async_trait::async_trait;
 use brotli2::read::BrotliDecoder;
 use regex::Regex;
-use sea_orm::ActiveValue::*;
-use sea_orm::DbErr;
 use serde::de::{self, Deserializer, MapAccess, Visitor};
 use std::fmt;
 use std::io::Read;
@@ -13,12 +13,12 @@ use tokio::sync::mpsc;
 
 pub mod schema;
 
-static KEY_RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r#"<Key>([^<]+)</Key>"#).unwrap());
+static KEY_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"<Key>([^<]+)</Key>"#).unwrap());
 static RELEASE_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"^nixpkgs/([^/]+)/packages\.json\.br$"#).unwrap());
-static CONTINUATION_RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r#"<NextContinuationToken>([^<]+)</NextContinuationToken>"#).unwrap());
+static CONTINUATION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"<NextContinuationToken>([^<]+)</NextContinuationToken>"#).unwrap()
+});
 static PREFIX_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"<Prefix>(nixpkgs/[^<]+/)</Prefix>"#).unwrap());
 
@@ -36,7 +36,11 @@ impl Provider for NixPkgs {
         }
     }
 
-    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr> {
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
         if !kinds.contains(&NGLDataKind::Package) {
             return Ok(());
         }
@@ -48,12 +52,13 @@ impl Provider for NixPkgs {
             self.discover_release().await?
         };
 
+        channel.set_source_revision(&release);
         self.fetch_packages_for_release(channel, release).await
     }
 }
 
 impl NixPkgs {
-    async fn discover_release(&self) -> Result<String, DbErr> {
+    async fn discover_release(&self) -> Result<String, NglError> {
         let mut continuation: Option<String> = None;
         let mut releases: Vec<String> = Vec::new();
 
@@ -76,9 +81,7 @@ impl NixPkgs {
             let body = self.fetch_s3_listing(None).await?;
             for cap in PREFIX_RE.captures_iter(&body) {
                 let prefix = cap.get(1).unwrap().as_str();
-                let release = prefix
-                    .trim_start_matches("nixpkgs/")
-                    .trim_end_matches('/');
+                let release = prefix.trim_start_matches("nixpkgs/").trim_end_matches('/');
                 releases.push(release.to_string());
             }
         }
@@ -86,10 +89,10 @@ impl NixPkgs {
         releases.sort();
         releases
             .pop()
-            .ok_or_else(|| DbErr::Custom("failed to discover nixpkgs release".to_string()))
+            .ok_or_else(|| NglError::Parse("failed to discover nixpkgs release".to_string()))
     }
 
-    async fn fetch_s3_listing(&self, continuation_token: Option<&str>) -> Result<String, DbErr> {
+    async fn fetch_s3_listing(&self, continuation_token: Option<&str>) -> Result<String, NglError> {
         let mut url =
             String::from("https://nix-releases.s3.amazonaws.com/?list-type=2&prefix=nixpkgs/");
         if let Some(token) = continuation_token {
@@ -99,48 +102,49 @@ impl NixPkgs {
             ));
         }
 
-        let resp = reqwest::get(&url)
+        let resp = http_client()?
+            .get(&url)
+            .send()
             .await
-            .map_err(|e| DbErr::Custom(format!("S3 list error: {}", e)))?;
+            .map_err(|e| NglError::Network(format!("S3 list error: {}", e)))?;
 
         if !resp.status().is_success() {
-            return Err(DbErr::Custom(format!(
+            return Err(NglError::Network(format!(
                 "S3 returned status {}",
                 resp.status()
             )));
         }
 
-        resp.text()
-            .await
-            .map_err(|e| DbErr::Custom(e.to_string()))
+        Ok(resp.text().await?)
     }
 
     async fn fetch_packages_for_release(
         &self,
         channel: &EventChannel,
         release: String,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), NglError> {
         let rel = release.trim_start_matches("nixpkgs/");
         let url = format!(
             "https://releases.nixos.org/nixpkgs/{}/packages.json.br",
             rel
         );
 
-        let resp = reqwest::get(&url)
-            .await
-            .map_err(|e| DbErr::Custom(e.to_string()))?;
+        let resp = http_client()?.get(&url).send().await?;
 
         if !resp.status().is_success() {
-            return Err(DbErr::Custom(format!(
+            return Err(NglError::Network(format!(
                 "unexpected http status {}",
                 resp.status()
             )));
         }
 
-        let bytes = resp
-            .bytes()
-            .await
-            .map_err(|e| DbErr::Custom(e.to_string()))?;
+        let bytes = read_body(resp, channel).await?;
+        let compressed = bytes.first().copied() != Some(b'{');
+        channel.phase(if compressed {
+            SyncPhase::Decompressing
+        } else {
+            SyncPhase::Parsing
+        });
 
         let (tx, mut rx) = mpsc::channel::<Result<(String, serde_json::Value), String>>(64);
 
@@ -150,20 +154,17 @@ impl NixPkgs {
         // This way we can start processing packages immediately without waiting for the entire file to be parsed,
         // and we never have more than one decompressed package in memory at a time.
         let parse_handle = tokio::task::spawn_blocking(move || {
-            let reader: Box<dyn Read + Send> = if bytes.first().map(|b| *b) == Some(b'{') {
-                Box::new(std::io::Cursor::new(bytes))
+            let reader: Box<dyn Read + Send> = if compressed {
+                Box::new(BrotliDecoder::new(std::io::Cursor::new(bytes)))
             } else {
-                let cursor = std::io::Cursor::new(bytes);
-                Box::new(BrotliDecoder::new(cursor))
+                Box::new(std::io::Cursor::new(bytes))
             };
 
-            if let Err(e) = stream_packages_to_channel(reader, tx) {
-                eprintln!("JSON parse error: {}", e);
-            }
+            stream_packages_to_channel(reader, tx)
         });
 
         while let Some(result) = rx.recv().await {
-            let (name, pkg_value) = result.map_err(DbErr::Custom)?;
+            let (name, pkg_value) = result.map_err(NglError::Parse)?;
             let meta = pkg_value.get("meta");
 
             let version = get_str(&pkg_value, "version");
@@ -177,30 +178,25 @@ impl NixPkgs {
             let unfree = meta.and_then(|m| get_bool(m, "unfree")).unwrap_or(false);
 
             channel
-                .send(ProviderEvent::Package(
-                    crate::db::entities::package::ActiveModel {
-                        id: NotSet,
-                        provider_name: Set("nixpkgs".to_string()),
-                        name: Set(name),
-                        version: Set(version),
-                        format: Set(
-                            crate::db::enums::documentation_format::DocumentationFormat::PlainText,
-                        ),
-                        data: Set(serde_json::to_string(&pkg_value).unwrap_or_default()),
-                        description: Set(description),
-                        homepage: Set(homepage),
-                        license: Set(license),
-                        source_code_url: Set(source_code_url),
-                        broken: Set(broken),
-                        unfree: Set(unfree),
-                    },
-                ))
+                .send(ProviderEvent::Package(PackageRecord {
+                    name,
+                    version,
+                    content: NGLRaw::PlainText(
+                        serde_json::to_string(&pkg_value).unwrap_or_default(),
+                    ),
+                    description,
+                    homepage,
+                    license,
+                    source_code_url,
+                    broken,
+                    unfree,
+                }))
                 .await;
         }
 
         parse_handle
             .await
-            .map_err(|e| DbErr::Custom(e.to_string()))?;
+            .map_err(|e| NglError::Parse(format!("package parser panicked: {}", e)))??;
 
         Ok(())
     }
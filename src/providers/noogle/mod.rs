@@ -1,18 +1,15 @@
 mod schema;
 
 use crate::{
-    db::{
-        entities::{example, function},
-        enums::documentation_format::DocumentationFormat,
-    },
+    error::NglError,
     providers::{
-        EventChannel, Provider, ProviderEvent, ProviderInformation, noogle::schema::NoogleResponse,
+        EventChannel, ExampleRecord, FunctionRecord, Provider, ProviderEvent, ProviderInformation,
+        SyncPhase, noogle::schema::NoogleResponse,
     },
-    schema::NGLDataKind,
-    utils::extract_examples_markdown,
+    schema::{NGLDataKind, NGLRaw},
+    utils::{extract_examples_markdown, http_client, read_body},
 };
 use async_trait::async_trait;
-use sea_orm::{ActiveValue::*, DbErr};
 
 static ENDPOINT_URL: &str = "https://noogle.dev/api/v1/data";
 
@@ -30,17 +27,23 @@ impl Provider for Noogle {
         }
     }
 
-    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr> {
-        let response = reqwest::get(ENDPOINT_URL)
-            .await
-            .map_err(|e| DbErr::Custom(e.to_string()))?
-            .json::<NoogleResponse>()
-            .await
-            .map_err(|e| DbErr::Custom(e.to_string()))?;
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        let body = read_body(http_client()?.get(ENDPOINT_URL).send().await?, channel).await?;
+        channel.phase(SyncPhase::Parsing);
+        let response = serde_json::from_slice::<NoogleResponse>(&body)
+            .map_err(|e| NglError::Parse(format!("Failed to parse noogle data: {}", e)))?;
 
         let fetch_functions = kinds.contains(&NGLDataKind::Function);
         let fetch_examples = kinds.contains(&NGLDataKind::Example);
+        if fetch_functions {
+            channel.set_total(NGLDataKind::Function, response.data.len());
+        }
         let upstream_rev = response.upstream_info.rev.clone();
+        channel.set_source_revision(&upstream_rev);
 
         for doc in response.data {
             let content = doc
@@ -62,43 +65,39 @@ impl Provider for Noogle {
                 let extracted_examples = extract_examples_markdown(&content);
                 for example in extracted_examples {
                     channel
-                        .send(ProviderEvent::Example(example::ActiveModel {
-                            id: NotSet,
-                            provider_name: Set("noogle".to_owned()),
-                            language: Set(example.language),
-                            data: Set(example.data),
-                            source_kind: Set(Some(NGLDataKind::Function)),
-                            source_link: Set(if doc.meta.path.is_empty() {
+                        .send(ProviderEvent::Example(ExampleRecord {
+                            code: example.data,
+                            language: example.language,
+                            source_kind: Some(NGLDataKind::Function),
+                            source_link: if doc.meta.path.is_empty() {
                                 None
                             } else {
                                 Some(format!(
                                     "https://github.com/NixOS/nixpkgs/blob/master/{}",
                                     doc.meta.path.join("/")
                                 ))
-                            }),
+                            },
                         }))
                         .await
                 }
             }
             if fetch_functions {
-                let aliases = doc.meta.aliases.as_ref().map(|a| {
-                    serde_json::to_string(
-                        &a.iter().map(|parts| parts.join(".")).collect::<Vec<_>>(),
-                    )
-                    .unwrap_or_default()
-                });
+                let aliases = doc
+                    .meta
+                    .aliases
+                    .iter()
+                    .flatten()
+                    .map(|parts| parts.join("."))
+                    .collect();
 
                 channel
-                    .send(ProviderEvent::Function(function::ActiveModel {
-                        id: NotSet,
-                        name: Set(doc.meta.title.clone()),
-                        provider_name: Set("noogle".to_owned()),
-                        format: Set(DocumentationFormat::Markdown),
-                        signature: Set(doc.meta.signature.clone()),
-                        data: Set(content),
-                        source_url: Set(source_url),
-                        source_code_url: Set(source_code_url),
-                        aliases: Set(aliases),
+                    .send(ProviderEvent::Function(FunctionRecord {
+                        name: doc.meta.title.clone(),
+                        signature: doc.meta.signature.clone(),
+                        content: NGLRaw::Markdown(content),
+                        source_url,
+                        source_code_url,
+                        aliases,
                     }))
                     .await;
             }
@@ -1,13 +1,19 @@
 use async_trait::async_trait;
 use chrono::Utc;
-use sea_orm::{ActiveValue::Set, DatabaseConnection, DbErr, EntityTrait};
+use sea_orm::{ActiveValue::Set, DatabaseConnection, EntityTrait, TransactionTrait};
 
 use crate::{
     NGLDataKind, NGLRequest,
     db::entities::provider,
+    error::NglError,
     providers::{
-        EventChannel, ProviderInformation, channel::StatusEvent, create_event_channel, sync,
+        EventChannel, ProviderInformation, SyncPhase,
+        channel::{CountsSnapShot, StatusEvent},
+        create_event_channel,
+        lock::{self, SyncLease},
+        sync,
     },
+    schema::{NGLLockPolicy, NGLSyncStatus},
 };
 
 #[async_trait]
@@ -38,7 +44,7 @@ pub trait Provider: Send {
     ///
     /// # Examples:
     /// ```ignore
-    /// async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr> {
+    /// async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), NglError> {
     ///     if kinds.contains(&NGLDataKind::Function) {
     ///         for func in self.fetch_functions().await {
     ///             channel.send(ProviderEvent::Function(func)).await;
@@ -47,72 +53,156 @@ pub trait Provider: Send {
     ///     Ok(())
     /// }
     /// ```
-    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind]) -> Result<(), DbErr>;
+    /// Failures should use the [`NglError`] variant that says what went wrong
+    /// (network, parse, ...) so callers can tell them apart in the sync report.
+    async fn sync(&mut self, channel: &EventChannel, kinds: &[NGLDataKind])
+    -> Result<(), NglError>;
 
     async fn refresh(
         &mut self,
         db: &DatabaseConnection,
         request: NGLRequest,
         status: tokio::sync::broadcast::Sender<StatusEvent>,
-    ) -> Result<bool, DbErr> {
-        let requested_kinds = request
-            .kinds
-            .as_ref()
-            .ok_or_else(|| DbErr::Custom("No kinds specified in request".to_string()))?;
+    ) -> Result<NGLSyncStatus, NglError> {
+        let requested_kinds = &request.requested_kinds();
 
         let info = self.get_info();
-        let kinds_to_sync = sync::determine_kinds_to_sync(
+        let sync_interval_hours = sync::effective_sync_interval(&info, request.sync_mode);
+        let mut kinds_to_sync = sync::determine_kinds_to_sync(
             db,
             requested_kinds,
             &info.kinds,
             &info.name,
-            info.sync_interval_hours.unwrap_or(24) as i64,
+            sync_interval_hours,
         )
         .await?;
 
         if kinds_to_sync.is_empty() && info.kinds.iter().any(|pk| requested_kinds.contains(pk)) {
-            return Ok(false);
+            return Ok(NGLSyncStatus::Fresh);
         }
 
-        // Upsert provider record
-        let provider_model = crate::db::entities::provider::ActiveModel {
-            name: Set(info.name.clone()),
-            last_updated: Set(Utc::now().into()),
+        let lease = match SyncLease::try_acquire(db, &info.name).await? {
+            Some(lease) => lease,
+            None if request.on_locked == NGLLockPolicy::Skip => {
+                let reason = "another process is syncing, using existing data".to_string();
+                let _ = status.send(StatusEvent::ProviderSkipped {
+                    provider: info.name.clone(),
+                    reason: reason.clone(),
+                });
+                return Ok(NGLSyncStatus::Skipped { reason });
+            }
+            None => {
+                let _ = status.send(StatusEvent::Message {
+                    provider: info.name.clone(),
+                    msg: "waiting for another process to finish syncing...".to_string(),
+                });
+                let Some(lease) = SyncLease::acquire(db, &info.name).await? else {
+                    let reason = format!(
+                        "gave up waiting for another process after {}s, using existing data",
+                        lock::MAX_WAIT.as_secs()
+                    );
+                    let _ = status.send(StatusEvent::ProviderSkipped {
+                        provider: info.name.clone(),
+                        reason: reason.clone(),
+                    });
+                    return Ok(NGLSyncStatus::Skipped { reason });
+                };
+
+                // The other process has most likely just synced what we were after.
+                kinds_to_sync = sync::determine_kinds_to_sync(
+                    db,
+                    requested_kinds,
+                    &info.kinds,
+                    &info.name,
+                    sync_interval_hours,
+                )
+                .await?;
+                if kinds_to_sync.is_empty() {
+                    lease.release().await?;
+                    let reason = "synced by another process".to_string();
+                    let _ = status.send(StatusEvent::ProviderSkipped {
+                        provider: info.name.clone(),
+                        reason: reason.clone(),
+                    });
+                    return Ok(NGLSyncStatus::Skipped { reason });
+                }
+                lease
+            }
         };
-        provider::Entity::insert(provider_model)
-            .on_conflict(
-                sea_orm::sea_query::OnConflict::column(provider::Column::Name)
-                    .update_column(provider::Column::LastUpdated)
-                    .to_owned(),
-            )
-            .exec(db)
-            .await?;
 
-        for kind in &kinds_to_sync {
-            sync::delete_provider_kind_data(db, kind, &info.name).await?;
-        }
+        let result = async {
+            // Upsert provider record
+            let provider_model = crate::db::entities::provider::ActiveModel {
+                name: Set(info.name.clone()),
+                last_updated: Set(Utc::now()),
+            };
+            provider::Entity::insert(provider_model)
+                .on_conflict(
+                    sea_orm::sea_query::OnConflict::column(provider::Column::Name)
+                        .update_column(provider::Column::LastUpdated)
+                        .to_owned(),
+                )
+                .exec(db)
+                .await?;
 
-        let (channel, consumer_handle) =
-            create_event_channel(self.get_info().name, db.clone(), status);
+            let started_at = Utc::now();
+            let (channel, collector) = create_event_channel(self.get_info().name, status);
 
-        let update_handle = tokio::spawn(async move {
-            loop {
-                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
-            }
-        });
+            let update_handle = tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                }
+            });
+
+            let sync_result = self.sync(&channel, &kinds_to_sync).await;
+            channel.phase(SyncPhase::Indexing);
+            let source_revision = channel.source_revision();
+            drop(channel);
+            let collected = collector
+                .await
+                .map_err(|e| NglError::Database(format!("Collector task panicked: {}", e)));
 
-        self.sync(&channel, &kinds_to_sync).await?;
-        drop(channel);
-        consumer_handle
-            .await
-            .map_err(|e| DbErr::Custom(format!("Consumer task panicked: {}", e)))??;
+            update_handle.abort();
 
-        update_handle.abort();
+            let counts = collected
+                .as_ref()
+                .map(|collected| collected.counts)
+                .unwrap_or_default();
+            // The old rows stay until the new ones are in, all in one transaction: queries
+            // made meanwhile answer from the old data, and a failed sync keeps it.
+            let result = match sync_result.and(collected) {
+                Ok(collected) => async {
+                    let txn = db.begin().await?;
+                    collected.replace(&txn, &kinds_to_sync).await?;
+                    sync::update_kind_cache(&txn, &kinds_to_sync, &info.name).await?;
+                    txn.commit().await
+                }
+                .await
+                .map_err(NglError::from),
+                Err(e) => Err(e),
+            };
+            sync::record_runs(
+                db,
+                &info.name,
+                &kinds_to_sync,
+                started_at,
+                if result.is_ok() {
+                    counts
+                } else {
+                    CountsSnapShot::default()
+                },
+                source_revision,
+                result.as_ref().err(),
+            )
+            .await?;
+            result
+        }
+        .await;
 
-        // Update cache timestamps
-        sync::update_kind_cache(db, &kinds_to_sync, &info.name).await?;
+        lease.release().await?;
+        result?;
 
-        Ok(true)
+        Ok(NGLSyncStatus::Synced)
     }
 
     fn new() -> Self
@@ -0,0 +1,94 @@
+// What providers emit, one struct per kind of data. These know nothing about the database:
+// the event channel fills in the provider name and maps them onto entities, so a provider
+// only needs this module, `EventChannel` and the `Provider` trait.
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::schema::{NGLDataKind, NGLRaw};
+
+/// Language of an [`ExampleRecord`]'s code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Nix,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionRecord {
+    /// Full attribute path, e.g. `lib.strings.concatStrings`.
+    pub name: String,
+    pub signature: Option<String>,
+    pub content: NGLRaw,
+    /// Where the documentation lives.
+    pub source_url: Option<String>,
+    /// Where the function is defined.
+    pub source_code_url: Option<String>,
+    /// Other paths the same function is reachable under.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionRecord {
+    pub name: String,
+    pub type_signature: Option<String>,
+    pub default_value: Option<String>,
+    pub content: NGLRaw,
+    /// Where the option is documented.
+    pub source_url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageRecord {
+    pub name: String,
+    pub version: Option<String>,
+    /// Everything known about the package, usually its metadata as JSON.
+    pub content: NGLRaw,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    pub source_code_url: Option<String>,
+    #[serde(default)]
+    pub broken: bool,
+    #[serde(default)]
+    pub unfree: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuideRecord {
+    /// Identifies the guide, [`GuideLink`]s refer to guides by it.
+    pub link: String,
+    pub title: String,
+    pub content: NGLRaw,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExampleRecord {
+    pub code: String,
+    /// Languages NGL doesn't know are dropped rather than rejected.
+    #[serde(default, deserialize_with = "known_language")]
+    pub language: Option<Language>,
+    /// Kind of the entry the example was taken from.
+    pub source_kind: Option<NGLDataKind>,
+    pub source_link: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeRecord {
+    pub name: String,
+    pub content: NGLRaw,
+}
+
+fn known_language<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Language>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?
+        .and_then(|language| serde_json::from_value(serde_json::Value::String(language)).ok()))
+}
+
+/// Makes the guide at `child` a sub-guide of the one at `parent`. Both are [`GuideRecord`]
+/// links from the same sync, the order they're sent in doesn't matter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuideLink {
+    pub parent: String,
+    pub child: String,
+}
@@ -1,11 +1,18 @@
-use chrono::Utc;
-use sea_orm::{ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
 
 use crate::{
     NGLDataKind,
     db::entities::{
-        example, function, guide, guide_xref, option, package, provider_kind_cache, r#type,
+        example, function, guide, guide_xref, option, package, provider_kind_cache, sync_run,
+        r#type,
     },
+    error::NglError,
+    providers::{ProviderInformation, channel::CountsSnapShot},
+    schema::{NGLFreshnessState, NGLProviderFreshness, NGLSyncMode, NGLSyncOutcome},
 };
 
 pub const HOST_KINDS: [NGLDataKind; 5] = [
@@ -16,6 +23,15 @@ pub const HOST_KINDS: [NGLDataKind; 5] = [
     NGLDataKind::Type,
 ];
 
+/// The cache age, in hours, after which a provider's kinds are synced again.
+/// A refresh uses zero so every cached kind looks expired.
+pub fn effective_sync_interval(info: &ProviderInformation, sync_mode: NGLSyncMode) -> i64 {
+    match sync_mode {
+        NGLSyncMode::Refresh => 0,
+        _ => info.sync_interval_hours.unwrap_or(24) as i64,
+    }
+}
+
 pub async fn determine_kinds_to_sync(
     db: &DatabaseConnection,
     requested_kinds: &[NGLDataKind],
@@ -44,7 +60,7 @@ pub async fn determine_kinds_to_sync(
         };
 
         if needs_sync {
-            kinds_to_sync.push(kind.clone());
+            kinds_to_sync.push(*kind);
         }
     }
 
@@ -75,7 +91,7 @@ pub async fn determine_kinds_to_sync(
                     .await?;
 
                 if host_cache.is_some() {
-                    kinds_to_sync.push(host_kind.clone());
+                    kinds_to_sync.push(*host_kind);
                 }
             }
         }
@@ -84,9 +100,81 @@ pub async fn determine_kinds_to_sync(
     Ok(kinds_to_sync)
 }
 
-// TODO: We should be able to use cascades for this right??? do those exist in lite??
-pub async fn delete_provider_kind_data(
+/// Kinds each provider currently has cached, whether or not they're still fresh.
+pub async fn cached_kinds(
+    db: &DatabaseConnection,
+) -> Result<Vec<(String, Vec<NGLDataKind>)>, DbErr> {
+    let mut cached: Vec<(String, Vec<NGLDataKind>)> = Vec::new();
+    for entry in provider_kind_cache::Entity::find().all(db).await? {
+        let Some(kind) = NGLDataKind::all()
+            .into_iter()
+            .find(|kind| format!("{:?}", kind) == entry.kind)
+        else {
+            continue;
+        };
+        match cached
+            .iter_mut()
+            .find(|(name, _)| *name == entry.provider_name)
+        {
+            Some((_, kinds)) => kinds.push(kind),
+            None => cached.push((entry.provider_name, vec![kind])),
+        }
+    }
+    Ok(cached)
+}
+
+/// Reports how current a provider's cached data is for the requested kinds
+/// without syncing anything.
+pub async fn provider_freshness(
     db: &DatabaseConnection,
+    requested_kinds: &[NGLDataKind],
+    supported_kinds: &[NGLDataKind],
+    provider_name: &str,
+    sync_interval_hours: i64,
+) -> Result<NGLProviderFreshness, DbErr> {
+    let mut synced_at = Vec::new();
+    let mut missing = false;
+
+    for kind in requested_kinds {
+        if !supported_kinds.contains(kind) {
+            continue;
+        }
+
+        let cache_entry = provider_kind_cache::Entity::find()
+            .filter(provider_kind_cache::Column::ProviderName.eq(provider_name))
+            .filter(provider_kind_cache::Column::Kind.eq(format!("{:?}", kind)))
+            .one(db)
+            .await?;
+
+        match cache_entry {
+            Some(entry) => synced_at.push(entry.last_synced.with_timezone(&Utc)),
+            None => missing = true,
+        }
+    }
+
+    let last_synced = synced_at.iter().min().copied();
+    let state = match last_synced {
+        None => NGLFreshnessState::NeverSynced,
+        Some(oldest)
+            if missing
+                || Utc::now().signed_duration_since(oldest)
+                    >= chrono::Duration::hours(sync_interval_hours) =>
+        {
+            NGLFreshnessState::Stale
+        }
+        Some(_) => NGLFreshnessState::Fresh,
+    };
+
+    Ok(NGLProviderFreshness {
+        provider_name: provider_name.to_string(),
+        state,
+        last_synced,
+    })
+}
+
+// TODO: We should be able to use cascades for this right??? do those exist in lite??
+pub async fn delete_provider_kind_data<C: ConnectionTrait>(
+    db: &C,
     kind: &NGLDataKind,
     provider_name: &str,
 ) -> Result<(), DbErr> {
@@ -148,8 +236,8 @@ pub async fn delete_provider_kind_data(
     Ok(())
 }
 
-pub async fn update_kind_cache(
-    db: &DatabaseConnection,
+pub async fn update_kind_cache<C: ConnectionTrait>(
+    db: &C,
     kinds: &[NGLDataKind],
     provider_name: &str,
 ) -> Result<(), DbErr> {
@@ -173,3 +261,40 @@ pub async fn update_kind_cache(
     }
     Ok(())
 }
+
+/// Adds a sync history row for each kind a refresh attempted.
+pub async fn record_runs(
+    db: &DatabaseConnection,
+    provider_name: &str,
+    kinds: &[NGLDataKind],
+    started_at: DateTime<Utc>,
+    counts: CountsSnapShot,
+    source_revision: Option<String>,
+    error: Option<&NglError>,
+) -> Result<(), DbErr> {
+    let finished_at = Utc::now();
+    let duration_ms = finished_at
+        .signed_duration_since(started_at)
+        .num_milliseconds();
+
+    for kind in kinds {
+        sync_run::Entity::insert(sync_run::ActiveModel {
+            id: NotSet,
+            provider_name: Set(provider_name.to_string()),
+            kind: Set(*kind),
+            started_at: Set(started_at.into()),
+            finished_at: Set(finished_at.into()),
+            duration_ms: Set(duration_ms),
+            rows: Set(counts.get(*kind) as i64),
+            source_revision: Set(source_revision.clone()),
+            outcome: Set(match error {
+                Some(_) => NGLSyncOutcome::Failed,
+                None => NGLSyncOutcome::Success,
+            }),
+            error: Set(error.map(|e| e.to_string())),
+        })
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
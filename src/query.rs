@@ -0,0 +1,47 @@
+use sea_orm::DatabaseConnection;
+use tokio::sync::broadcast;
+
+use crate::{
+    db::services::{query_data, search_hits},
+    error::NglError,
+    providers::StatusEvent,
+    registry::ProviderRegistry,
+    schema::{NGLQueryResult, NGLRequest, NGLSearchHit, NGLSyncMode},
+};
+
+/// Syncs whatever `request` needs against an open database and answers it.
+/// Shared by the library entry points and the daemon, which keeps `db` and `registry` around
+/// between requests.
+pub async fn answer(
+    db: &DatabaseConnection,
+    request: NGLRequest,
+    registry: &ProviderRegistry,
+    status: &broadcast::Sender<StatusEvent>,
+) -> Result<NGLQueryResult, NglError> {
+    let sync_report = registry.sync(db, request.clone(), status).await?;
+
+    let stale_providers = registry.stale_providers(db, &request).await?;
+    let responses = query_data(db, &request).await?;
+
+    if request.sync_mode == NGLSyncMode::StaleWhileRevalidate && !stale_providers.is_empty() {
+        registry.revalidate(db, request, status.clone());
+    }
+
+    Ok(NGLQueryResult {
+        responses,
+        stale_providers,
+        sync_report,
+    })
+}
+
+/// Syncs what `request` needs like [`answer`] does, then lists the matching search index rows
+/// without hydrating them, for pickers that only show names and fetch one entry at a time.
+pub async fn hits(
+    db: &DatabaseConnection,
+    request: NGLRequest,
+    registry: &ProviderRegistry,
+    status: &broadcast::Sender<StatusEvent>,
+) -> Result<Vec<NGLSearchHit>, NglError> {
+    registry.sync(db, request.clone(), status).await?;
+    Ok(search_hits(db, &request, None).await?)
+}
@@ -7,124 +7,456 @@ use crate::providers::nixpkgs::NixPkgs;
 #[cfg(feature = "noogle")]
 use crate::providers::noogle::Noogle;
 use crate::{
-    cli::progress::run_progress_ui,
-    providers::{Provider, meta::MetaProvider},
-    schema::NGLRequest,
+    config::Config,
+    error::NglError,
+    providers::{
+        EventChannel, Provider, ProviderInformation, StatusEvent,
+        external::{ExternalProvider, ExternalProviderConfig},
+        meta::{MetaProvider, MetaProviderConfig},
+        sync,
+    },
+    schema::{
+        NGLDataKind, NGLFreshnessState, NGLProviderFreshness, NGLProviderSyncResult, NGLRequest,
+        NGLSyncMode, NGLSyncReport, NGLSyncStatus,
+    },
 };
+use async_trait::async_trait;
 use futures::future::join_all;
-use sea_orm::{DatabaseConnection, DbErr};
-use std::path::PathBuf;
-use tokio::sync::broadcast;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use std::{collections::HashMap, sync::Arc};
+use tokio::{
+    sync::{Mutex, OnceCell, broadcast},
+    task::JoinHandle,
+};
+
+/// The providers a process syncs and queries. Start from [`ProviderRegistry::from_config`]
+/// (or [`ProviderRegistry::builtin`]) and [`register`](Self::register) your own next to them:
+///
+/// ```ignore
+/// let registry = ProviderRegistry::builtin()
+///     .register(Box::new(MyProvider::new()))
+///     .disable("nixpkgs");
+/// let report = registry.sync(&db, request, &status).await?;
+/// ```
+///
+/// Clones share the providers themselves, so a clone can be handed to a background task.
+/// Two syncs of the same provider through one registry take turns.
+#[derive(Clone, Default)]
+pub struct ProviderRegistry {
+    providers: Vec<Registered>,
+    /// Hours between syncs by provider name, applied to everything registered.
+    sync_intervals: HashMap<String, u32>,
+    sync_interval_hours: Option<u32>,
+    /// Executables from the config, registered once their `--ngl-info` handshake has run.
+    external_providers: Vec<ExternalProviderConfig>,
+    /// Shared by clones, so the handshakes run at most once per process.
+    external: Arc<OnceCell<Vec<Registered>>>,
+}
 
-pub struct ProviderRegistry;
+#[derive(Clone)]
+struct Registered {
+    info: ProviderInformation,
+    enabled: bool,
+    provider: Arc<Mutex<Box<dyn Provider + Send>>>,
+}
+
+/// A registered provider as listed by [`ProviderRegistry::list`].
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+pub struct RegisteredProvider {
+    #[serde(flatten)]
+    pub info: ProviderInformation,
+    /// Disabled providers are never synced or selected.
+    pub enabled: bool,
+}
 
 impl ProviderRegistry {
-    /// Sync all registered providers with the database.
-    /// If no kinds are specified in the request, all providers are synced.
-    /// Otherwise, only providers that support the requested kinds are synced.
-    /// Automatically loads templates.json from current directory if it exists.
-    pub async fn sync(db: &DatabaseConnection, request: NGLRequest) -> Result<(), DbErr> {
-        let config_path = PathBuf::from("templates.json");
-        let config = if config_path.exists() {
-            Some(config_path)
-        } else {
-            None
-        };
-        Self::sync_with_config(db, request, config).await
+    /// A registry without any providers.
+    #[allow(unused)]
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// Sync with optional meta provider config file
-    pub async fn sync_with_config(
-        db: &DatabaseConnection,
-        request: NGLRequest,
-        config_path: Option<PathBuf>,
-    ) -> Result<(), DbErr> {
+    /// Every compiled in provider.
+    #[allow(unused)]
+    pub fn builtin() -> Self {
+        Self::from_config(None)
+    }
+
+    /// Every compiled in provider, plus the template providers, external providers and sync
+    /// intervals from `config`. External providers are only run for their information once
+    /// the registry first needs it, to sync or list providers.
+    pub fn from_config(config: Option<&MetaProviderConfig>) -> Self {
         #[allow(unused_mut)]
-        let mut providers: Vec<Box<dyn Provider + Send>> = vec![];
+        let mut registry = Self {
+            sync_intervals: config.map(|c| c.sync_intervals.clone()).unwrap_or_default(),
+            sync_interval_hours: config.and_then(|c| c.sync_interval_hours),
+            ..Self::default()
+        };
 
         #[cfg(feature = "example")]
-        providers.push(Box::new(Example::new()));
+        {
+            registry = registry.register(Box::new(Example::new()));
+        }
         // ADD YOUR PROVIDERS HERE, IDEALLY ALSO TIE THEM INTO A FEATURE
         // SO OTHER PROGRAMS CAN CHOOSE TO COMPILE THEM OUT
         #[cfg(feature = "noogle")]
-        providers.push(Box::new(Noogle::new()));
+        {
+            registry = registry.register(Box::new(Noogle::new()));
+        }
         #[cfg(feature = "nixpkgs")]
-        providers.push(Box::new(NixPkgs::new()));
+        {
+            registry = registry.register(Box::new(NixPkgs::new()));
+        }
         #[cfg(feature = "nixos_manual")]
-        providers.push(Box::new(NixosManual::new()));
+        {
+            registry = registry.register(Box::new(NixosManual::new()));
+        }
 
-        if let Some(path) = config_path {
-            match MetaProvider::from_file(&path) {
-                Ok(meta) => {
-                    let meta_providers = meta.build_providers();
-                    providers.extend(meta_providers);
-                }
-                Err(e) => {
-                    eprintln!("Warning: failed to load meta provider config: {}", e);
-                }
+        if let Some(config) = config {
+            for provider in MetaProvider::new(config.clone()).build_providers() {
+                registry = registry.register(provider);
             }
+            registry.external_providers = config.external_providers.clone();
         }
 
-        // broadcaster to send status events to, right now we just have a simple progress tui in term, but
-        // this will work later on when we need to provide status to calling code.
-        let (status_tx, _) = broadcast::channel(1024);
-        tokio::spawn(run_progress_ui(status_tx.subscribe()));
-        let sync_futures: Vec<_> = providers
-            .into_iter()
-            .filter_map(|mut provider| {
-                if provider.get_info().kinds.iter().any(|provider_kind| {
-                    request
-                        .kinds
-                        .as_ref()
-                        .map_or(false, |kinds| kinds.contains(provider_kind))
-                }) {
-                    let provider_name = provider.get_info().name.clone();
-                    Some({
-                        let request_clone = request.clone();
-                        let db_clone = db.clone();
-                        let status_clone = status_tx.clone();
-                        async move {
-                            let result = provider
-                                .refresh(&db_clone, request_clone, status_clone)
-                                .await;
-                            (provider_name, result)
-                        }
-                    })
-                } else {
-                    None
-                }
+        registry
+    }
+
+    /// [`Self::from_config`] with the template config from the user's config file,
+    /// see [`Config::from_env`].
+    #[allow(unused)]
+    pub fn from_env() -> Self {
+        Self::from_config(Config::from_env().provider_config().as_ref())
+    }
+
+    /// Adds `provider`, enabled. A provider registered under a name that's already taken
+    /// replaces the old one, which is how a built in provider is swapped for your own.
+    pub fn register(mut self, provider: Box<dyn Provider + Send>) -> Self {
+        let registered = self.registered(provider);
+        self.providers
+            .retain(|p| p.info.name != registered.info.name);
+        self.providers.push(registered);
+        self
+    }
+
+    fn registered(&self, provider: Box<dyn Provider + Send>) -> Registered {
+        let mut info = provider.get_info();
+        let interval = self.sync_intervals.get(&info.name).copied();
+        let provider = match interval.or(self.sync_interval_hours) {
+            Some(hours) => {
+                info.sync_interval_hours = Some(hours);
+                Rescheduled::wrap(provider, hours)
+            }
+            None => provider,
+        };
+
+        Registered {
+            info,
+            enabled: true,
+            provider: Arc::new(Mutex::new(provider)),
+        }
+    }
+
+    /// Turns a provider back on after [`Self::disable`]. Unknown names are ignored.
+    #[allow(unused)]
+    pub fn enable(mut self, name: &str) -> Self {
+        self.set_enabled(name, true);
+        self
+    }
+
+    /// Keeps a provider from being synced or queried without unregistering it.
+    /// Unknown names are ignored.
+    #[allow(unused)]
+    pub fn disable(mut self, name: &str) -> Self {
+        self.set_enabled(name, false);
+        self
+    }
+
+    /// Enables or disables the provider called `name`, returning false if there's none.
+    /// External providers from the config can't be toggled, leave them out of it instead.
+    #[allow(unused)]
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.providers.iter_mut().find(|p| p.info.name == name) {
+            Some(provider) => {
+                provider.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Every registered provider in registration order, disabled ones included.
+    pub async fn list(&self) -> Vec<RegisteredProvider> {
+        self.all()
+            .await
+            .map(|p| RegisteredProvider {
+                info: p.info.clone(),
+                enabled: p.enabled,
             })
-            .collect();
+            .collect()
+    }
 
-        let results = join_all(sync_futures).await;
+    /// Information on the enabled providers.
+    pub async fn providers(&self) -> Vec<ProviderInformation> {
+        self.enabled().await.map(|p| p.info.clone()).collect()
+    }
 
-        let mut reindex = false;
-        let mut errors = Vec::new();
-        for (provider_name, result) in results {
-            match result {
-                Ok(synced) => {
-                    if synced {
-                        reindex = true;
+    async fn enabled(&self) -> impl Iterator<Item = &Registered> {
+        self.all().await.filter(|p| p.enabled)
+    }
+
+    /// Every provider, external ones last. The first call runs the external providers'
+    /// handshakes, those that fail are skipped with a warning. A provider registered directly
+    /// wins over an external one of the same name.
+    async fn all(&self) -> impl Iterator<Item = &Registered> {
+        let external = self
+            .external
+            .get_or_init(|| async {
+                join_all(
+                    self.external_providers
+                        .iter()
+                        .cloned()
+                        .map(ExternalProvider::from_config),
+                )
+                .await
+                .into_iter()
+                .filter_map(|provider| match provider {
+                    Ok(provider) => Some(self.registered(Box::new(provider))),
+                    Err(e) => {
+                        eprintln!("Warning: skipping external provider: {}", e);
+                        None
                     }
-                }
-                Err(e) => {
-                    errors.push(format!("{}: {}", provider_name, e));
+                })
+                .collect()
+            })
+            .await;
+        self.providers.iter().chain(
+            external
+                .iter()
+                .filter(|e| !self.providers.iter().any(|p| p.info.name == e.info.name)),
+        )
+    }
+
+    /// Narrows the enabled providers down to the ones a request is about: those supporting
+    /// at least one requested kind and, if the request names providers, only those.
+    /// Naming a provider that isn't registered (or is disabled) is an error.
+    async fn select(&self, request: &NGLRequest) -> Result<Vec<&Registered>, NglError> {
+        let enabled: Vec<&Registered> = self.enabled().await.collect();
+        if let Some(names) = &request.providers {
+            let unknown: Vec<String> = names
+                .iter()
+                .filter(|name| !enabled.iter().any(|p| &p.info.name == *name))
+                .cloned()
+                .collect();
+            if !unknown.is_empty() {
+                let registered = enabled.iter().map(|p| p.info.name.clone()).collect();
+                return Err(NglError::UnknownProvider {
+                    unknown,
+                    registered,
+                });
+            }
+        }
+
+        let requested_kinds = request.requested_kinds();
+        Ok(enabled
+            .into_iter()
+            .filter(|provider| {
+                let info = &provider.info;
+                let kind_requested = info
+                    .kinds
+                    .iter()
+                    .any(|provider_kind| requested_kinds.contains(provider_kind));
+                let name_requested = request
+                    .providers
+                    .as_ref()
+                    .is_none_or(|names| names.contains(&info.name));
+                kind_requested && name_requested
+            })
+            .collect())
+    }
+
+    /// Reports requested providers whose cached data is missing or expired.
+    /// Never touches the network, so this is safe to call in offline mode.
+    pub async fn stale_providers(
+        &self,
+        db: &DatabaseConnection,
+        request: &NGLRequest,
+    ) -> Result<Vec<NGLProviderFreshness>, NglError> {
+        let requested_kinds = request.requested_kinds();
+        let mut stale = Vec::new();
+        for provider in self.select(request).await? {
+            let info = &provider.info;
+            let freshness = sync::provider_freshness(
+                db,
+                &requested_kinds,
+                &info.kinds,
+                &info.name,
+                info.sync_interval_hours.unwrap_or(24) as i64,
+            )
+            .await?;
+            if freshness.state != NGLFreshnessState::Fresh {
+                stale.push(freshness);
+            }
+        }
+
+        Ok(stale)
+    }
+
+    /// Refreshes expired providers in a background task, for callers that already
+    /// answered from stale data. Queries made after the task finishes see the fresh data.
+    pub fn revalidate(
+        &self,
+        db: &DatabaseConnection,
+        mut request: NGLRequest,
+        status: broadcast::Sender<StatusEvent>,
+    ) -> JoinHandle<Result<NGLSyncReport, NglError>> {
+        let db = db.clone();
+        let registry = self.clone();
+        request.sync_mode = NGLSyncMode::Auto;
+        tokio::spawn(async move { registry.sync(&db, request, &status).await })
+    }
+
+    /// Works out which kinds each selected provider would sync for `request`,
+    /// without syncing anything. Providers with nothing to do get an empty list.
+    pub async fn plan(
+        &self,
+        db: &DatabaseConnection,
+        request: &NGLRequest,
+    ) -> Result<Vec<(String, Vec<NGLDataKind>)>, NglError> {
+        let requested_kinds = request.requested_kinds();
+        let mut plan = Vec::new();
+        for provider in self.select(request).await? {
+            let info = &provider.info;
+            let kinds = if request.sync_mode == NGLSyncMode::Offline {
+                vec![]
+            } else {
+                sync::determine_kinds_to_sync(
+                    db,
+                    &requested_kinds,
+                    &info.kinds,
+                    &info.name,
+                    sync::effective_sync_interval(info, request.sync_mode),
+                )
+                .await?
+            };
+            plan.push((info.name.clone(), kinds));
+        }
+
+        Ok(plan)
+    }
+
+    /// Sync the enabled providers with the database.
+    /// Only providers that support the requested kinds, and are named in the
+    /// request's provider filter if it has one, are synced.
+    /// Provider failures don't abort the sync, they're recorded in the returned report instead.
+    /// Progress is broadcast on `status`, see [`crate::providers::channel::status_channel`].
+    pub async fn sync(
+        &self,
+        db: &DatabaseConnection,
+        request: NGLRequest,
+        status: &broadcast::Sender<StatusEvent>,
+    ) -> Result<NGLSyncReport, NglError> {
+        // Selected first so unknown provider names are reported offline too.
+        let mut providers = self.select(&request).await?;
+        if request.sync_mode == NGLSyncMode::Offline {
+            return Ok(NGLSyncReport::default());
+        }
+
+        // Stale data is good enough to answer from, so only providers with nothing
+        // cached at all are synced up front. `revalidate` takes care of the rest.
+        if request.sync_mode == NGLSyncMode::StaleWhileRevalidate {
+            let requested_kinds = request.requested_kinds();
+            let mut never_synced = Vec::new();
+            for provider in providers {
+                let info = &provider.info;
+                let freshness = sync::provider_freshness(
+                    db,
+                    &requested_kinds,
+                    &info.kinds,
+                    &info.name,
+                    info.sync_interval_hours.unwrap_or(24) as i64,
+                )
+                .await?;
+                if freshness.state == NGLFreshnessState::NeverSynced {
+                    never_synced.push(provider);
                 }
             }
+            providers = never_synced;
         }
 
-        if reindex {
-            eprint!("Reindexing FTS5 tables...");
+        let sync_futures: Vec<_> = providers
+            .into_iter()
+            .map(|registered| {
+                let provider_name = registered.info.name.clone();
+                let provider = registered.provider.clone();
+                let request_clone = request.clone();
+                let db_clone = db.clone();
+                let status_clone = status.clone();
+                async move {
+                    let result = provider
+                        .lock()
+                        .await
+                        .refresh(&db_clone, request_clone, status_clone)
+                        .await;
+                    (provider_name, result)
+                }
+            })
+            .collect();
+
+        let report = NGLSyncReport {
+            providers: join_all(sync_futures)
+                .await
+                .into_iter()
+                .map(|(provider_name, result)| NGLProviderSyncResult {
+                    provider_name,
+                    status: result.unwrap_or_else(|error| NGLSyncStatus::Failed { error }),
+                })
+                .collect(),
+        };
+
+        if report.any_synced() {
+            let _ = status.send(StatusEvent::Reindexing);
             crate::db::services::populate_fts5(db).await?;
         }
 
-        if !errors.is_empty() {
-            eprintln!("\nSync errors:");
-            for err in &errors {
-                eprintln!("  {}", err);
-            }
+        Ok(report)
+    }
+}
+
+/// A provider synced on the schedule from the config rather than its own.
+struct Rescheduled {
+    provider: Box<dyn Provider + Send>,
+    sync_interval_hours: u32,
+}
+
+impl Rescheduled {
+    fn wrap(
+        provider: Box<dyn Provider + Send>,
+        sync_interval_hours: u32,
+    ) -> Box<dyn Provider + Send> {
+        Box::new(Self {
+            provider,
+            sync_interval_hours,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for Rescheduled {
+    fn get_info(&self) -> ProviderInformation {
+        ProviderInformation {
+            sync_interval_hours: Some(self.sync_interval_hours),
+            ..self.provider.get_info()
         }
+    }
 
-        Ok(())
+    async fn sync(
+        &mut self,
+        channel: &EventChannel,
+        kinds: &[NGLDataKind],
+    ) -> Result<(), NglError> {
+        self.provider.sync(channel, kinds).await
     }
 }
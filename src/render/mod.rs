@@ -0,0 +1,245 @@
+// Markdown rendering of hydrated entries, for the frontends that show docs to people
+// (hover, previews, --output markdown) rather than handing JSON to a program.
+mod nix;
+mod terminal;
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::{
+    schema::{NGLData, NGLDataVariant, NGLRaw},
+    utils::html_to_markdown,
+};
+
+pub use terminal::terminal;
+
+/// The name an entry is known by: function/option/package/type name, guide title, or the
+/// first line of an example.
+pub fn title(data: &NGLData) -> String {
+    match &data.data {
+        NGLDataVariant::Function(f) => f.name.clone(),
+        NGLDataVariant::Option(o) => o.name.clone(),
+        NGLDataVariant::Package(p) => p.name.clone(),
+        NGLDataVariant::Type(t) => t.name.clone(),
+        NGLDataVariant::Guide(g) => raw_text(&g.title),
+        NGLDataVariant::Example(e) => e.code.lines().next().unwrap_or_default().to_string(),
+    }
+}
+
+pub fn markdown(data: &NGLData) -> String {
+    let mut out = String::new();
+    match &data.data {
+        NGLDataVariant::Function(f) => {
+            out.push_str(&format!("### {}\n\n", f.name));
+            if let Some(signature) = &f.signature {
+                out.push_str(&format!("```\n{}\n```\n\n", signature));
+            }
+            out.push_str(&raw_to_markdown(&f.content));
+            if let Some(aliases) = f.aliases.as_ref().filter(|a| !a.is_empty()) {
+                out.push_str(&format!("\n\n**Aliases:** {}", code_list(aliases)));
+            }
+            links(
+                &mut out,
+                &[
+                    ("Documentation", f.source_url.as_deref()),
+                    ("Source", f.source_code_url.as_deref()),
+                ],
+            );
+        }
+        NGLDataVariant::Option(o) => {
+            out.push_str(&format!("### {}\n\n", o.name));
+            if let Some(option_type) = &o.option_type {
+                out.push_str(&format!("**Type:** `{}`\n\n", option_type));
+            }
+            if let Some(default) = &o.default_value {
+                out.push_str(&format!("**Default:** `{}`\n\n", default));
+            }
+            if let Some(description) = &o.description {
+                out.push_str(&option_description(description));
+            }
+            if let Some(example) = &o.example {
+                out.push_str(&format!("\n\n**Example:**\n\n```nix\n{}\n```", example));
+            }
+            links(&mut out, &[("Documentation", o.source_url.as_deref())]);
+        }
+        NGLDataVariant::Package(p) => {
+            match &p.version {
+                Some(version) => out.push_str(&format!("### {} {}\n\n", p.name, version)),
+                None => out.push_str(&format!("### {}\n\n", p.name)),
+            }
+            if let Some(description) = &p.description {
+                out.push_str(&format!("{}\n\n", description));
+            }
+            if let Some(license) = &p.license {
+                out.push_str(&format!("**License:** {}\n\n", license));
+            }
+            if p.broken {
+                out.push_str("**Broken**\n\n");
+            }
+            if p.unfree {
+                out.push_str("**Unfree**\n\n");
+            }
+            links(
+                &mut out,
+                &[
+                    ("Homepage", p.homepage.as_deref()),
+                    ("Source", p.source_code_url.as_deref()),
+                ],
+            );
+        }
+        NGLDataVariant::Type(t) => {
+            out.push_str(&format!("### {}\n\n", t.name));
+            if let Some(description) = &t.description {
+                out.push_str(description);
+            }
+        }
+        NGLDataVariant::Guide(g) => {
+            out.push_str(&format!("## {}\n\n", raw_text(&g.title)));
+            out.push_str(&raw_to_markdown(&g.content));
+            if let Some(parent) = &g.parent_guide {
+                out.push_str(&format!(
+                    "\n\n**Part of:** {}",
+                    parent.title.as_deref().unwrap_or("parent guide")
+                ));
+            }
+            if !g.sub_guides.is_empty() {
+                out.push_str("\n\n**Sections:**\n");
+                for sub in &g.sub_guides {
+                    out.push_str(&format!(
+                        "\n- {}",
+                        sub.title.as_deref().unwrap_or("untitled")
+                    ));
+                }
+            }
+            links(&mut out, &[("Link", Some(g.link.as_str()))]);
+        }
+        NGLDataVariant::Example(e) => {
+            out.push_str(&format!(
+                "```{}\n{}\n```",
+                e.language.as_deref().unwrap_or_default().to_lowercase(),
+                e.code.trim_end()
+            ));
+            links(&mut out, &[("From", e.source_link.as_deref())]);
+        }
+    }
+    out.trim_end().to_string()
+}
+
+pub fn raw_to_markdown(raw: &NGLRaw) -> String {
+    match raw {
+        NGLRaw::Markdown(s) | NGLRaw::PlainText(s) => s.clone(),
+        NGLRaw::HTML(s) => html_to_markdown(s),
+    }
+}
+
+fn raw_text(raw: &NGLRaw) -> String {
+    match raw {
+        NGLRaw::Markdown(s) | NGLRaw::PlainText(s) | NGLRaw::HTML(s) => s.clone(),
+    }
+}
+
+/// The options.json template stores the whole option entry, everything else stores markdown.
+fn option_description(data: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(data)
+        .ok()
+        .and_then(|entry| {
+            entry.get("description").map(|d| match d {
+                serde_json::Value::String(s) => s.clone(),
+                other => other
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        })
+        .unwrap_or_else(|| data.to_string())
+}
+
+fn code_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("`{}`", item))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn links(out: &mut String, links: &[(&str, Option<&str>)]) {
+    let links: Vec<String> = links
+        .iter()
+        .filter_map(|(label, url)| url.map(|url| format!("[{}]({})", label, url)))
+        .collect();
+    if !links.is_empty() {
+        out.push_str(&format!("\n\n{}", links.join(" · ")));
+    }
+}
+
+/// One line about an entry for listings: the start of its description or content.
+pub fn summary(data: &NGLData) -> String {
+    let text = match &data.data {
+        NGLDataVariant::Function(f) => raw_to_markdown(&f.content),
+        NGLDataVariant::Option(o) => o
+            .description
+            .as_deref()
+            .map(option_description)
+            .unwrap_or_default(),
+        NGLDataVariant::Package(p) => p.description.clone().unwrap_or_default(),
+        NGLDataVariant::Type(t) => t.description.clone().unwrap_or_default(),
+        NGLDataVariant::Guide(g) => raw_to_markdown(&g.content),
+        NGLDataVariant::Example(e) => return e.code.lines().next().unwrap_or_default().into(),
+    };
+    plain(&text)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Markdown with the markup taken out: links keep their target, code blocks are kept as is.
+pub fn plain(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut links = Vec::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(text) | Event::Code(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                out.push_str(&text)
+            }
+            Event::SoftBreak => out.push(' '),
+            Event::HardBreak => out.push('\n'),
+            Event::Rule => out.push_str("\n\n"),
+            Event::Start(Tag::Item) => out.push_str("- "),
+            Event::Start(Tag::Link { dest_url, .. }) => links.push(dest_url),
+            Event::End(TagEnd::Link) => {
+                if let Some(url) = links.pop()
+                    && !out.ends_with(url.as_ref())
+                {
+                    out.push_str(&format!(" <{}>", url));
+                }
+            }
+            Event::End(TagEnd::Item) if !out.ends_with('\n') => out.push('\n'),
+            Event::End(
+                TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::List(_),
+            ) => {
+                if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    out.trim_end().to_string()
+}
+
+/// `text` cut down to `width` columns, ending in `tail` when something was cut off. Styled
+/// text keeps its escapes. Unlike `console::truncate_str` alone, text that fits is left whole.
+pub fn fit(text: &str, width: usize, tail: &str) -> String {
+    if console::measure_text_width(text) <= width {
+        return text.to_string();
+    }
+    let tail = if console::measure_text_width(tail) <= width {
+        tail
+    } else {
+        ""
+    };
+    console::truncate_str(text, width, tail).into_owned()
+}
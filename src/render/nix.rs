@@ -0,0 +1,175 @@
+use console::style;
+
+const KEYWORDS: [&str; 11] = [
+    "let", "in", "with", "rec", "inherit", "if", "then", "else", "assert", "or", "import",
+];
+const CONSTANTS: [&str; 3] = ["true", "false", "null"];
+
+/// Where a line starts when the previous one left a comment or string open.
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Code,
+    Comment,
+    IndentedString,
+}
+
+/// Nix source colored per line: keywords, strings, comments, numbers, paths and the
+/// attribute names being defined. It's a tokenizer, not a parser, and errs on the side of
+/// leaving things uncolored.
+pub fn highlight(code: &str) -> Vec<String> {
+    let mut state = State::Code;
+    code.lines()
+        .map(|line| {
+            let (out, next) = highlight_line(line, state);
+            state = next;
+            out
+        })
+        .collect()
+}
+
+fn highlight_line(line: &str, mut state: State) -> (String, State) {
+    let chars: Vec<char> = line.chars().collect();
+    let text = |from: usize, to: usize| chars[from..to].iter().collect::<String>();
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match state {
+            State::Comment => {
+                let end = find(&chars, i, "*/").map(|e| e + 2);
+                out.push_str(&style(text(i, end.unwrap_or(chars.len()))).dim().to_string());
+                match end {
+                    Some(end) => {
+                        i = end;
+                        state = State::Code;
+                    }
+                    None => i = chars.len(),
+                }
+            }
+            State::IndentedString => {
+                let end = indented_string_end(&chars, i);
+                out.push_str(
+                    &style(text(i, end.unwrap_or(chars.len())))
+                        .green()
+                        .to_string(),
+                );
+                match end {
+                    Some(end) => {
+                        i = end;
+                        state = State::Code;
+                    }
+                    None => i = chars.len(),
+                }
+            }
+            State::Code => {
+                let c = chars[i];
+                let next = chars.get(i + 1).copied();
+                if c == '#' {
+                    out.push_str(&style(text(i, chars.len())).dim().to_string());
+                    i = chars.len();
+                } else if c == '/' && next == Some('*') {
+                    out.push_str(&style("/*").dim().to_string());
+                    i += 2;
+                    state = State::Comment;
+                } else if c == '\'' && next == Some('\'') {
+                    out.push_str(&style("''").green().to_string());
+                    i += 2;
+                    state = State::IndentedString;
+                } else if c == '"' {
+                    let mut end = i + 1;
+                    while end < chars.len() && chars[end] != '"' {
+                        end += if chars[end] == '\\' { 2 } else { 1 };
+                    }
+                    let end = (end + 1).min(chars.len());
+                    out.push_str(&style(text(i, end)).green().to_string());
+                    i = end;
+                } else if c.is_ascii_digit() {
+                    let end = scan(&chars, i, |c| c.is_ascii_digit() || c == '.');
+                    out.push_str(&style(text(i, end)).yellow().to_string());
+                    i = end;
+                } else if c == '<' && next.is_some_and(|n| n.is_ascii_alphabetic()) {
+                    let end = scan(&chars, i + 1, is_path_char);
+                    if chars.get(end) == Some(&'>') {
+                        out.push_str(&style(text(i, end + 1)).cyan().to_string());
+                        i = end + 1;
+                    } else {
+                        out.push(c);
+                        i += 1;
+                    }
+                } else if chars[i..].starts_with(&['.', '/'])
+                    || chars[i..].starts_with(&['.', '.', '/'])
+                    || chars[i..].starts_with(&['~', '/'])
+                {
+                    let end = scan(&chars, i, is_path_char);
+                    out.push_str(&style(text(i, end)).cyan().to_string());
+                    i = end;
+                } else if c.is_alphabetic() || c == '_' {
+                    let end = scan(&chars, i, |c| {
+                        c.is_alphanumeric() || matches!(c, '_' | '\'' | '-')
+                    });
+                    let word = text(i, end);
+                    let rest = chars[end..].iter().collect::<String>();
+                    let rest = rest.trim_start();
+                    let defined = rest.starts_with('=') && !rest.starts_with("==");
+                    out.push_str(&if KEYWORDS.contains(&word.as_str()) {
+                        style(word).magenta().to_string()
+                    } else if CONSTANTS.contains(&word.as_str()) {
+                        style(word).yellow().to_string()
+                    } else if defined || rest.starts_with('.') && is_attr_path(rest) {
+                        style(word).blue().to_string()
+                    } else {
+                        word
+                    });
+                    i = end;
+                } else {
+                    out.push(c);
+                    i += 1;
+                }
+            }
+        }
+    }
+    (out, state)
+}
+
+/// True for the rest of an attribute path being defined, like `.enable = true;`.
+fn is_attr_path(rest: &str) -> bool {
+    let path: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '\'' | '-' | '.' | '"'))
+        .collect();
+    let after = rest[path.len()..].trim_start();
+    after.starts_with('=') && !after.starts_with("==")
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | '/' | '_' | '-' | '+' | '~')
+}
+
+fn scan(chars: &[char], from: usize, keep: impl Fn(char) -> bool) -> usize {
+    chars[from..]
+        .iter()
+        .position(|c| !keep(*c))
+        .map_or(chars.len(), |p| from + p)
+}
+
+fn find(chars: &[char], from: usize, pattern: &str) -> Option<usize> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    (from..chars.len()).find(|&i| chars[i..].starts_with(&pattern))
+}
+
+/// End of an indented string, skipping its escapes (`'''`, `''$` and `''\`).
+fn indented_string_end(chars: &[char], from: usize) -> Option<usize> {
+    let mut i = from;
+    while i + 1 < chars.len() {
+        if chars[i] == '\'' && chars[i + 1] == '\'' {
+            match chars.get(i + 2) {
+                Some('\'') | Some('$') => i += 3,
+                Some('\\') => i += 4,
+                _ => return Some(i + 2),
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
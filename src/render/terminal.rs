@@ -0,0 +1,344 @@
+use console::{Color, Style, measure_text_width, style};
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use super::{fit, nix};
+
+/// Markdown laid out for a terminal `width` columns wide, styled with ANSI escapes: bold
+/// headings, emphasis, colored inline code, highlighted Nix code blocks, lists and quotes.
+/// Text is word wrapped, code is cut off instead. Every styled word is closed on its own, so
+/// a line can be printed next to anything without its style leaking.
+pub fn terminal(markdown: &str, width: usize) -> Vec<String> {
+    let mut renderer = Renderer {
+        width: width.max(1),
+        ..Default::default()
+    };
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(markdown, options) {
+        renderer.event(event);
+    }
+    renderer.flush();
+    while renderer.lines.last().is_some_and(|line| line.is_empty()) {
+        renderer.lines.pop();
+    }
+    renderer.lines
+}
+
+enum Word {
+    Text(String),
+    Space,
+    Break,
+}
+
+#[derive(Default)]
+struct Renderer {
+    width: usize,
+    lines: Vec<String>,
+    /// Inline content of the block being built.
+    words: Vec<Word>,
+    styles: Vec<Inline>,
+    /// Line prefixes of the enclosing lists and quotes.
+    indent: Vec<String>,
+    /// Marker replacing the innermost indent on the first line of a list item.
+    marker: Option<String>,
+    /// Next number of each enclosing list, None for bullets.
+    lists: Vec<Option<u64>>,
+    links: Vec<String>,
+    /// Language and text of the code block being read.
+    code: Option<(String, String)>,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event) {
+        if let Some((_, code)) = &mut self.code {
+            match event {
+                Event::Text(text) => code.push_str(&text),
+                Event::End(TagEnd::CodeBlock) => self.code_block(),
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => self.text(&text),
+            Event::Code(text) => {
+                self.styles.push(Inline {
+                    color: Some(Color::Yellow),
+                    ..Default::default()
+                });
+                self.text(&text);
+                self.styles.pop();
+            }
+            Event::SoftBreak => self.words.push(Word::Space),
+            Event::HardBreak => self.words.push(Word::Break),
+            Event::Rule => {
+                self.flush();
+                let rule = "─".repeat(self.width.min(40));
+                self.lines.push(style(rule).dim().to_string());
+                self.blank();
+            }
+            Event::TaskListMarker(done) => self.text(if done { "[x] " } else { "[ ] " }),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.styles.push(Inline {
+                    bold: true,
+                    underlined: matches!(level, HeadingLevel::H1 | HeadingLevel::H2),
+                    ..Default::default()
+                });
+            }
+            Tag::Emphasis => self.styles.push(Inline {
+                italic: true,
+                ..Default::default()
+            }),
+            Tag::Strong => self.styles.push(Inline {
+                bold: true,
+                ..Default::default()
+            }),
+            Tag::Strikethrough => self.styles.push(Inline {
+                strikethrough: true,
+                ..Default::default()
+            }),
+            Tag::Link { dest_url, .. } => {
+                self.styles.push(Inline {
+                    underlined: true,
+                    color: Some(Color::Blue),
+                    ..Default::default()
+                });
+                self.links.push(dest_url.to_string());
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split([' ', ','])
+                        .next()
+                        .unwrap_or_default()
+                        .to_lowercase(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.indent.push(" ".repeat(measure_text_width(&marker)));
+                self.marker = Some(marker);
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.indent.push(style("│ ").dim().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.flush();
+                self.blank();
+            }
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.flush();
+                self.blank();
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::Link => {
+                self.styles.pop();
+                // Relative links mean nothing outside the page they came from.
+                if let Some(url) = self.links.pop()
+                    && url.contains("://")
+                {
+                    self.words.push(Word::Space);
+                    self.words
+                        .push(Word::Text(style(format!("<{}>", url)).dim().to_string()));
+                }
+            }
+            TagEnd::Item => {
+                self.flush();
+                self.indent.pop();
+                self.marker = None;
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.indent.pop();
+                self.blank();
+            }
+            TagEnd::TableCell => self.text(" │ "),
+            TagEnd::TableHead | TagEnd::TableRow => self.flush(),
+            TagEnd::Table => self.blank(),
+            _ => {}
+        }
+    }
+
+    /// Adds `text` as words in the current style, keeping the spaces around it.
+    fn text(&mut self, text: &str) {
+        let style = self
+            .styles
+            .iter()
+            .fold(Inline::default(), |below, layer| layer.over(below))
+            .style();
+        if text.starts_with(char::is_whitespace) {
+            self.words.push(Word::Space);
+        }
+        for (i, word) in text.split_whitespace().enumerate() {
+            if i > 0 {
+                self.words.push(Word::Space);
+            }
+            self.words
+                .push(Word::Text(style.apply_to(word).to_string()));
+        }
+        if text.ends_with(char::is_whitespace) && !text.trim().is_empty() {
+            self.words.push(Word::Space);
+        }
+    }
+
+    /// Prefixes for the first and the following lines of the current block.
+    fn prefixes(&mut self) -> (String, String) {
+        let rest = self.indent.concat();
+        let first = match self.marker.take() {
+            Some(marker) => {
+                let outer = self.indent[..self.indent.len().saturating_sub(1)].concat();
+                format!("{}{}", outer, style(marker).dim())
+            }
+            None => rest.clone(),
+        };
+        (first, rest)
+    }
+
+    /// Wraps the pending words into lines.
+    fn flush(&mut self) {
+        if self.words.iter().all(|w| matches!(w, Word::Space)) {
+            self.words.clear();
+            return;
+        }
+        let (first, rest) = self.prefixes();
+        let mut line = first;
+        let mut line_empty = true;
+        let mut pending_space = false;
+
+        for word in std::mem::take(&mut self.words) {
+            match word {
+                Word::Space => pending_space = !line_empty,
+                Word::Break => {
+                    self.lines.push(std::mem::replace(&mut line, rest.clone()));
+                    line_empty = true;
+                    pending_space = false;
+                }
+                Word::Text(text) => {
+                    let needed = measure_text_width(&text) + usize::from(pending_space);
+                    if !line_empty && measure_text_width(&line) + needed > self.width {
+                        self.lines.push(std::mem::replace(&mut line, rest.clone()));
+                        pending_space = false;
+                    }
+                    if pending_space {
+                        line.push(' ');
+                    }
+                    line.push_str(&text);
+                    line_empty = false;
+                    pending_space = false;
+                }
+            }
+        }
+        // A single word longer than the line still has to fit.
+        self.lines.push(fit(&line, self.width, "…"));
+    }
+
+    fn code_block(&mut self) {
+        let Some((language, code)) = self.code.take() else {
+            return;
+        };
+        let rest = format!("{}  ", self.indent.concat());
+        let available = self.width.saturating_sub(measure_text_width(&rest));
+        // Nix manuals leave most of their examples untagged.
+        let lines = if language.is_empty() || language == "nix" {
+            nix::highlight(code.trim_end_matches('\n'))
+        } else {
+            code.trim_end_matches('\n')
+                .lines()
+                .map(|line| style(line).cyan().to_string())
+                .collect()
+        };
+        for line in lines {
+            self.lines
+                .push(format!("{}{}", rest, fit(&line, available, "…")));
+        }
+        self.blank();
+    }
+
+    fn blank(&mut self) {
+        if self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+}
+
+/// One layer of inline styling; nested layers add up, e.g. a link inside bold text.
+#[derive(Default, Clone, Copy)]
+struct Inline {
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    strikethrough: bool,
+    color: Option<Color>,
+}
+
+impl Inline {
+    fn over(self, below: Inline) -> Inline {
+        Inline {
+            bold: self.bold || below.bold,
+            italic: self.italic || below.italic,
+            underlined: self.underlined || below.underlined,
+            strikethrough: self.strikethrough || below.strikethrough,
+            color: self.color.or(below.color),
+        }
+    }
+
+    fn style(self) -> Style {
+        let mut style = Style::new();
+        if self.bold {
+            style = style.bold();
+        }
+        if self.italic {
+            style = style.italic();
+        }
+        if self.underlined {
+            style = style.underlined();
+        }
+        if self.strikethrough {
+            style = style.strikethrough();
+        }
+        if let Some(color) = self.color {
+            style = style.fg(color);
+        }
+        style
+    }
+}
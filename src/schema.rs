@@ -1,29 +1,191 @@
+use chrono::{DateTime, Utc};
 use sea_orm::{DeriveActiveEnum, EnumIter};
 // Base schema defining the language of NGL data structure
 // Defines components of an NGLRequest and an NGLResponse
 use serde::{Deserialize, Serialize};
 
+use crate::error::NglError;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct NGLRequest {
     pub search_term: Option<String>,
     // TODO: We could probably make this an enum?
     pub providers: Option<Vec<String>>,
     pub kinds: Option<Vec<NGLDataKind>>,
+    /// How the provider cache is treated before answering, defaults to syncing
+    /// anything that is missing or past its `sync_interval_hours`.
+    #[serde(default)]
+    pub sync_mode: NGLSyncMode,
+    /// What to do when another process is already syncing a provider.
+    #[serde(default)]
+    pub on_locked: NGLLockPolicy,
+}
+
+impl NGLRequest {
+    /// The kinds asked for, every kind if the request leaves them out.
+    pub fn requested_kinds(&self) -> Vec<NGLDataKind> {
+        self.kinds.clone().unwrap_or_else(NGLDataKind::all)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+pub enum NGLLockPolicy {
+    /// Wait for the other sync to finish, then only sync what is still expired.
+    /// Gives up and skips after the lease length (five minutes).
+    #[default]
+    Wait,
+    /// Don't sync the provider, answer from the data it currently has.
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum NGLSyncMode {
+    /// Sync providers whose cache is missing or expired.
+    #[default]
+    Auto,
+    /// Never touch the network, answer from whatever is already in the database.
+    Offline,
+    /// Sync every requested provider regardless of cache age.
+    Refresh,
+    /// Answer from expired data right away and refresh it in the background.
+    /// Providers that have never been synced are still synced first.
+    StaleWhileRevalidate,
+}
+
+/// Everything a query hands back: the matches, plus which providers the
+/// answer might be incomplete for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+#[allow(unused)]
+pub struct NGLQueryResult {
+    pub responses: Vec<NGLResponse>,
+    /// Requested providers whose data is missing or past its sync interval.
+    pub stale_providers: Vec<NGLProviderFreshness>,
+    /// Outcome of the sync that ran before answering, empty when offline.
+    pub sync_report: NGLSyncReport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+pub struct NGLProviderFreshness {
+    pub provider_name: String,
+    pub state: NGLFreshnessState,
+    /// Oldest sync time across the requested kinds, None if never synced.
+    pub last_synced: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+pub enum NGLFreshnessState {
+    Fresh,
+    Stale,
+    NeverSynced,
+}
+
+/// A recorded attempt at syncing one kind of data from a provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NGLSyncRun {
+    pub provider_name: String,
+    pub kind: NGLDataKind,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: i64,
+    /// Rows inserted for this kind, partial counts are kept for failed runs.
+    pub rows: i64,
+    /// Upstream revision the data came from, if the provider knows it
+    /// (e.g. the nixpkgs release or noogle's upstream rev).
+    pub source_revision: Option<String>,
+    pub outcome: NGLSyncOutcome,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, DeriveActiveEnum, EnumIter)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum NGLSyncOutcome {
+    #[sea_orm(string_value = "success")]
+    Success,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+/// What happened to each provider during a sync.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+pub struct NGLSyncReport {
+    pub providers: Vec<NGLProviderSyncResult>,
+}
+
+impl NGLSyncReport {
+    /// Providers whose sync failed, with the reason.
+    pub fn failures(&self) -> impl Iterator<Item = (&str, &NglError)> {
+        self.providers.iter().filter_map(|p| match &p.status {
+            NGLSyncStatus::Failed { error } => Some((p.provider_name.as_str(), error)),
+            _ => None,
+        })
+    }
+
+    pub fn any_synced(&self) -> bool {
+        self.providers
+            .iter()
+            .any(|p| p.status == NGLSyncStatus::Synced)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+pub struct NGLProviderSyncResult {
+    pub provider_name: String,
+    pub status: NGLSyncStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum NGLSyncStatus {
+    /// New data was fetched and stored.
+    Synced,
+    /// Cached data was still within the provider's sync interval.
+    Fresh,
+    /// The sync was left to someone else, e.g. another process holding the lease.
+    Skipped {
+        reason: String,
+    },
+    Failed {
+        error: NglError,
+    },
+}
+
+/// A single row of the search index, enough to list a match and fetch it by id later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NGLSearchHit {
+    pub kind: NGLDataKind,
+    pub provider_name: String,
+    /// Id of the entity within its kind, the same one `ngl_search` stores.
+    pub id: i32,
+    /// Name or title, examples use the start of their code.
+    pub name: String,
 }
 
 /// The data coming back from the crate
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct NGLResponse {
     pub provider_name: String,
     pub matches: Vec<NGLData>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct NGLData {
     pub data: NGLDataVariant,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub enum NGLDataVariant {
     Function(FunctionData),
     Example(ExampleData),
@@ -34,6 +196,7 @@ pub enum NGLDataVariant {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct FunctionData {
     /// The name of the function.
     pub name: String,
@@ -50,6 +213,7 @@ pub struct FunctionData {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct GuideRef {
     pub id: i32,
     pub link: Option<String>,
@@ -57,6 +221,7 @@ pub struct GuideRef {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct ExampleData {
     /// Code block parsed as plaintext with formatting preserved
     pub code: String,
@@ -69,6 +234,7 @@ pub struct ExampleData {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct GuideData {
     pub parent_guide: Option<GuideRef>,
     /// Sub-guides that are children of this guide
@@ -86,15 +252,19 @@ pub struct GuideData {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct OptionData {
     pub name: String,
     pub option_type: Option<String>,
     pub default_value: Option<String>,
     pub description: Option<String>,
     pub example: Option<String>,
+    /// URL to the option's documentation, usually an anchor on the source page.
+    pub source_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct PackageData {
     pub name: String,
     pub version: Option<String>,
@@ -107,6 +277,7 @@ pub struct PackageData {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 pub struct TypeData {
     pub name: String,
     pub description: Option<String>,
@@ -115,6 +286,7 @@ pub struct TypeData {
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, DeriveActiveEnum, EnumIter,
 )]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
 #[sea_orm(rs_type = "i32", db_type = "Integer")]
 pub enum NGLDataKind {
     #[sea_orm(num_value = 0)]
@@ -142,13 +314,29 @@ pub enum NGLDataKind {
 // It's important to understand that it's possible for datatypes to nest other types
 // this separation just makes it easier for the consumer to see the primary format of the text
 // without analysis.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "serve", derive(schemars::JsonSchema))]
+#[allow(clippy::upper_case_acronyms)]
 pub enum NGLRaw {
     Markdown(String),
     HTML(String),
     PlainText(String),
 }
 
+impl NGLDataKind {
+    /// Every kind, used when a request doesn't narrow them down.
+    pub fn all() -> Vec<NGLDataKind> {
+        vec![
+            NGLDataKind::Function,
+            NGLDataKind::Package,
+            NGLDataKind::Option,
+            NGLDataKind::Example,
+            NGLDataKind::Guide,
+            NGLDataKind::Type,
+        ]
+    }
+}
+
 impl NGLDataVariant {
     /// Returns the kind of this [`NGLDataVariant`].
     #[allow(unused)]
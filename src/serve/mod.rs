@@ -0,0 +1,319 @@
+pub mod openapi;
+
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    header,
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use tokio::{net::TcpListener, sync::broadcast};
+
+use crate::{
+    db::services::{fetch, lookup},
+    error::NglError,
+    providers::StatusEvent,
+    query,
+    registry::ProviderRegistry,
+    schema::{NGLDataKind, NGLLockPolicy, NGLRequest, NGLSyncMode},
+    utils,
+};
+
+/// What every request handler gets to work with, loaded once at startup.
+struct ServerState {
+    db: DatabaseConnection,
+    registry: ProviderRegistry,
+    status: broadcast::Sender<StatusEvent>,
+}
+
+/// A failed request, rendered as `{"error": NglError}`.
+struct ApiError {
+    status: StatusCode,
+    error: NglError,
+}
+
+impl From<NglError> for ApiError {
+    fn from(error: NglError) -> Self {
+        let status = match error {
+            NglError::UnknownProvider { .. } | NglError::Config(_) | NglError::Parse(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self { status, error }
+    }
+}
+
+impl From<sea_orm::DbErr> for ApiError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        NglError::from(e).into()
+    }
+}
+
+fn bad_request(msg: impl Into<String>) -> ApiError {
+    ApiError {
+        status: StatusCode::BAD_REQUEST,
+        error: NglError::Parse(msg.into()),
+    }
+}
+
+type ApiResult = Result<Response<Full<Bytes>>, ApiError>;
+
+/// Serves the HTTP API on `addr` until interrupted.
+pub async fn serve(
+    db: DatabaseConnection,
+    registry: ProviderRegistry,
+    addr: SocketAddr,
+    status: broadcast::Sender<StatusEvent>,
+) -> Result<(), NglError> {
+    let listener = TcpListener::bind(addr).await?;
+    let state = Arc::new(ServerState {
+        db,
+        registry,
+        status,
+    });
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let state = state.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(state.clone(), req));
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<ServerState>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let response = route(&state, req)
+        .await
+        .unwrap_or_else(|e| json(e.status, &serde_json::json!({ "error": e.error })));
+    Ok(response)
+}
+
+async fn route(state: &ServerState, req: Request<Incoming>) -> ApiResult {
+    let params = QueryParams::parse(req.uri().query());
+    let method = req.method().clone();
+    let path = req.uri().path().trim_end_matches('/').to_string();
+
+    let response = match (req.method().clone(), path.as_str()) {
+        (Method::OPTIONS, _) => Ok(empty(StatusCode::NO_CONTENT)),
+        (Method::GET, "/openapi.json") => Ok(json(StatusCode::OK, &openapi::document())),
+        // Any origin can call this one, so it answers from the database and leaves syncing to
+        // the `POST` routes.
+        (Method::GET, "/search") => {
+            if params.get("sync_mode").is_some() {
+                return Err(bad_request(
+                    "GET /search doesn't sync, send sync_mode to POST /query instead",
+                ));
+            }
+            let request = NGLRequest {
+                search_term: Some(params.require("q")?.to_string()),
+                providers: params.list("providers"),
+                kinds: params.kinds()?,
+                sync_mode: NGLSyncMode::Offline,
+                on_locked: NGLLockPolicy::default(),
+            };
+            search(state, request).await
+        }
+        (Method::POST, "/query") => {
+            let mut request: NGLRequest = read_json(req).await?;
+            if request.sync_mode == NGLSyncMode::Auto {
+                request.sync_mode = utils::default_sync_mode();
+            }
+            search(state, request).await
+        }
+        (Method::GET, "/lookup") => {
+            let kind = parse_kind(params.require("kind")?)?;
+            let providers = params.list("providers");
+            let responses = lookup(
+                &state.db,
+                kind,
+                params.require("name")?,
+                providers.as_deref(),
+            )
+            .await?;
+            Ok(json(StatusCode::OK, &responses))
+        }
+        (Method::GET, "/providers") => Ok(json(StatusCode::OK, &state.registry.list().await)),
+        (Method::POST, "/sync") => {
+            let request: NGLRequest = read_json(req).await?;
+            sync(state, request).await
+        }
+        (Method::GET, guide) if guide.starts_with("/guides/") => {
+            let id: i32 = guide["/guides/".len()..]
+                .parse()
+                .map_err(|_| bad_request("guide id must be a number"))?;
+            match fetch(&state.db, NGLDataKind::Guide, id).await? {
+                Some(data) => Ok(json(StatusCode::OK, &data)),
+                None => Ok(empty(StatusCode::NOT_FOUND)),
+            }
+        }
+        _ => Ok(empty(StatusCode::NOT_FOUND)),
+    };
+    Ok(allow_cross_origin(response?, &method))
+}
+
+async fn search(state: &ServerState, request: NGLRequest) -> ApiResult {
+    let result = query::answer(&state.db, request, &state.registry, &state.status).await?;
+    Ok(json(StatusCode::OK, &result))
+}
+
+async fn sync(state: &ServerState, request: NGLRequest) -> ApiResult {
+    let report = state
+        .registry
+        .sync(&state.db, request, &state.status)
+        .await?;
+    Ok(json(StatusCode::OK, &report))
+}
+
+/// Far more than any `NGLRequest` needs.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+async fn read_body(req: Request<Incoming>) -> Result<Bytes, ApiError> {
+    match Limited::new(req.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+    {
+        Ok(body) => Ok(body.to_bytes()),
+        Err(e) if e.is::<LengthLimitError>() => Err(ApiError {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            error: NglError::Parse(format!(
+                "request body is larger than {} bytes",
+                MAX_BODY_BYTES
+            )),
+        }),
+        Err(e) => Err(bad_request(e.to_string())),
+    }
+}
+
+/// Reads a JSON body. Other content types are refused: browsers send those cross origin
+/// without asking first, JSON needs a preflight, which only allows `GET`.
+async fn read_json<T: serde::de::DeserializeOwned>(req: Request<Incoming>) -> Result<T, ApiError> {
+    let is_json = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim_start().starts_with("application/json"));
+    if !is_json {
+        return Err(ApiError {
+            status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            error: NglError::Parse("expected a Content-Type: application/json body".to_string()),
+        });
+    }
+    serde_json::from_slice(&read_body(req).await?).map_err(|e| bad_request(e.to_string()))
+}
+
+fn json<T: Serialize>(status: StatusCode, body: &T) -> Response<Full<Bytes>> {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    let mut response = empty(status);
+    *response.body_mut() = Full::new(Bytes::from(body));
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn empty(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::new()));
+    *response.status_mut() = status;
+    response
+}
+
+/// Lets a docs page or browser extension on any origin read the `GET` routes. The `POST`
+/// routes can start syncs, so preflights only allow `GET` and pages can't call them.
+fn allow_cross_origin(
+    mut response: Response<Full<Bytes>>,
+    method: &Method,
+) -> Response<Full<Bytes>> {
+    if method != Method::GET && method != Method::OPTIONS {
+        return response;
+    }
+    let headers = response.headers_mut();
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        header::HeaderValue::from_static("*"),
+    );
+    if method == Method::OPTIONS {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            header::HeaderValue::from_static("GET"),
+        );
+    }
+    response
+}
+
+fn parse_kind(value: &str) -> Result<NGLDataKind, ApiError> {
+    NGLDataKind::all()
+        .into_iter()
+        .find(|kind| format!("{:?}", kind).eq_ignore_ascii_case(value.trim()))
+        .ok_or_else(|| bad_request(format!("unknown kind: {}", value)))
+}
+
+struct QueryParams(Vec<(String, String)>);
+
+impl QueryParams {
+    fn parse(query: Option<&str>) -> Self {
+        let decode = |s: &str| {
+            urlencoding::decode(&s.replace('+', " "))
+                .map(|s| s.into_owned())
+                .unwrap_or_default()
+        };
+        Self(
+            query
+                .unwrap_or_default()
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    (decode(key), decode(value))
+                })
+                .collect(),
+        )
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn require(&self, key: &str) -> Result<&str, ApiError> {
+        self.get(key)
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| bad_request(format!("missing query parameter: {}", key)))
+    }
+
+    fn list(&self, key: &str) -> Option<Vec<String>> {
+        self.get(key).map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        })
+    }
+
+    fn kinds(&self) -> Result<Option<Vec<NGLDataKind>>, ApiError> {
+        self.list("kinds")
+            .map(|kinds| kinds.iter().map(|k| parse_kind(k)).collect())
+            .transpose()
+    }
+}
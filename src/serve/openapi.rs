@@ -0,0 +1,152 @@
+use schemars::{JsonSchema, SchemaGenerator, generate::SchemaSettings};
+use serde_json::{Value, json};
+
+use crate::{
+    error::NglError,
+    registry::RegisteredProvider,
+    schema::{NGLData, NGLQueryResult, NGLRequest, NGLResponse, NGLSyncReport},
+};
+
+/// Schemas derived from the API types, collected under `components/schemas` as the document
+/// refers to them.
+struct Schemas(SchemaGenerator);
+
+impl Schemas {
+    fn new() -> Self {
+        let mut settings = SchemaSettings::draft2020_12();
+        settings.definitions_path = "/components/schemas".into();
+        Self(settings.into_generator())
+    }
+
+    /// A `$ref` to `T`'s schema.
+    fn reference<T: JsonSchema>(&mut self) -> Value {
+        self.0.subschema_for::<T>().into()
+    }
+
+    fn json_response<T: JsonSchema>(&mut self, description: &str) -> Value {
+        json!({
+            "description": description,
+            "content": { "application/json": { "schema": self.reference::<T>() } }
+        })
+    }
+
+    fn into_components(mut self) -> Value {
+        Value::Object(self.0.take_definitions(true))
+    }
+}
+
+fn query_param(name: &str, description: &str, required: bool) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": required,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+/// The OpenAPI 3.1 document served at `/openapi.json`.
+pub fn document() -> Value {
+    let mut schemas = Schemas::new();
+    let error = json!({ "$ref": "#/components/responses/Error" });
+    let results = schemas.json_response::<Vec<NGLResponse>>("Matches grouped by provider");
+    let search = schemas.json_response::<NGLQueryResult>("Search results");
+    let request = schemas.reference::<NGLRequest>();
+    let guide = schemas.json_response::<NGLData>("The guide");
+    let providers = schemas.json_response::<Vec<RegisteredProvider>>(
+        "Every registered provider, disabled ones included",
+    );
+    let sync = schemas.json_response::<NGLSyncReport>("What happened to each provider");
+    let error_schema = schemas.reference::<NglError>();
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "NGL",
+            "description": "Nix Global Lookup",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/search": {
+                "get": {
+                    "summary": "Full text search over the data already synced",
+                    "parameters": [
+                        query_param("q", "Search term", true),
+                        query_param("kinds", "Comma separated kinds, defaults to all", false),
+                        query_param("providers", "Comma separated provider names", false),
+                    ],
+                    "responses": { "200": search.clone(), "default": error },
+                }
+            },
+            "/query": {
+                "post": {
+                    "summary": "Answer a full NGLRequest",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": request.clone() } }
+                    },
+                    "responses": { "200": search, "default": error },
+                }
+            },
+            "/lookup": {
+                "get": {
+                    "summary": "Entries of one kind with exactly this name",
+                    "parameters": [
+                        query_param("kind", "function, option, package, type or guide", true),
+                        query_param("name", "Exact name, or a guide's title or link", true),
+                        query_param("providers", "Comma separated provider names", false),
+                    ],
+                    "responses": { "200": results, "default": error },
+                }
+            },
+            "/guides/{id}": {
+                "get": {
+                    "summary": "A guide with its parent and sub-guides, for navigating guide trees",
+                    "parameters": [{
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "integer" },
+                    }],
+                    "responses": {
+                        "200": guide,
+                        "404": { "description": "No guide with that id" },
+                        "default": error,
+                    },
+                }
+            },
+            "/providers": {
+                "get": {
+                    "summary": "Registered providers",
+                    "responses": {
+                        "200": providers,
+                        "default": error,
+                    },
+                }
+            },
+            "/sync": {
+                "post": {
+                    "summary": "Sync providers without querying, kinds default to all",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": request } }
+                    },
+                    "responses": { "200": sync, "default": error },
+                }
+            },
+        },
+        "components": {
+            "responses": {
+                "Error": {
+                    "description": "The request failed",
+                    "content": { "application/json": { "schema": {
+                        "type": "object",
+                        "required": ["error"],
+                        "properties": { "error": error_schema },
+                    } } }
+                }
+            },
+            "schemas": schemas.into_components(),
+        },
+    })
+}
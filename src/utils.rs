@@ -1,19 +1,24 @@
-use crate::db::enums::language::Language;
+use crate::{
+    config::NetworkSettings,
+    error::NglError,
+    providers::{EventChannel, Language, SyncPhase},
+    schema::NGLSyncMode,
+};
 use regex::Regex;
+use std::{sync::OnceLock, time::Duration};
 
 pub struct ExtractedExample {
     pub language: Option<Language>,
     pub data: String,
 }
 
-#[allow(unused)]
 pub fn extract_examples_html(content: &str) -> Vec<ExtractedExample> {
     let re =
         Regex::new(r#"<pre[^>]*>\s*<code[^>]*class="([^"]*)"[^>]*>([\s\S]*?)</code>\s*</pre>"#)
             .unwrap();
     let mut examples = Vec::new();
 
-    for capture in re.captures_iter(content).into_iter() {
+    for capture in re.captures_iter(content) {
         let class_str = capture.get(1).map(|m| m.as_str()).unwrap_or("");
         let code = capture.get(2).map(|m| m.as_str()).unwrap_or("");
 
@@ -72,26 +77,129 @@ pub fn is_url(source: &str) -> bool {
     source.starts_with("http://") || source.starts_with("https://")
 }
 
-pub async fn fetch_source(
-    source: &str,
-) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+static NETWORK: OnceLock<NetworkSettings> = OnceLock::new();
+
+/// Network settings from the config for every download made afterwards.
+/// Only the first call counts, later ones are ignored.
+pub fn configure_network(settings: NetworkSettings) {
+    let _ = NETWORK.set(settings);
+}
+
+fn network() -> &'static NetworkSettings {
+    NETWORK.get_or_init(NetworkSettings::default)
+}
+
+/// How a query syncs when it doesn't ask for a mode: not at all if the config says `offline`.
+pub fn default_sync_mode() -> NGLSyncMode {
+    if network().offline == Some(true) {
+        NGLSyncMode::Offline
+    } else {
+        NGLSyncMode::Auto
+    }
+}
+
+/// Attempts per download.
+pub fn retries() -> u32 {
+    network().retries.unwrap_or(3).max(1)
+}
+
+/// The user agent sent with every request.
+pub fn user_agent() -> &'static str {
+    network()
+        .user_agent
+        .as_deref()
+        .unwrap_or("NGL/0.1 (Nix Global Lookup)")
+}
+
+/// HTTP client for downloads, with the configured user agent, proxy and timeout.
+/// The timeout applies to connecting and to each read, so big downloads aren't cut short.
+pub fn http_client() -> Result<reqwest::Client, NglError> {
+    let network = network();
+    let timeout = Duration::from_secs(network.timeout_secs.unwrap_or(60));
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent())
+        .connect_timeout(timeout)
+        .read_timeout(timeout);
+    if let Some(proxy) = &network.proxy {
+        builder = builder.proxy(
+            reqwest::Proxy::all(proxy)
+                .map_err(|e| NglError::Config(format!("proxy {}: {}", proxy, e)))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+/// Download progress is reported roughly this often, rather than per chunk.
+const DOWNLOAD_REPORT_BYTES: u64 = 256 * 1024;
+/// Most a body's buffer is sized up front from `Content-Length`, which the server could
+/// make up. Bodies larger than this still grow as they arrive.
+const MAX_PREALLOCATED_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Reads a response body, reporting download progress on `channel` as it goes.
+pub async fn read_body(
+    mut resp: reqwest::Response,
+    channel: &EventChannel,
+) -> Result<Vec<u8>, NglError> {
+    let total_bytes = resp.content_length();
+    let mut body =
+        Vec::with_capacity(total_bytes.unwrap_or(0).min(MAX_PREALLOCATED_BYTES) as usize);
+    let mut reported = 0;
+
+    channel.phase(SyncPhase::Downloading);
+    channel.download_progress(0, total_bytes);
+    while let Some(chunk) = resp.chunk().await? {
+        body.extend_from_slice(&chunk);
+        let bytes = body.len() as u64;
+        if bytes - reported >= DOWNLOAD_REPORT_BYTES {
+            channel.download_progress(bytes, total_bytes);
+            reported = bytes;
+        }
+    }
+    channel.download_progress(body.len() as u64, total_bytes);
+
+    Ok(body)
+}
+
+/// The encoding named by the response's Content-Type charset, UTF-8 if it names none we know.
+/// Decoding replaces malformed bytes rather than failing, and a byte order mark wins over both.
+fn charset(resp: &reqwest::Response) -> &'static encoding_rs::Encoding {
+    resp.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value.split(';').skip(1).find_map(|param| {
+                let (name, label) = param.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("charset")
+                    .then(|| label.trim().trim_matches('"'))
+            })
+        })
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8)
+}
+
+/// Fetches a URL or reads a local file. Downloads report their progress on `channel`.
+pub async fn fetch_source(source: &str, channel: &EventChannel) -> Result<String, NglError> {
     if is_url(source) {
-        let client = reqwest::Client::builder()
-            .user_agent("NGL/0.1 (Nix Global Lookup)")
-            .timeout(std::time::Duration::from_secs(60))
-            .build()?;
+        let client = http_client()?;
 
         let mut last_error = None;
-        for attempt in 0..3 {
+        for attempt in 0..retries() {
             if attempt > 0 {
-                tokio::time::sleep(std::time::Duration::from_secs(2u64.pow(attempt))).await;
+                tokio::time::sleep(Duration::from_secs(2u64.pow(attempt.min(6)))).await;
             }
             match client.get(source).send().await {
                 Ok(resp) => {
                     if !resp.status().is_success() {
-                        return Err(format!("HTTP error: {}", resp.status()).into());
+                        return Err(NglError::Network(format!(
+                            "HTTP error {} fetching {}",
+                            resp.status(),
+                            source
+                        )));
                     }
-                    return Ok(resp.text().await?);
+                    let encoding = charset(&resp);
+                    let body = read_body(resp, channel).await?;
+                    return Ok(encoding.decode(&body).0.into_owned());
                 }
                 Err(e) => {
                     last_error = Some(e);